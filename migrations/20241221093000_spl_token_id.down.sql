@@ -0,0 +1,17 @@
+-- Add down migration script here
+CREATE TABLE spl_token_old (
+    mint TEXT PRIMARY KEY, -- mint address
+    smart_address TEXT NOT NULL, -- smart address, who related to this token
+    monitor_status TEXT NOT NULL DEFAULT 'active', -- monitor status
+    strategy_name TEXT NOT NULL, -- strategy name
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, -- created at
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP -- updated at
+);
+
+INSERT OR IGNORE INTO spl_token_old (mint, smart_address, monitor_status, strategy_name, created_at, updated_at)
+SELECT mint, smart_address, monitor_status, strategy_name,
+    datetime(created_at, 'unixepoch'), datetime(updated_at, 'unixepoch')
+FROM spl_token;
+
+DROP TABLE spl_token;
+ALTER TABLE spl_token_old RENAME TO spl_token;
@@ -0,0 +1,24 @@
+-- Add up migration script here
+
+-- spl_token was keyed by mint, so only one smart address could hold a token,
+-- and the timestamps were text while the rows are read as unix seconds
+CREATE TABLE spl_token_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    mint TEXT NOT NULL, -- mint address
+    smart_address TEXT NOT NULL, -- smart address, who related to this token
+    monitor_status TEXT NOT NULL DEFAULT 'active', -- monitor status
+    strategy_name TEXT NOT NULL, -- strategy name
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')), -- created at
+    updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')) -- updated at
+);
+
+INSERT INTO spl_token_new (mint, smart_address, monitor_status, strategy_name, created_at, updated_at)
+SELECT mint, smart_address, monitor_status, strategy_name,
+    COALESCE(strftime('%s', created_at), strftime('%s', 'now')),
+    COALESCE(strftime('%s', updated_at), strftime('%s', 'now'))
+FROM spl_token;
+
+DROP TABLE spl_token;
+ALTER TABLE spl_token_new RENAME TO spl_token;
+
+CREATE INDEX idx_spl_token_smart_address ON spl_token (smart_address, strategy_name, monitor_status);
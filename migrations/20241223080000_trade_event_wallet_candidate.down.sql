@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE wallet_candidate;
+DROP TABLE trade_event;
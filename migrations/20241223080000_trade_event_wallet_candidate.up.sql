@@ -0,0 +1,32 @@
+-- Add up migration script here
+
+-- every pump.fun trade seen by the recorder, amounts in lamports and raw token units
+CREATE TABLE trade_event (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    signature TEXT NOT NULL, -- transaction signature
+    mint TEXT NOT NULL, -- mint address
+    user TEXT NOT NULL, -- trader
+    is_buy INTEGER NOT NULL, -- 1 buy, 0 sell
+    sol_amount INTEGER NOT NULL,
+    token_amount INTEGER NOT NULL,
+    timestamp INTEGER NOT NULL, -- block time of the trade
+    virtual_sol_reserves INTEGER NOT NULL,
+    virtual_token_reserves INTEGER NOT NULL,
+    real_sol_reserves INTEGER NOT NULL,
+    real_token_reserves INTEGER NOT NULL,
+    UNIQUE (signature, mint, user, is_buy, token_amount)
+);
+
+CREATE INDEX idx_trade_event_mint ON trade_event (mint, timestamp);
+CREATE INDEX idx_trade_event_user ON trade_event (user, timestamp);
+
+-- wallets proposed by the discovery job, monitored once approved
+CREATE TABLE wallet_candidate (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    address TEXT NOT NULL UNIQUE, -- wallet address
+    early_count INTEGER NOT NULL, -- winners the wallet was an early buyer of
+    mints TEXT NOT NULL, -- those winners, comma separated
+    status TEXT NOT NULL DEFAULT 'pending', -- pending, approved or rejected
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')), -- created at
+    updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')) -- updated at
+);
@@ -0,0 +1,16 @@
+-- Add up migration script here
+
+-- performance of a wallet over the trades of the last window_secs, pnl in SOL
+CREATE TABLE wallet_score (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    wallet TEXT NOT NULL, -- wallet address
+    window_secs INTEGER NOT NULL, -- scored window
+    realized_pnl REAL NOT NULL,
+    unrealized_pnl REAL NOT NULL, -- holdings at the last recorded price
+    win_rate REAL, -- percentage of sold tokens with a realized profit, null without sells
+    median_roi REAL, -- median percentage return per token, null without buys
+    avg_hold_secs REAL, -- from the first buy to the last sell, null without sells
+    trade_count INTEGER NOT NULL,
+    computed_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+    UNIQUE (wallet, window_secs)
+);
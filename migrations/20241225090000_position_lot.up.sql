@@ -0,0 +1,18 @@
+-- Add up migration script here
+
+-- every buy and sell of a followed position, amounts in lamports and raw token units
+CREATE TABLE position_lot (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    wallet TEXT NOT NULL, -- wallet address
+    mint TEXT NOT NULL, -- mint address
+    signature TEXT NOT NULL, -- transaction signature
+    is_buy INTEGER NOT NULL, -- 1 buy, 0 sell
+    sol_amount INTEGER NOT NULL, -- SOL into or out of the bonding curve
+    token_amount INTEGER NOT NULL,
+    pump_fee INTEGER NOT NULL, -- pump.fun fee on top of a buy, taken from a sell
+    network_fee INTEGER NOT NULL, -- transaction fee paid by the wallet
+    timestamp INTEGER NOT NULL, -- block time of the trade
+    UNIQUE (signature, wallet, mint, is_buy, token_amount)
+);
+
+CREATE INDEX idx_position_lot_wallet ON position_lot (wallet, mint, timestamp);
@@ -0,0 +1,16 @@
+-- Add up migration script here
+
+-- exit rule state of a followed position, so a restart keeps the high-water mark
+CREATE TABLE position_watermark (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    wallet TEXT NOT NULL, -- followed wallet
+    mint TEXT NOT NULL, -- mint address
+    strategy_name TEXT NOT NULL, -- strategy name
+    entry_price REAL NOT NULL, -- the wallet's average entry price in SOL
+    high_price REAL NOT NULL, -- highest price since the entry
+    take_profit_fired INTEGER NOT NULL DEFAULT 0,
+    stop_loss_fired INTEGER NOT NULL DEFAULT 0,
+    trailing_stop_fired INTEGER NOT NULL DEFAULT 0, -- rearmed by a new high
+    updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')), -- updated at
+    UNIQUE (wallet, mint, strategy_name)
+);
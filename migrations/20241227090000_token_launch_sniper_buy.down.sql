@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE sniper_buy;
+DROP TABLE token_launch;
@@ -0,0 +1,25 @@
+-- Add up migration script here
+
+-- pump.fun token launches seen by the sniper detector
+CREATE TABLE token_launch (
+    mint TEXT PRIMARY KEY NOT NULL, -- mint address
+    creator TEXT NOT NULL, -- user of the CreateEvent
+    slot INTEGER NOT NULL, -- creation slot
+    signature TEXT NOT NULL, -- creation transaction
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')) -- created at
+);
+
+-- buys landing within a few slots of the launch
+CREATE TABLE sniper_buy (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    mint TEXT NOT NULL, -- mint address
+    signature TEXT NOT NULL, -- transaction signature
+    user TEXT NOT NULL, -- buyer
+    slot INTEGER NOT NULL, -- slot of the buy
+    slot_offset INTEGER NOT NULL, -- slots after the creation slot
+    sol_amount INTEGER NOT NULL, -- lamports
+    token_amount INTEGER NOT NULL, -- raw token units
+    funder TEXT, -- payer of the buyer's first transaction, NULL when unknown
+    UNIQUE (signature, user, mint)
+);
+CREATE INDEX idx_sniper_buy_mint ON sniper_buy (mint);
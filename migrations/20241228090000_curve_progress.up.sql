@@ -0,0 +1,9 @@
+-- Add up migration script here
+
+-- bonding curve progress alerted per mint, so a restart doesn't repeat the alerts
+CREATE TABLE curve_progress (
+    mint TEXT PRIMARY KEY NOT NULL, -- mint address
+    threshold REAL NOT NULL DEFAULT 0, -- highest progress threshold alerted, percent
+    completed INTEGER NOT NULL DEFAULT 0, -- the curve completed and the token migrates
+    updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')) -- updated at
+);
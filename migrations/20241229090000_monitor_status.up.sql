@@ -0,0 +1,13 @@
+-- Add up migration script here
+
+-- state of the supervised monitor tasks of the running daemon
+CREATE TABLE monitor_status (
+    address TEXT NOT NULL, -- monitored address
+    rule_type TEXT NOT NULL, -- strategy name
+    target TEXT NOT NULL, -- wallet or mint
+    state TEXT NOT NULL, -- running, restarting, failed or stopped
+    restarts INTEGER NOT NULL DEFAULT 0, -- restarts since the monitor last ran healthy
+    last_error TEXT, -- error or panic of the last failed run
+    updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')), -- updated at
+    PRIMARY KEY (address, rule_type, target)
+);
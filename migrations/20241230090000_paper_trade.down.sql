@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE paper_fill;
+DROP TABLE paper_position;
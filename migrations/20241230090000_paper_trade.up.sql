@@ -0,0 +1,36 @@
+-- Add up migration script here
+
+-- copy trades of the followed wallets on paper, one position per wallet and mint while open
+CREATE TABLE paper_position (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    wallet TEXT NOT NULL, -- followed wallet
+    mint TEXT NOT NULL, -- token mint
+    token_amount INTEGER NOT NULL, -- tokens held, raw units
+    cost INTEGER NOT NULL, -- lamports paid for the holding, fees included
+    realized_pnl INTEGER NOT NULL DEFAULT 0, -- lamports, proceeds of the sells minus their cost
+    entry_price REAL NOT NULL, -- average fill price in SOL
+    high_price REAL NOT NULL, -- highest price since the entry
+    status TEXT NOT NULL, -- open or closed
+    opened_at INTEGER NOT NULL, -- time of the opening fill
+    closed_at INTEGER -- time of the closing fill
+);
+
+CREATE INDEX idx_paper_position_wallet ON paper_position (wallet, status);
+
+-- the fills of the paper positions, the pnl history
+CREATE TABLE paper_fill (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    position_id INTEGER NOT NULL, -- paper_position id
+    signature TEXT NOT NULL, -- the transaction the fill followed
+    is_buy BOOLEAN NOT NULL,
+    sol_amount INTEGER NOT NULL, -- lamports paid or received, fee included
+    token_amount INTEGER NOT NULL, -- raw units
+    fee INTEGER NOT NULL, -- pump.fun fee, lamports
+    price REAL NOT NULL, -- average fill price in SOL
+    slippage REAL NOT NULL, -- percent from the price before the fill
+    reason TEXT NOT NULL, -- copy, or the exit rule of the sell
+    realized_pnl INTEGER, -- lamports realized by a sell
+    timestamp INTEGER NOT NULL -- time of the followed trade
+);
+
+CREATE INDEX idx_paper_fill_position ON paper_fill (position_id);
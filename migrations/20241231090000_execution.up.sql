@@ -0,0 +1,37 @@
+-- Add up migration script here
+
+-- positions of the copy trade execution, one per mint while open
+CREATE TABLE execution_position (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    wallet TEXT NOT NULL, -- followed wallet that opened it
+    mint TEXT NOT NULL, -- token mint
+    token_amount INTEGER NOT NULL DEFAULT 0, -- tokens held, raw units
+    cost INTEGER NOT NULL DEFAULT 0, -- lamports paid for the holding, fees included
+    realized_pnl INTEGER NOT NULL DEFAULT 0, -- lamports
+    dry_run BOOLEAN NOT NULL, -- only logged, nothing was sent
+    status TEXT NOT NULL, -- pending until the opening buy confirms, then open, closed or failed
+    opened_at INTEGER NOT NULL, -- time of the opening order
+    closed_at INTEGER -- time of the closing order
+);
+
+CREATE INDEX idx_execution_position_status ON execution_position (status, dry_run);
+
+-- the orders of the execution positions
+CREATE TABLE execution_order (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    position_id INTEGER NOT NULL, -- execution_position id
+    is_buy BOOLEAN NOT NULL,
+    token_amount INTEGER NOT NULL, -- raw units, quoted until the fill is confirmed
+    sol_amount INTEGER NOT NULL, -- lamports paid or received, fee included, quoted until the fill is confirmed
+    sol_limit INTEGER NOT NULL, -- max_sol_cost of a buy, min_sol_output of a sell
+    signature TEXT NOT NULL, -- signature of the signed transaction
+    last_valid_block_height INTEGER NOT NULL DEFAULT 0, -- the transaction can't land after this block
+    status TEXT NOT NULL, -- dry_run, sent, confirmed or failed
+    reason TEXT NOT NULL, -- copy, or the exit rule of a sell
+    realized_pnl INTEGER, -- lamports realized by a sell once applied
+    error TEXT, -- why the order failed
+    created_at INTEGER NOT NULL, -- time the order was sent
+    updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')) -- updated at
+);
+
+CREATE INDEX idx_execution_order_position ON execution_order (position_id);
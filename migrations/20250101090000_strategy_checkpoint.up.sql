@@ -0,0 +1,12 @@
+-- Add up migration script here
+
+-- in-memory state of a monitor saved when it stops, taken back by its next run
+CREATE TABLE strategy_checkpoint (
+    address TEXT NOT NULL, -- monitored address, or the consensus group name
+    rule_type TEXT NOT NULL, -- strategy name
+    target TEXT NOT NULL, -- wallet, mint or group
+    name TEXT NOT NULL, -- the saved part, e.g. the strategy or its cooldown
+    state TEXT NOT NULL, -- json
+    updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')), -- updated at
+    PRIMARY KEY (address, rule_type, target, name)
+);
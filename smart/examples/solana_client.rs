@@ -5,10 +5,10 @@ use std::env;
 use anyhow::Result;
 use smart::{
     sol_client::client::SolanaMonitor,
-    strategies::{MonitorCondition, MonitorRule, MonitorRuleType},
+    strategies::{MonitorCondition, MonitorRule, BUY_STRATEGY},
 };
-use solana_client::rpc_response::RpcLogsResponse;
-use tracing::{debug, error, info};
+use solana_client::rpc_response::{Response, RpcLogsResponse};
+use tracing::info;
 use utils::log::init_tracing;
 
 #[tokio::main]
@@ -17,7 +17,8 @@ async fn main() -> Result<()> {
     init_tracing();
     let mr = MonitorRule {
         address: "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x".to_string(),
-        rule_type: MonitorRuleType::Buy,
+        rule_type: BUY_STRATEGY.to_string(),
+        target: Default::default(),
         conditions: MonitorCondition {
             price_below: Some(1.0),
             price_above: Some(2.0),
@@ -25,12 +26,15 @@ async fn main() -> Result<()> {
             is_first_sell: Some(true),
             partial_sell: Some(true),
             holding_percentage: Some(4.0),
+            when: None,
         },
+        config: Default::default(),
+        cooldown: None,
     };
     let wss = env::var("WSS_SOLANA_URL")?;
     let rpc = env::var("RPC_SOLANA_URL")?;
 
-    let (sender, mut receiver) = tokio::sync::mpsc::channel::<RpcLogsResponse>(1000);
+    let (sender, mut receiver) = tokio::sync::mpsc::channel::<Response<RpcLogsResponse>>(1000);
 
     // start monitoring in a new task
     tokio::spawn(async move {
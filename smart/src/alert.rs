@@ -0,0 +1,395 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::sol_client::{jupiter::WSOL_MINT, ui_amount, PUMP_TOKEN_DECIMALS, SOL_DECIMALS};
+
+pub type AlertSender = mpsc::Sender<Alert>;
+pub type AlertReceiver = mpsc::Receiver<Alert>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub address: String,   // 监控地址
+    pub rule: String,      // 触发的规则
+    pub mint: String,      // token mint
+    pub signature: String, // 交易签名
+    pub kind: AlertKind,   // 告警内容
+    pub suppressed: u32,   // similar alerts held back by the rule's cooldown since the last one
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum AlertKind {
+    Buy {
+        sol_amount: u64,   // SOL spent, lamports
+        token_amount: u64, // tokens received, raw units
+        price: f64,        // price in SOL after the buy
+    },
+    Sell {
+        sol_amount: u64,         // SOL received, lamports
+        token_amount: u64,       // tokens sold, raw units
+        price: f64,              // price in SOL after the sell
+        first_sell: bool,        // first sell of the position
+        partial: bool,           // still holding after the sell
+        holding_percentage: f64, // remaining holding percentage
+    },
+    Profit {
+        profit_percentage: f64, // unrealized profit percentage
+        price: f64,             // current price in SOL
+        entry_price: f64,       // average entry price in SOL
+        holding: u64,           // tokens held, raw units
+    },
+    Consensus {
+        wallets: Vec<ConsensusWallet>, // 参与的钱包, in the order they bought
+        window_secs: u64,              // the buys happened within this window
+    },
+    Exit {
+        reason: ExitReason,     // 触发的退出规则
+        price: f64,             // current price in SOL
+        entry_price: f64,       // the wallet's average entry price in SOL
+        high_price: f64,        // highest price since the entry
+        change_percentage: f64, // price change from the entry
+    },
+    DevSell {
+        sol_amount: u64,              // SOL received, lamports
+        token_amount: u64,            // tokens sold, raw units
+        price: f64,                   // price in SOL after the sell
+        sold_percentage: Option<f64>, // part of the creator's holding sold, None when unknown
+        remaining: Option<u64>,       // tokens the creator still holds, raw units
+    },
+    Graduation {
+        threshold: f64,         // progress threshold crossed, percent
+        progress: f64,          // percent of the curve supply sold
+        real_sol_reserves: u64, // SOL in the curve, lamports
+        price: f64,             // price in SOL after the trade
+    },
+    Migration {
+        bonding_curve: String, // completed curve, the liquidity moves to raydium
+    },
+    Momentum {
+        metric: SpikeMetric, // 突增的指标
+        value: f64,          // the metric in the current window
+        baseline: f64,       // the metric's average over the earlier windows
+        window_secs: u64,
+        buy_sol: u64,  // SOL bought in the current window, lamports
+        sell_sol: u64, // SOL sold in the current window, lamports
+        buyers: usize, // unique buyers in the current window
+    },
+    Threshold {
+        price: f64,      // price in SOL after the trade
+        market_cap: f64, // market cap in SOL after the trade
+    },
+    PaperFill {
+        is_buy: bool,
+        sol_amount: u64,           // SOL paid or received, fee included, lamports
+        token_amount: u64,         // raw units
+        price: f64,                // average fill price in SOL
+        slippage: f64,             // percent from the price before the fill
+        exit: Option<ExitReason>,  // the exit rule of a sell, None when following the wallet
+        realized_pnl: Option<i64>, // lamports realized by a sell
+    },
+    Swap {
+        input_mint: String,
+        input_amount: u64, // raw units, lamports for SOL
+        output_mint: String,
+        output_amount: u64, // raw units, lamports for SOL
+        amms: Vec<String>,  // amm programs of the jupiter route
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ExitReason {
+    TakeProfit,
+    StopLoss,
+    TrailingStop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SpikeMetric {
+    BuyVolume,  // SOL
+    SellVolume, // SOL
+    UniqueBuyers,
+    BuySellRatio, // SOL bought per SOL sold
+}
+
+/// the buys of one wallet of a consensus group in the window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConsensusWallet {
+    pub wallet: String,
+    pub sol_amount: u64,   // SOL spent, lamports
+    pub token_amount: u64, // tokens received, raw units
+    pub entry_price: f64,  // average price of the buys in SOL
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {} ", self.rule, self.address)?;
+        match &self.kind {
+            AlertKind::Buy {
+                sol_amount,
+                token_amount,
+                price,
+            } => write!(
+                f,
+                "bought {} of {} for {} SOL at {:.10} SOL",
+                ui_amount(*token_amount, PUMP_TOKEN_DECIMALS),
+                self.mint,
+                ui_amount(*sol_amount, SOL_DECIMALS),
+                price
+            )?,
+            AlertKind::Sell {
+                sol_amount,
+                token_amount,
+                price,
+                first_sell,
+                partial,
+                holding_percentage,
+            } => write!(
+                f,
+                "{} {} of {} for {} SOL at {:.10} SOL, {}holding {:.2}%",
+                if *partial {
+                    "partially sold"
+                } else {
+                    "sold all"
+                },
+                ui_amount(*token_amount, PUMP_TOKEN_DECIMALS),
+                self.mint,
+                ui_amount(*sol_amount, SOL_DECIMALS),
+                price,
+                if *first_sell { "first sell, " } else { "" },
+                holding_percentage
+            )?,
+            AlertKind::Profit {
+                profit_percentage,
+                price,
+                entry_price,
+                holding,
+            } => write!(
+                f,
+                "holds {} of {} at {:.2}% profit, price {:.10} SOL, entry {:.10} SOL",
+                ui_amount(*holding, PUMP_TOKEN_DECIMALS),
+                self.mint,
+                profit_percentage,
+                price,
+                entry_price
+            )?,
+            AlertKind::Consensus {
+                wallets,
+                window_secs,
+            } => {
+                write!(
+                    f,
+                    "{} wallets bought {} within {}s:",
+                    wallets.len(),
+                    self.mint,
+                    window_secs
+                )?;
+                for w in wallets {
+                    write!(
+                        f,
+                        " {} {} SOL at {:.10} SOL;",
+                        w.wallet,
+                        ui_amount(w.sol_amount, SOL_DECIMALS),
+                        w.entry_price
+                    )?;
+                }
+            }
+            AlertKind::Exit {
+                reason,
+                price,
+                entry_price,
+                high_price,
+                change_percentage,
+            } => write!(
+                f,
+                "{:?} on {}, price {:.10} SOL, {:.2}% from entry {:.10} SOL, high {:.10} SOL",
+                reason, self.mint, price, change_percentage, entry_price, high_price
+            )?,
+            AlertKind::DevSell {
+                sol_amount,
+                token_amount,
+                price,
+                sold_percentage,
+                remaining,
+            } => {
+                write!(
+                    f,
+                    "creator sold {}",
+                    ui_amount(*token_amount, PUMP_TOKEN_DECIMALS)
+                )?;
+                if let Some(p) = sold_percentage {
+                    write!(f, " ({:.2}% of the holding)", p)?;
+                }
+                write!(
+                    f,
+                    " of {} for {} SOL at {:.10} SOL",
+                    self.mint,
+                    ui_amount(*sol_amount, SOL_DECIMALS),
+                    price
+                )?;
+                match remaining {
+                    Some(r) => write!(f, ", {} left", ui_amount(*r, PUMP_TOKEN_DECIMALS))?,
+                    None => write!(f, ", holding unknown")?,
+                }
+            }
+            AlertKind::Graduation {
+                threshold,
+                progress,
+                real_sol_reserves,
+                price,
+            } => write!(
+                f,
+                "{} bonding curve passed {}%, at {:.2}% with {} SOL, price {:.10} SOL",
+                self.mint,
+                threshold,
+                progress,
+                ui_amount(*real_sol_reserves, SOL_DECIMALS),
+                price
+            )?,
+            AlertKind::Momentum {
+                metric,
+                value,
+                baseline,
+                window_secs,
+                buy_sol,
+                sell_sol,
+                buyers,
+            } => write!(
+                f,
+                "{:?} spike on {}: {:.2} against a baseline of {:.2} per {}s, bought {} SOL, sold {} SOL, {} buyers",
+                metric,
+                self.mint,
+                value,
+                baseline,
+                window_secs,
+                ui_amount(*buy_sol, SOL_DECIMALS),
+                ui_amount(*sell_sol, SOL_DECIMALS),
+                buyers
+            )?,
+            AlertKind::Threshold { price, market_cap } => write!(
+                f,
+                "{} passed the threshold at {:.10} SOL, market cap {:.2} SOL",
+                self.mint, price, market_cap
+            )?,
+            AlertKind::PaperFill {
+                is_buy,
+                sol_amount,
+                token_amount,
+                price,
+                slippage,
+                exit,
+                realized_pnl,
+            } => {
+                write!(
+                    f,
+                    "paper {} {} of {} for {} SOL at {:.10} SOL, slippage {:.2}%",
+                    if *is_buy { "bought" } else { "sold" },
+                    ui_amount(*token_amount, PUMP_TOKEN_DECIMALS),
+                    self.mint,
+                    ui_amount(*sol_amount, SOL_DECIMALS),
+                    price,
+                    slippage
+                )?;
+                if let Some(reason) = exit {
+                    write!(f, ", {:?}", reason)?;
+                }
+                if let Some(pnl) = realized_pnl {
+                    write!(f, ", realized {} SOL", *pnl as f64 / 10f64.powi(SOL_DECIMALS as i32))?;
+                }
+            }
+            AlertKind::Swap {
+                input_mint,
+                input_amount,
+                output_mint,
+                output_amount,
+                amms,
+            } => {
+                let amount = |mint: &str, amount: u64| match mint {
+                    WSOL_MINT => format!("{} SOL", ui_amount(amount, SOL_DECIMALS)),
+                    _ => format!("{} of {}", amount, mint),
+                };
+                write!(
+                    f,
+                    "swapped {} for {} through jupiter, {} amms",
+                    amount(input_mint, *input_amount),
+                    amount(output_mint, *output_amount),
+                    amms.len()
+                )?
+            }
+            AlertKind::Migration { bonding_curve } => write!(
+                f,
+                "{} bonding curve {} completed, migrating to raydium",
+                self.mint, bonding_curve
+            )?,
+        }
+        write!(f, ", tx: {}", self.signature)?;
+        if self.suppressed > 0 {
+            write!(f, ", {} similar suppressed", self.suppressed)?;
+        }
+        Ok(())
+    }
+}
+
+impl AlertKind {
+    /// the variant, with the exit reason or the spike metric that tell apart alerts of one rule
+    pub fn name(&self) -> String {
+        match self {
+            AlertKind::Buy { .. } => "Buy".to_string(),
+            AlertKind::Sell { .. } => "Sell".to_string(),
+            AlertKind::Profit { .. } => "Profit".to_string(),
+            AlertKind::Consensus { .. } => "Consensus".to_string(),
+            AlertKind::Exit { reason, .. } => format!("Exit:{:?}", reason),
+            AlertKind::DevSell { .. } => "DevSell".to_string(),
+            AlertKind::Graduation { .. } => "Graduation".to_string(),
+            AlertKind::Migration { .. } => "Migration".to_string(),
+            AlertKind::Momentum { metric, .. } => format!("Momentum:{:?}", metric),
+            AlertKind::Threshold { .. } => "Threshold".to_string(),
+            AlertKind::PaperFill { is_buy: true, .. } => "PaperBuy".to_string(),
+            AlertKind::PaperFill { .. } => "PaperSell".to_string(),
+            AlertKind::Swap { .. } => "Swap".to_string(),
+        }
+    }
+
+    /// SOL traded by the alerted trades, lamports
+    pub fn sol_amount(&self) -> Option<u64> {
+        match self {
+            AlertKind::Buy { sol_amount, .. }
+            | AlertKind::Sell { sol_amount, .. }
+            | AlertKind::DevSell { sol_amount, .. }
+            | AlertKind::PaperFill { sol_amount, .. } => Some(*sol_amount),
+            AlertKind::Consensus { wallets, .. } => {
+                Some(wallets.iter().map(|w| w.sol_amount).sum())
+            }
+            AlertKind::Swap {
+                input_mint,
+                input_amount,
+                output_amount,
+                ..
+            } => Some(if input_mint == WSOL_MINT {
+                *input_amount
+            } else {
+                *output_amount
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub fn alert_channel() -> (AlertSender, AlertReceiver) {
+    mpsc::channel(1000)
+}
+
+/// send an alert to the dispatcher, a full or closed channel only loses this alert
+pub async fn send_alert(sender: &AlertSender, alert: Alert) {
+    if let Err(e) = sender.send(alert).await {
+        warn!("Failed to send alert: {}", e.0);
+    }
+}
+
+/// deliver alerts until every sender is dropped
+pub async fn run_alert_dispatcher(mut receiver: AlertReceiver) {
+    while let Some(alert) = receiver.recv().await {
+        info!(target: "alert", "{}", alert);
+    }
+}
@@ -0,0 +1,311 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::{
+    alert::{alert_channel, Alert, AlertReceiver},
+    models::{ModelsManager, TradeRecord},
+    strategies::{
+        MonitorContext, MonitorRule, Strategy, StrategyContext, StrategyEvent, TICK_INTERVAL,
+    },
+};
+
+/// the forward returns of an alert are measured this many seconds after it
+pub const FORWARD_HORIZONS: [i64; 3] = [60, 300, 3600];
+
+/// the recorded trades are replayed this many at a time
+const TRADE_PAGE_SIZE: i64 = 10_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestAlert {
+    pub alert: Alert,
+    pub timestamp: i64,                   // simulated time the alert was sent
+    pub price: Option<f64>,               // last price of the mint at the alert
+    pub returns: Vec<(i64, Option<f64>)>, // (horizon secs, price change), None when the trades don't reach that far
+}
+
+/// the prices of the alerted mints
+struct PriceSeries {
+    prices: HashMap<String, Vec<(i64, f64)>>, // mint -> (timestamp, price), ascending
+    end: i64,                                 // the trades cover the prices until then
+}
+
+impl PriceSeries {
+    /// load the prices of `mints` in `[from, to]`
+    async fn load(
+        manager: &ModelsManager,
+        mints: HashSet<&str>,
+        from: i64,
+        to: i64,
+    ) -> Result<Self> {
+        let mut prices = HashMap::new();
+        for mint in mints {
+            let trades = manager.get_mint_trades_between(mint, from, to).await?;
+            let series = trades.iter().map(|t| (t.timestamp, t.price())).collect();
+            prices.insert(mint.to_string(), series);
+        }
+        let end = manager.get_last_trade_time(from, to).await?.unwrap_or(0);
+        Ok(Self { prices, end })
+    }
+
+    /// the last price of `mint` at or before `at`
+    fn price_at(&self, mint: &str, at: i64) -> Option<f64> {
+        let prices = self.prices.get(mint)?;
+        let i = prices.partition_point(|(t, _)| *t <= at);
+        (i > 0).then(|| prices[i - 1].1)
+    }
+
+    fn forward_return(&self, mint: &str, at: i64, horizon: i64) -> Option<f64> {
+        if at + horizon > self.end {
+            return None;
+        }
+        let price = self.price_at(mint, at).filter(|p| *p > 0.0)?;
+        Some(self.price_at(mint, at + horizon)? / price - 1.0)
+    }
+}
+
+/// append the recorded trades grouped by transaction, the decoded part a monitor would have seen
+fn push_events(events: &mut Vec<(i64, StrategyEvent)>, trades: &[TradeRecord]) {
+    for t in trades {
+        match events.last_mut() {
+            Some((_, event)) if event.signature == t.signature => {
+                event.trades.push(t.to_trade_event())
+            }
+            _ => events.push((
+                t.timestamp,
+                StrategyEvent {
+                    signature: t.signature.clone(),
+                    trades: vec![t.to_trade_event()],
+                    ..Default::default()
+                },
+            )),
+        }
+    }
+}
+
+fn drain_alerts(receiver: &mut AlertReceiver, now: i64, sent: &mut Vec<(i64, Alert)>) {
+    while let Ok(alert) = receiver.try_recv() {
+        sent.push((now, alert));
+    }
+}
+
+/// a rule being replayed
+struct Replay<'a> {
+    rule: &'a MonitorRule,
+    strategy: Box<dyn Strategy>,
+    sctx: StrategyContext,
+}
+
+impl Replay<'_> {
+    async fn on_tick(&mut self, now: i64) {
+        self.sctx.set_now(now);
+        if let Err(e) = self.strategy.on_tick(&self.sctx).await {
+            error!(
+                "{} on_tick error: {:?}, address: {}",
+                self.rule.rule_type, e, self.rule.address
+            );
+        }
+    }
+
+    async fn on_event(&mut self, timestamp: i64, event: &StrategyEvent) {
+        // what the subscriptions of the monitor would have received
+        let mentioned = event.trades.iter().any(|t| {
+            [&t.user, &t.mint]
+                .into_iter()
+                .any(|a| *a == self.rule.address || self.sctx.is_watching(a))
+        });
+        if !mentioned {
+            return;
+        }
+        self.sctx.set_now(timestamp);
+        if let Err(e) = self.strategy.on_event(&self.sctx, event).await {
+            error!(
+                "{} on_event error: {:?}, address: {}",
+                self.rule.rule_type, e, self.rule.address
+            );
+        }
+    }
+}
+
+/// replay the trades recorded in `ctx.manager` between `from` and `to` through the strategies of `rules`.
+///
+/// the strategies run against an empty in-memory database with the time of the replayed trades,
+/// so wallet scores and positions only come from the replay itself.
+pub async fn run_backtest(
+    ctx: &MonitorContext,
+    rules: &[MonitorRule],
+    from: i64,
+    to: i64,
+) -> Result<Vec<BacktestAlert>> {
+    let (alerts, mut receiver) = alert_channel();
+    let replay = MonitorContext {
+        manager: Arc::new(ModelsManager::open_memory().await?),
+        alerts,
+        // never trade while replaying
+        executor: None,
+        ..ctx.clone()
+    };
+    let mut replays = Vec::new();
+    for rule in rules {
+        let mut strategy = replay.registry.create(rule)?;
+        let sctx = StrategyContext::replay(rule, &replay, from);
+        strategy.init(&sctx).await?;
+        replays.push(Replay {
+            rule,
+            strategy,
+            sctx,
+        });
+    }
+
+    // the trades are paged through in the order they were recorded, the rules see them together
+    let tick = TICK_INTERVAL.as_secs() as i64;
+    let mut next_tick = from + tick;
+    let mut sent = Vec::new();
+    let (mut trades, mut transactions) = (0, 0);
+    let mut after = None;
+    let mut events = Vec::new();
+    loop {
+        let page = ctx
+            .manager
+            .get_trades_between(from, to, after, TRADE_PAGE_SIZE)
+            .await?;
+        let last_page = (page.len() as i64) < TRADE_PAGE_SIZE;
+        after = page.last().map(|t| (t.timestamp, t.id)).or(after);
+        trades += page.len();
+        push_events(&mut events, &page);
+        // the last transaction of the page may go on in the next one
+        let pending = if last_page { None } else { events.pop() };
+        for (timestamp, event) in events.drain(..) {
+            while next_tick <= timestamp {
+                for r in replays.iter_mut() {
+                    r.on_tick(next_tick).await;
+                }
+                drain_alerts(&mut receiver, next_tick, &mut sent);
+                next_tick += tick;
+            }
+            for r in replays.iter_mut() {
+                r.on_event(timestamp, &event).await;
+            }
+            drain_alerts(&mut receiver, timestamp, &mut sent);
+            transactions += 1;
+        }
+        events.extend(pending);
+        if last_page {
+            break;
+        }
+    }
+    for r in replays.iter_mut() {
+        r.sctx.set_now(to);
+        r.strategy.shutdown(&r.sctx).await?;
+    }
+    drain_alerts(&mut receiver, to, &mut sent);
+    replay.manager.close().await;
+    info!(
+        "backtest {} trades, {} transactions, {} rules",
+        trades,
+        transactions,
+        rules.len()
+    );
+
+    // the returns only need the prices of the alerted mints
+    let max_horizon = FORWARD_HORIZONS.iter().max().copied().unwrap_or_default();
+    let mints = sent.iter().map(|(_, a)| a.mint.as_str()).collect();
+    let prices = PriceSeries::load(&ctx.manager, mints, from, to + max_horizon).await?;
+    sent.sort_by_key(|(t, _)| *t);
+    Ok(sent
+        .into_iter()
+        .map(|(timestamp, alert)| BacktestAlert {
+            price: prices.price_at(&alert.mint, timestamp),
+            returns: FORWARD_HORIZONS
+                .iter()
+                .map(|h| (*h, prices.forward_return(&alert.mint, timestamp, *h)))
+                .collect(),
+            timestamp,
+            alert,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alert::AlertKind, sol_client::TradeEventData, strategies::BUY_STRATEGY,
+        test_support::MockSolana,
+    };
+
+    #[tokio::test]
+    async fn test_backtest_forward_returns() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let (sender, _receiver) = alert_channel();
+        let ctx = mock.context(sender).await?;
+        let wallet = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x";
+        let mint = "7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump";
+        let start = 1_734_616_564;
+        // virtual sol reserves of 30 SOL per 1e9 tokens is a price of 3e-8
+        let trade = |user: &str, is_buy: bool, secs: i64, sol: u64| TradeEventData {
+            mint: mint.to_string(),
+            sol_amount: 1_000_000_000,
+            token_amount: 1_000_000,
+            is_buy,
+            user: user.to_string(),
+            timestamp: start + secs,
+            virtual_sol_reserves: sol * 1_000_000_000,
+            virtual_token_reserves: 1_000_000_000 * 1_000_000,
+            real_sol_reserves: 0,
+            real_token_reserves: 0,
+        };
+        for (i, t) in [
+            trade(wallet, true, 0, 30),
+            trade("other", true, 30, 33),
+            trade("other", true, 200, 45),
+            // after the replayed range, only for the returns
+            trade(wallet, true, 150, 40),
+            trade("other", false, 2000, 15),
+        ]
+        .iter()
+        .enumerate()
+        {
+            ctx.manager
+                .add_trade_events(&format!("sig{}", i), std::slice::from_ref(t))
+                .await?;
+        }
+
+        let rule: MonitorRule = toml::from_str(&format!(
+            "address = \"{}\"\nrule_type = \"{}\"",
+            wallet, BUY_STRATEGY
+        ))?;
+        let alerts = run_backtest(&ctx, &[rule], start - 60, start + 100).await?;
+        assert_eq!(alerts.len(), 1);
+        let a = &alerts[0];
+        assert_eq!(a.alert.signature, "sig0");
+        assert_eq!(a.timestamp, start);
+        assert!(matches!(a.alert.kind, AlertKind::Buy { .. }));
+        let close = |r: Option<f64>, expected: f64| r.is_some_and(|r| (r - expected).abs() < 1e-9);
+        assert!(close(a.price, 3e-8));
+        assert_eq!(a.returns[0].0, 60);
+        assert!(close(a.returns[0].1, 0.1));
+        assert!(close(a.returns[1].1, 0.5));
+        // the last trade is before the hour is over
+        assert_eq!(a.returns[2].1, None);
+
+        // the pages go on after the last trade of the previous one
+        let (mut after, mut signatures) = (None, Vec::new());
+        loop {
+            let page = ctx
+                .manager
+                .get_trades_between(start, start + 3600, after, 2)
+                .await?;
+            let Some(last) = page.last() else { break };
+            after = Some((last.timestamp, last.id));
+            signatures.extend(page.into_iter().map(|t| t.signature));
+        }
+        assert_eq!(signatures, ["sig0", "sig1", "sig3", "sig2", "sig4"]);
+        Ok(())
+    }
+}
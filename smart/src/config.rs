@@ -2,11 +2,23 @@ use anyhow::Result;
 use serde::Deserialize;
 use std::{env, str::FromStr, sync::Arc};
 use tokio::{fs, sync::OnceCell};
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
-use crate::strategies::MonitorRule;
+use crate::{
+    dev_watch::DevWatchConfig,
+    discovery::DiscoveryConfig,
+    execution::ExecutionConfig,
+    graduation::GraduationConfig,
+    reload::ReloadConfig,
+    scoring::ScoringConfig,
+    shutdown::ShutdownConfig,
+    sniper::SniperConfig,
+    strategies::{ConsensusRule, MonitorRule, RuleTarget},
+    supervisor::SupervisorConfig,
+};
 
 #[derive(Clone, Debug, Validate, Deserialize)]
+#[validate(schema(function = "validate_config"))]
 pub struct Config {
     #[validate(length(min = 1))]
     pub database_url: String, // database url
@@ -17,7 +29,67 @@ pub struct Config {
     #[validate(length(min = 1))]
     pub solana_wss_url: String, // solana wss url
 
+    #[validate(nested)]
     pub monitors: Vec<MonitorRule>, // monitor rules
+    #[serde(default)]
+    #[validate(nested)]
+    pub consensus: Vec<ConsensusRule>, // 多钱包共识规则
+    #[serde(default)]
+    #[validate(nested)]
+    pub discovery: DiscoveryConfig, // 聪明钱包发现
+    #[serde(default)]
+    #[validate(nested)]
+    pub scoring: ScoringConfig, // 钱包评分
+    #[serde(default)]
+    #[validate(nested)]
+    pub sniper: SniperConfig, // 狙击和捆绑检测
+    #[serde(default)]
+    #[validate(nested)]
+    pub dev_watch: DevWatchConfig, // 创建者卖出告警
+    #[serde(default)]
+    #[validate(nested)]
+    pub graduation: GraduationConfig, // bonding curve 进度告警
+    #[serde(default)]
+    #[validate(nested)]
+    pub reload: ReloadConfig, // 监控规则热加载
+    #[serde(default)]
+    #[validate(nested)]
+    pub supervisor: SupervisorConfig, // 监控任务重启策略
+    #[serde(default)]
+    #[validate(nested)]
+    pub shutdown: ShutdownConfig, // 优雅退出
+    #[serde(default)]
+    #[validate(nested)]
+    pub execution: ExecutionConfig, // 跟单交易
+}
+
+/// the checks across sections
+fn validate_config(c: &Config) -> Result<(), ValidationError> {
+    // the scores are computed from the recorded trades only
+    if c.scoring.interval_secs > 0 && !c.discovery.record_trades {
+        return Err(ValidationError::new("scoring").with_message(
+            "scoring needs discovery.record_trades, it scores the recorded trades".into(),
+        ));
+    }
+    Ok(())
+}
+
+impl Config {
+    /// the wallets of the wallet monitors and the consensus groups
+    pub fn watched_wallets(&self) -> Vec<String> {
+        let mut wallets: Vec<String> = Vec::new();
+        let addresses = self
+            .monitors
+            .iter()
+            .filter(|m| m.target == RuleTarget::Wallet)
+            .map(|m| &m.address);
+        for w in addresses.chain(self.consensus.iter().flat_map(|g| &g.wallets)) {
+            if !wallets.contains(w) {
+                wallets.push(w.clone());
+            }
+        }
+        wallets
+    }
 }
 
 impl FromStr for Config {
@@ -28,6 +100,13 @@ impl FromStr for Config {
     }
 }
 
+/// read, parse and validate the config file
+pub async fn load_config(path: &str) -> Result<Config> {
+    let c: Config = fs::read_to_string(path).await?.parse()?;
+    c.validate()?;
+    Ok(c)
+}
+
 pub static GLOBAL_CONFIG: OnceCell<Arc<Config>> = OnceCell::const_new();
 
 pub async fn get_global_config() -> &'static Arc<Config> {
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use validator::{Validate, ValidationError};
+
+use crate::{
+    alert::Alert,
+    sol_client::{ui_amount, SOL_DECIMALS},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CooldownKey {
+    Address, // the alerted wallet, the trader for a mint rule
+    Mint,
+    Rule,
+    Kind, // the alert variant, with the exit reason or spike metric
+}
+
+/// hold back the alerts of a rule sharing a key for `window_secs` after one was sent
+#[derive(Clone, Debug, PartialEq, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+#[validate(schema(function = "validate_alert_cooldown"))]
+pub struct AlertCooldown {
+    #[validate(range(min = 1))]
+    pub window_secs: u64, // 冷却时间
+    #[serde(default = "default_cooldown_key")]
+    pub key: Vec<CooldownKey>, // the alerts with equal parts share a cooldown
+    #[serde(default)]
+    pub escalate_sol: Vec<f64>, // cumulative SOL of the key, crossing the next one breaks through the cooldown
+}
+
+fn default_cooldown_key() -> Vec<CooldownKey> {
+    vec![CooldownKey::Address, CooldownKey::Mint, CooldownKey::Rule]
+}
+
+fn validate_alert_cooldown(cooldown: &AlertCooldown) -> Result<(), ValidationError> {
+    if cooldown.key.is_empty() {
+        return Err(ValidationError::new("key").with_message("key is empty".into()));
+    }
+    if cooldown
+        .escalate_sol
+        .iter()
+        .any(|s| !s.is_finite() || *s <= 0.0)
+        || cooldown.escalate_sol.windows(2).any(|w| w[0] >= w[1])
+    {
+        return Err(ValidationError::new("escalate_sol")
+            .with_message("escalate_sol is not positive and ascending".into()));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CooldownEntry {
+    last_sent: Option<i64>, // unix seconds
+    suppressed: u32,
+    sol_amount: u64,  // lamports of every alert of the key, sent or not
+    escalated: usize, // escalate_sol thresholds crossed
+}
+
+/// the entries of a throttle, kept over a restart
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ThrottleState(HashMap<String, CooldownEntry>);
+
+/// the cooldown state of one rule
+pub struct AlertThrottle {
+    cooldown: AlertCooldown,
+    entries: HashMap<String, CooldownEntry>,
+}
+
+impl AlertThrottle {
+    pub fn new(cooldown: AlertCooldown) -> Self {
+        Self {
+            cooldown,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn state(&self) -> ThrottleState {
+        ThrottleState(self.entries.clone())
+    }
+
+    pub fn restore(&mut self, state: ThrottleState) {
+        self.entries = state.0;
+    }
+
+    fn key(&self, alert: &Alert) -> String {
+        self.cooldown
+            .key
+            .iter()
+            .map(|k| match k {
+                CooldownKey::Address => alert.address.clone(),
+                CooldownKey::Mint => alert.mint.clone(),
+                CooldownKey::Rule => alert.rule.clone(),
+                CooldownKey::Kind => alert.kind.name(),
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// whether `alert` is sent at `now` unix seconds, a sent alert carries the count suppressed before it
+    pub fn check(&mut self, alert: &mut Alert, now: i64) -> bool {
+        let window = self.cooldown.window_secs as i64;
+        // keys cold for a whole window have nothing left to report
+        self.entries
+            .retain(|_, e| e.suppressed > 0 || e.last_sent.is_none_or(|t| now - t < window));
+        let key = self.key(alert);
+        let entry = self.entries.entry(key.clone()).or_default();
+        entry.sol_amount += alert.kind.sol_amount().unwrap_or_default();
+        let sol = ui_amount(entry.sol_amount, SOL_DECIMALS);
+        let crossed = self
+            .cooldown
+            .escalate_sol
+            .iter()
+            .filter(|t| sol >= **t)
+            .count();
+        let escalated = crossed > entry.escalated;
+        entry.escalated = crossed;
+        let cold = entry.last_sent.is_none_or(|t| now - t >= window);
+        if !cold && !escalated {
+            entry.suppressed += 1;
+            debug!(
+                "alert suppressed, key: {}, suppressed: {}",
+                key, entry.suppressed
+            );
+            return false;
+        }
+        if !cold {
+            debug!(
+                "alert escalated past the cooldown, key: {}, {} SOL",
+                key, sol
+            );
+        }
+        alert.suppressed = entry.suppressed;
+        entry.suppressed = 0;
+        entry.last_sent = Some(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::AlertKind;
+    use anyhow::Result;
+
+    fn buy(address: &str, mint: &str, sol: u64) -> Alert {
+        Alert {
+            address: address.to_string(),
+            rule: "Buy".to_string(),
+            mint: mint.to_string(),
+            signature: "sig".to_string(),
+            kind: AlertKind::Buy {
+                sol_amount: sol * 1_000_000_000,
+                token_amount: 1,
+                price: 1.0,
+            },
+            suppressed: 0,
+        }
+    }
+
+    #[test]
+    fn test_alert_cooldown() -> Result<()> {
+        let cooldown: AlertCooldown = toml::from_str(
+            r#"
+            window_secs = 60
+            escalate_sol = [5.0, 10.0]
+            "#,
+        )?;
+        cooldown.validate()?;
+        assert_eq!(cooldown.key, default_cooldown_key());
+        let mut throttle = AlertThrottle::new(cooldown);
+        let at = |secs: i64| 1_734_616_564 + secs;
+
+        assert!(throttle.check(&mut buy("w", "m", 1), at(0)));
+        assert!(!throttle.check(&mut buy("w", "m", 1), at(10)));
+        assert!(!throttle.check(&mut buy("w", "m", 1), at(20)));
+        // another mint has its own key
+        assert!(throttle.check(&mut buy("w", "other", 1), at(20)));
+        // 5 SOL bought in the mint breaks through
+        let mut alert = buy("w", "m", 2);
+        assert!(throttle.check(&mut alert, at(30)));
+        assert_eq!(alert.suppressed, 2);
+        assert!(!throttle.check(&mut buy("w", "m", 1), at(40)));
+        // the cooldown of the escalation ended
+        let mut alert = buy("w", "m", 1);
+        assert!(throttle.check(&mut alert, at(95)));
+        assert_eq!(alert.suppressed, 1);
+        assert!(alert.to_string().ends_with(", 1 similar suppressed"));
+        // a restart keeps the cooldowns
+        let state = serde_json::to_string(&throttle.state())?;
+        let mut restarted = AlertThrottle::new(throttle.cooldown.clone());
+        restarted.restore(serde_json::from_str(&state)?);
+        assert!(!restarted.check(&mut buy("w", "m", 1), at(100)));
+
+        let invalid: AlertCooldown =
+            toml::from_str("window_secs = 60\nescalate_sol = [10.0, 5.0]")?;
+        assert!(invalid.validate().is_err());
+        Ok(())
+    }
+}
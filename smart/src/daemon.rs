@@ -1,22 +1,180 @@
+use std::{future::Future, sync::Arc};
+
 use anyhow::Result;
-use tracing::{error, info};
+use futures_util::future::join_all;
+use tokio::{
+    sync::watch,
+    task::JoinHandle,
+    time::{timeout_at, Instant},
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    alert::{alert_channel, run_alert_dispatcher},
+    config::get_global_config,
+    dev_watch::{run_dev_watcher, DEV_SELL_RULE},
+    discovery::run_trade_recorder,
+    execution::Executor,
+    graduation::{run_graduation_watcher, GRADUATION_RULE},
+    models::{get_global_manager, MonitorStatus},
+    reload::{monitor_rules, run_config_reloader, MonitorSet},
+    scoring::run_scoring,
+    shutdown::shutdown_signal,
+    sniper::run_sniper_detector,
+    sol_client::{client::SolanaMonitor, PUMP_PROGRAM_ID},
+    strategies::{MonitorContext, CONSENSUS_STRATEGY},
+    supervisor::{supervise, StatusReporter, SupervisorConfig},
+};
+
+/// run a background task of the daemon under `supervise`, listed by `smart monitors`
+fn spawn_supervised<F, Fut>(
+    ctx: MonitorContext,
+    supervisor: &'static SupervisorConfig,
+    status: MonitorStatus,
+    make: F,
+) -> JoinHandle<()>
+where
+    F: Fn(MonitorContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let name = format!("{} of {}", status.rule_type, status.address);
+    let status = StatusReporter::new(ctx.manager.clone(), status);
+    tokio::spawn(async move {
+        // the task drains on the daemon shutdown, the supervisor then stops it
+        let stop = ctx.shutdown.clone();
+        supervise(&name, supervisor, &status, stop, |_| make(ctx.clone())).await
+    })
+}
 
-use crate::config::get_global_config;
+/// the status of a background task watching the pump.fun program
+fn program_status(rule_type: &str) -> MonitorStatus {
+    MonitorStatus {
+        address: PUMP_PROGRAM_ID.to_string(),
+        rule_type: rule_type.to_string(),
+        target: "program".to_string(),
+        ..Default::default()
+    }
+}
 
 pub async fn daemon() -> Result<()> {
     info!("daemon start");
     let c = get_global_config().await;
-    for m in &c.monitors {
-        info!("monitor: {:?}", m);
-        // every monitor should have its own thread
-        tokio::spawn(async move {
-            if let Err(e) = m.should_alert().await {
-                error!("Monitor error: {}, MonitorRult: {:?}", e, m);
-            }
-        });
-    }
-    // wait forever
-    tokio::signal::ctrl_c().await?;
+    let (alert_sender, alert_receiver) = alert_channel();
+    let dispatcher = tokio::spawn(run_alert_dispatcher(alert_receiver));
+    let (shutdown_sender, shutdown) = watch::channel(false);
+    // approved discovery candidates run next to the configured monitors
+    let manager = get_global_manager().await;
+    manager.clear_monitor_statuses().await?;
+    let rules = monitor_rules(manager, c).await?;
+    let candidate_wallets: Vec<String> = rules[c.monitors.len()..]
+        .iter()
+        .map(|m| m.address.clone())
+        .collect();
+    let mut ctx = MonitorContext::from_global(alert_sender.clone(), shutdown.clone()).await;
+    if c.execution.enabled {
+        info!(
+            "copy trade execution enabled, dry run: {}",
+            c.execution.dry_run
+        );
+        let executor = Arc::new(Executor::new(
+            &c.execution,
+            manager.clone(),
+            SolanaMonitor::new(&c.solana_wss_url, &c.solana_rpc_url),
+        )?);
+        // the orders sent before the restart settle before new ones
+        executor.recover().await?;
+        ctx.executor = Some(executor);
+    }
+    // every monitor should have its own thread, restarted when the config changes
+    let mut monitors = MonitorSet::new(ctx, &c.reload, &c.supervisor);
+    monitors.apply(&rules).await;
+    let mut tasks = vec![tokio::spawn(async move {
+        if let Err(e) = run_config_reloader(monitors, &c.reload).await {
+            error!("Config reloader error: {}", e);
+        }
+    })];
+    if c.discovery.record_trades {
+        let ctx = MonitorContext::from_global(alert_sender.clone(), shutdown.clone()).await;
+        tasks.push(spawn_supervised(
+            ctx,
+            &c.supervisor,
+            program_status("TradeRecorder"),
+            move |ctx| async move { run_trade_recorder(&ctx).await },
+        ));
+    }
+    if c.sniper.enabled {
+        let ctx = MonitorContext::from_global(alert_sender.clone(), shutdown.clone()).await;
+        tasks.push(spawn_supervised(
+            ctx,
+            &c.supervisor,
+            program_status("Sniper"),
+            move |ctx| async move { run_sniper_detector(&ctx, &c.sniper).await },
+        ));
+    }
+    if c.dev_watch.enabled {
+        let ctx = MonitorContext::from_global(alert_sender.clone(), shutdown.clone()).await;
+        tasks.push(spawn_supervised(
+            ctx,
+            &c.supervisor,
+            program_status(DEV_SELL_RULE),
+            move |ctx| async move { run_dev_watcher(&ctx, &c.dev_watch).await },
+        ));
+    }
+    if c.graduation.enabled {
+        let ctx = MonitorContext::from_global(alert_sender.clone(), shutdown.clone()).await;
+        tasks.push(spawn_supervised(
+            ctx,
+            &c.supervisor,
+            program_status(GRADUATION_RULE),
+            move |ctx| async move { run_graduation_watcher(&ctx, &c.graduation).await },
+        ));
+    }
+    let mut scoring = None;
+    if c.scoring.interval_secs > 0 {
+        let mut wallets = c.watched_wallets();
+        wallets.extend(candidate_wallets);
+        scoring = Some(tokio::spawn(run_scoring(
+            get_global_manager().await,
+            &c.scoring,
+            wallets,
+        )));
+    }
+    for g in &c.consensus {
+        info!("consensus group: {:?}", g);
+        let ctx = MonitorContext::from_global(alert_sender.clone(), shutdown.clone()).await;
+        let status = MonitorStatus {
+            address: g.name.clone(),
+            rule_type: CONSENSUS_STRATEGY.to_string(),
+            target: "group".to_string(),
+            ..Default::default()
+        };
+        tasks.push(spawn_supervised(
+            ctx,
+            &c.supervisor,
+            status,
+            move |ctx| async move { g.run(&ctx).await },
+        ));
+    }
+    shutdown_signal().await;
 
+    info!("daemon shutting down");
+    let deadline = Instant::now() + c.shutdown.drain_timeout();
+    // the ingestion stops, the queued logs are handled
+    let _ = shutdown_sender.send(true);
+    if let Some(scoring) = scoring {
+        scoring.abort();
+    }
+    let aborts: Vec<_> = tasks.iter().map(|t| t.abort_handle()).collect();
+    if timeout_at(deadline, join_all(tasks)).await.is_err() {
+        warn!("tasks did not drain in {}s, aborted", c.shutdown.drain_secs);
+        aborts.iter().for_each(|t| t.abort());
+    }
+    // the dispatcher ends with the last alert sender, after the queued alerts
+    drop(alert_sender);
+    if timeout_at(deadline, dispatcher).await.is_err() {
+        warn!("alerts not delivered in {}s", c.shutdown.drain_secs);
+    }
+    manager.close().await;
+    info!("daemon stopped");
     Ok(())
 }
@@ -0,0 +1,335 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use anyhow::Result;
+use serde::Deserialize;
+use solana_client::rpc_response::{Response, RpcLogsResponse};
+use tokio::{sync::mpsc, task::JoinHandle};
+use tracing::{error, info, warn};
+use validator::Validate;
+
+use crate::{
+    alert::{send_alert, Alert, AlertKind},
+    models::{ModelsManager, TokenLaunch},
+    sol_client::client::SolanaMonitor,
+    strategies::{spawn_log_subscribe, MonitorContext, RecentSignatures, StrategyEvent},
+};
+
+/// rule name of the creator sell alerts
+pub const DEV_SELL_RULE: &str = "DevSell";
+
+#[derive(Clone, Debug, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct DevWatchConfig {
+    pub enabled: bool, // 监听持仓代币创建者的卖出
+    #[validate(range(min = 1))]
+    pub refresh_secs: u64, // how often the active spl_token mints are reloaded
+}
+
+impl Default for DevWatchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_secs: 60,
+        }
+    }
+}
+
+/// follow the creators of the mints held by the monitors
+pub struct DevWatcher {
+    solana_client: SolanaMonitor,
+    creators: HashMap<String, String>, // mint -> creator, a creator not found is looked up on the next refresh
+    exited: HashSet<String>,           // mints their creator sold out of
+    subscriptions: HashMap<String, JoinHandle<()>>, // creator -> logs subscription
+}
+
+impl DevWatcher {
+    pub fn new(ctx: &MonitorContext) -> Self {
+        Self {
+            solana_client: SolanaMonitor::new(&ctx.wss_url, &ctx.rpc_url),
+            creators: HashMap::new(),
+            exited: HashSet::new(),
+            subscriptions: HashMap::new(),
+        }
+    }
+
+    /// the creator of `mint`, from the launches seen or its creation transaction
+    async fn creator(&self, manager: &ModelsManager, mint: &str) -> Result<Option<String>> {
+        if let Some(launch) = manager.get_token_launch(mint).await? {
+            return Ok(Some(launch.creator));
+        }
+        let Some((signature, slot, create)) = self.solana_client.get_create_event(mint).await?
+        else {
+            return Ok(None);
+        };
+        manager
+            .add_token_launch(&TokenLaunch {
+                mint: mint.to_string(),
+                creator: create.user.clone(),
+                slot: slot as i64,
+                signature,
+            })
+            .await?;
+        Ok(Some(create.user))
+    }
+
+    /// follow the creators of the active mints, stop following the others
+    pub async fn refresh(
+        &mut self,
+        ctx: &MonitorContext,
+        sender: &mpsc::Sender<Response<RpcLogsResponse>>,
+    ) -> Result<()> {
+        let active: HashSet<String> = ctx
+            .manager
+            .get_active_spl_mints()
+            .await?
+            .into_iter()
+            .collect();
+        self.creators.retain(|mint, _| active.contains(mint));
+        self.exited.retain(|mint| active.contains(mint));
+        for mint in active {
+            if self.creators.contains_key(&mint) {
+                continue;
+            }
+            // retried on the next refresh
+            match self.creator(&ctx.manager, &mint).await {
+                Ok(Some(creator)) => {
+                    self.creators.insert(mint, creator);
+                }
+                Ok(None) => warn!("creator of {} not found", mint),
+                Err(e) => warn!("get creator error: {:?}, mint: {}", e, mint),
+            }
+        }
+        self.sync_subscriptions(ctx, sender);
+        Ok(())
+    }
+
+    fn unsubscribe_all(&mut self) {
+        for (_, handle) in self.subscriptions.drain() {
+            handle.abort();
+        }
+    }
+
+    fn sync_subscriptions(
+        &mut self,
+        ctx: &MonitorContext,
+        sender: &mpsc::Sender<Response<RpcLogsResponse>>,
+    ) {
+        let wanted: HashSet<String> = self
+            .creators
+            .iter()
+            .filter(|(mint, _)| !self.exited.contains(*mint))
+            .map(|(_, creator)| creator.clone())
+            .collect();
+        self.subscriptions.retain(|creator, handle| {
+            let keep = wanted.contains(creator);
+            if !keep {
+                info!("stop watching creator {}", creator);
+                handle.abort();
+            }
+            keep
+        });
+        for creator in wanted {
+            self.subscriptions
+                .entry(creator)
+                .or_insert_with_key(|creator| {
+                    info!("watching creator {}", creator);
+                    spawn_log_subscribe(creator, ctx, sender.clone())
+                });
+        }
+    }
+
+    /// alert on the sells of a followed creator, a creator without tokens left is not followed anymore
+    pub async fn on_event(
+        &mut self,
+        ctx: &MonitorContext,
+        sender: &mpsc::Sender<Response<RpcLogsResponse>>,
+        event: &StrategyEvent,
+    ) -> Result<()> {
+        for trade in event.trades.iter().filter(|t| !t.is_buy) {
+            if self.creators.get(&trade.mint) != Some(&trade.user)
+                || self.exited.contains(&trade.mint)
+            {
+                continue;
+            }
+            // the alert goes out without the balances it couldn't read
+            let (sold_percentage, remaining) = match self
+                .solana_client
+                .get_token_balances(&event.signature, &trade.user, &trade.mint)
+                .await
+            {
+                Ok((0, post)) => (Some(100.0), Some(post)),
+                Ok((pre, post)) => (
+                    Some(trade.token_amount as f64 / pre as f64 * 100.0),
+                    Some(post),
+                ),
+                Err(e) => {
+                    error!(
+                        "get token balances error: {:?}, signature: {}",
+                        e, event.signature
+                    );
+                    (None, None)
+                }
+            };
+            send_alert(
+                &ctx.alerts,
+                Alert {
+                    address: trade.user.clone(),
+                    rule: DEV_SELL_RULE.to_string(),
+                    mint: trade.mint.clone(),
+                    signature: event.signature.clone(),
+                    kind: AlertKind::DevSell {
+                        sol_amount: trade.sol_amount,
+                        token_amount: trade.token_amount,
+                        price: trade.price(),
+                        sold_percentage,
+                        remaining,
+                    },
+                    suppressed: 0,
+                },
+            )
+            .await;
+            if remaining == Some(0) {
+                info!("creator {} sold out of {}", trade.user, trade.mint);
+                self.exited.insert(trade.mint.clone());
+            }
+        }
+        self.sync_subscriptions(ctx, sender);
+        Ok(())
+    }
+}
+
+/// alert on the sells of the creators of the active mints, reloaded every `refresh_secs`
+pub async fn run_dev_watcher(ctx: &MonitorContext, config: &DevWatchConfig) -> Result<()> {
+    let (sender, mut receiver) = mpsc::channel::<Response<RpcLogsResponse>>(1000);
+    let mut watcher = DevWatcher::new(ctx);
+    let mut recent = RecentSignatures::new(1000);
+    let mut refresh = tokio::time::interval(Duration::from_secs(config.refresh_secs));
+    loop {
+        tokio::select! {
+            log = ctx.next_log(&mut receiver) => {
+                let Some(log) = log else { break };
+                if !recent.insert(&log.value.signature) {
+                    continue;
+                }
+                let event = StrategyEvent::from(log);
+                if let Err(e) = watcher.on_event(ctx, &sender, &event).await {
+                    error!("dev watcher error: {:?}, signature: {}", e, event.signature);
+                }
+            }
+            _ = refresh.tick(), if !ctx.is_shutdown() => {
+                if let Err(e) = watcher.refresh(ctx, &sender).await {
+                    error!("dev watcher refresh error: {:?}", e);
+                }
+            }
+        }
+    }
+    watcher.unsubscribe_all();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alert::alert_channel,
+        models::MONITOR_STATUS_CLOSED,
+        test_support::{fixture, MockSolana},
+    };
+    use serde_json::json;
+    use solana_sdk::signature::Signature;
+
+    #[tokio::test]
+    async fn test_dev_sell_alert() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let creator = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x";
+        let mint = "7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump";
+        mock.add_transaction_fixture("pump_sell_half_tx.json");
+        mock.add_transaction_fixture("pump_sell_rest_tx.json");
+
+        let (alerts, mut alert_receiver) = alert_channel();
+        let ctx = mock.context(alerts).await?;
+        ctx.manager.add_new_spl_token(mint, "holder", "Buy").await?;
+
+        let (sender, mut receiver) = mpsc::channel(10);
+        let mut watcher = DevWatcher::new(&ctx);
+        // the launch isn't known yet
+        watcher.refresh(&ctx, &sender).await?;
+        assert!(watcher.creators.is_empty());
+
+        ctx.manager
+            .add_token_launch(&TokenLaunch {
+                mint: mint.to_string(),
+                creator: creator.to_string(),
+                slot: 308456000,
+                signature: "create".to_string(),
+            })
+            .await?;
+        watcher.refresh(&ctx, &sender).await?;
+        assert!(watcher.subscriptions.contains_key(creator));
+
+        mock.push_logs_fixture(creator, "pump_sell_half_logs.json");
+        let event = StrategyEvent::from(receiver.recv().await.expect("no logs received"));
+        watcher.on_event(&ctx, &sender, &event).await?;
+        let alert = alert_receiver.recv().await.expect("no alert received");
+        assert_eq!(alert.rule, DEV_SELL_RULE);
+        assert_eq!(alert.address, creator);
+        let AlertKind::DevSell {
+            sold_percentage,
+            remaining,
+            ..
+        } = alert.kind
+        else {
+            panic!("not a dev sell alert");
+        };
+        assert!(sold_percentage.is_some_and(|p| (p - 50.0).abs() < 1e-6));
+        assert_eq!(remaining, Some(18904581368109));
+        assert!(watcher.subscriptions.contains_key(creator));
+
+        // the balances of a transaction not found are unknown, the sell still alerts
+        let mut logs = fixture("pump_sell_half_logs.json");
+        logs["value"]["signature"] = json!(Signature::new_unique().to_string());
+        mock.push_logs(creator, logs);
+        let event = StrategyEvent::from(receiver.recv().await.expect("no logs received"));
+        watcher.on_event(&ctx, &sender, &event).await?;
+        let alert = alert_receiver.recv().await.expect("no alert received");
+        let AlertKind::DevSell {
+            sold_percentage,
+            remaining,
+            ..
+        } = alert.kind
+        else {
+            panic!("not a dev sell alert");
+        };
+        assert_eq!((sold_percentage, remaining), (None, None));
+        assert!(watcher.subscriptions.contains_key(creator));
+
+        // the creator sold out, nothing left to watch
+        mock.push_logs_fixture(creator, "pump_sell_rest_logs.json");
+        let event = StrategyEvent::from(receiver.recv().await.expect("no logs received"));
+        watcher.on_event(&ctx, &sender, &event).await?;
+        let alert = alert_receiver.recv().await.expect("no alert received");
+        let AlertKind::DevSell {
+            sold_percentage,
+            remaining,
+            ..
+        } = alert.kind
+        else {
+            panic!("not a dev sell alert");
+        };
+        assert!(sold_percentage.is_some_and(|p| (p - 100.0).abs() < 1e-6));
+        assert_eq!(remaining, Some(0));
+        assert!(watcher.subscriptions.is_empty());
+
+        // the monitor closed its position
+        ctx.manager
+            .update_spl_token_status(mint, "holder", "Buy", MONITOR_STATUS_CLOSED)
+            .await?;
+        watcher.refresh(&ctx, &sender).await?;
+        assert!(watcher.creators.is_empty());
+        assert!(watcher.exited.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use serde::Deserialize;
+use solana_client::rpc_response::{Response, RpcLogsResponse};
+use tokio::sync::mpsc;
+use tracing::{error, info};
+use validator::Validate;
+
+use crate::{
+    models::{ModelsManager, CANDIDATE_STATUS_APPROVED},
+    sol_client::PUMP_PROGRAM_ID,
+    strategies::{spawn_log_subscribe, MonitorContext, MonitorRule, StrategyEvent, BUY_STRATEGY},
+};
+
+#[derive(Clone, Debug, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscoveryConfig {
+    pub record_trades: bool, // 记录所有 pump.fun 交易, discovery only sees what was recorded
+    #[validate(range(min = 0.0))]
+    pub market_cap: f64, // a token reaching this market cap in SOL is a winner, graduated ones always are
+    #[validate(range(min = 1))]
+    pub early_buyers: usize, // the first n distinct buyers of a winner are early
+    #[validate(range(min = 1))]
+    pub min_early_count: usize, // propose wallets early in at least n winners
+    pub lookback_secs: u64, // only the tokens traded in this window
+    #[validate(length(min = 1))]
+    pub rule_type: String, // strategy of the monitors of approved candidates
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            record_trades: false,
+            market_cap: 300.0,
+            early_buyers: 20,
+            min_early_count: 2,
+            lookback_secs: 7 * 24 * 3600,
+            rule_type: BUY_STRATEGY.to_string(),
+        }
+    }
+}
+
+/// a wallet that was early in several winners
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub address: String,
+    pub mints: Vec<String>, // the winners it was early in
+}
+
+/// rank the early buyers of the winners since `now - lookback_secs` and store them as pending candidates.
+///
+/// `known` wallets are already monitored and skipped, a reviewed candidate keeps its status.
+pub async fn discover_wallets(
+    manager: &ModelsManager,
+    config: &DiscoveryConfig,
+    now: i64,
+    known: &HashSet<String>,
+) -> Result<Vec<Candidate>> {
+    let since = now - config.lookback_secs as i64;
+    let winners = manager.get_winner_mints(since, config.market_cap).await?;
+    info!("discovery: {} winners since {}", winners.len(), since);
+
+    let mut early: HashMap<String, Vec<String>> = HashMap::new();
+    for mint in &winners {
+        for buyer in manager.get_early_buyers(mint, config.early_buyers).await? {
+            if !known.contains(&buyer) {
+                early.entry(buyer).or_default().push(mint.clone());
+            }
+        }
+    }
+
+    let mut candidates: Vec<Candidate> = early
+        .into_iter()
+        .filter(|(_, mints)| mints.len() >= config.min_early_count)
+        .map(|(address, mints)| Candidate { address, mints })
+        .collect();
+    candidates.sort_by(|a, b| {
+        b.mints
+            .len()
+            .cmp(&a.mints.len())
+            .then_with(|| a.address.cmp(&b.address))
+    });
+    for c in &candidates {
+        manager
+            .upsert_wallet_candidate(&c.address, c.mints.len() as i64, &c.mints.join(","))
+            .await?;
+    }
+    Ok(candidates)
+}
+
+impl DiscoveryConfig {
+    /// the monitors of the approved candidates not already in `monitors`
+    pub async fn candidate_rules(
+        &self,
+        manager: &ModelsManager,
+        monitors: &[MonitorRule],
+    ) -> Result<Vec<MonitorRule>> {
+        let known: HashSet<&String> = monitors.iter().map(|m| &m.address).collect();
+        let approved = manager
+            .get_wallet_candidates(CANDIDATE_STATUS_APPROVED)
+            .await?;
+        Ok(approved
+            .into_iter()
+            .filter(|c| !known.contains(&c.address))
+            .map(|c| MonitorRule {
+                address: c.address,
+                rule_type: self.rule_type.clone(),
+                target: Default::default(),
+                conditions: Default::default(),
+                config: Default::default(),
+                cooldown: None,
+            })
+            .collect())
+    }
+}
+
+/// store every pump.fun trade until the program subscription ends or the daemon shuts down
+pub async fn run_trade_recorder(ctx: &MonitorContext) -> Result<()> {
+    let (sender, mut receiver) = mpsc::channel::<Response<RpcLogsResponse>>(1000);
+    let subscription = spawn_log_subscribe(PUMP_PROGRAM_ID, ctx, sender);
+    while let Some(log) = ctx.next_log(&mut receiver).await {
+        let event = StrategyEvent::from(log);
+        if event.trades.is_empty() {
+            continue;
+        }
+        if let Err(e) = ctx
+            .manager
+            .add_trade_events(&event.signature, &event.trades)
+            .await
+        {
+            error!(
+                "record trade error: {:?}, signature: {}",
+                e, event.signature
+            );
+        }
+    }
+    if ctx.is_shutdown() {
+        subscription.abort();
+        return Ok(());
+    }
+    subscription.await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::{CANDIDATE_STATUS_PENDING, CANDIDATE_STATUS_REJECTED},
+        sol_client::TradeEventData,
+        test_support::memory_manager,
+    };
+
+    fn trade(user: &str, mint: &str, timestamp: i64, real_token_reserves: u64) -> TradeEventData {
+        TradeEventData {
+            mint: mint.to_string(),
+            sol_amount: 1_000_000_000,
+            token_amount: 30_000_000_000,
+            is_buy: true,
+            user: user.to_string(),
+            timestamp,
+            virtual_sol_reserves: 30_000_000_000,
+            virtual_token_reserves: 1_000_000_000_000_000,
+            real_sol_reserves: 0,
+            real_token_reserves,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_discover_wallets() -> Result<()> {
+        let manager = memory_manager().await?;
+        let now = 1_734_600_000;
+        let live = 500_000_000_000_000;
+        // two graduated winners and a token that went nowhere
+        for (i, mint) in ["win1", "win2"].iter().enumerate() {
+            let t = now - 1000 + i as i64 * 100;
+            let trades = [
+                trade("early", mint, t, live),
+                trade("monitored", mint, t + 1, live),
+                trade(&format!("late{}", i), mint, t + 50, 0),
+            ];
+            manager
+                .add_trade_events(&format!("sig{}", mint), &trades)
+                .await?;
+        }
+        manager
+            .add_trade_events("sigloss", &[trade("loser", "loss", now - 500, live)])
+            .await?;
+        manager
+            .add_trade_events("sigloss", &[trade("loser", "loss", now - 500, live)])
+            .await?;
+
+        let config = DiscoveryConfig {
+            early_buyers: 2,
+            ..Default::default()
+        };
+        let known = HashSet::from(["monitored".to_string()]);
+        let candidates = discover_wallets(&manager, &config, now, &known).await?;
+        assert_eq!(
+            candidates,
+            vec![Candidate {
+                address: "early".to_string(),
+                mints: vec!["win1".to_string(), "win2".to_string()],
+            }]
+        );
+
+        let pending = manager
+            .get_wallet_candidates(CANDIDATE_STATUS_PENDING)
+            .await?;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].early_count, 2);
+
+        // a reviewed candidate is not proposed again
+        assert!(
+            manager
+                .update_wallet_candidate_status("early", CANDIDATE_STATUS_REJECTED)
+                .await?
+        );
+        discover_wallets(&manager, &config, now, &known).await?;
+        assert!(manager
+            .get_wallet_candidates(CANDIDATE_STATUS_PENDING)
+            .await?
+            .is_empty());
+
+        // the address is bound, a quote in it matches nothing
+        assert!(
+            !manager
+                .update_wallet_candidate_status("x' OR '1' = '1", CANDIDATE_STATUS_APPROVED)
+                .await?
+        );
+        manager
+            .update_wallet_candidate_status("early", CANDIDATE_STATUS_APPROVED)
+            .await?;
+        let rules = config.candidate_rules(&manager, &[]).await?;
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].address, "early");
+        assert_eq!(rules[0].rule_type, BUY_STRATEGY);
+        Ok(())
+    }
+}
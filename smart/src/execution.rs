@@ -0,0 +1,723 @@
+use std::{
+    collections::HashSet,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair},
+    signer::Signer,
+};
+use tracing::{error, info, warn};
+use validator::{Validate, ValidationError};
+
+use crate::{
+    models::{
+        ExecutionOrder, ExecutionPosition, ModelsManager, EXECUTION_ORDER_CONFIRMED,
+        EXECUTION_ORDER_DRY_RUN, EXECUTION_ORDER_FAILED, EXECUTION_ORDER_SENT,
+        EXECUTION_POSITION_CLOSED, EXECUTION_POSITION_FAILED, EXECUTION_POSITION_OPEN,
+        EXECUTION_POSITION_PENDING,
+    },
+    sol_client::{
+        client::SolanaMonitor,
+        curve::PUMP_FEE_BPS,
+        pump::{serialize_tx, PumpMarket, PumpQuote, PumpTxConfig},
+        SOL_DECIMALS,
+    },
+};
+
+/// how often the status of a sent transaction is polled
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// how many times the landed transaction is fetched for its fill
+const FILL_ATTEMPTS: usize = 3;
+
+#[derive(Clone, Debug, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+#[validate(schema(function = "validate_execution_config"))]
+pub struct ExecutionConfig {
+    pub enabled: bool,          // CopyTrade 规则自动下单
+    pub dry_run: bool,          // only log the transactions, nothing is sent
+    pub keypair_path: String,   // solana keypair json of the trading wallet
+    pub max_sol_per_trade: f64, // 单笔最大 SOL, the fee included, a bigger rule size is cut down
+    pub max_open_positions: usize,
+    pub daily_loss_limit_sol: f64, // no buys once the realized loss of the UTC day reaches it, 0 disables
+    pub blacklist: Vec<String>,    // mints never bought
+    #[validate(nested)]
+    pub tx: PumpTxConfig,
+}
+
+impl Default for ExecutionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dry_run: true,
+            keypair_path: String::new(),
+            max_sol_per_trade: 0.1,
+            max_open_positions: 5,
+            daily_loss_limit_sol: 1.0,
+            blacklist: vec![],
+            tx: PumpTxConfig::default(),
+        }
+    }
+}
+
+fn validate_execution_config(config: &ExecutionConfig) -> Result<(), ValidationError> {
+    if !config.max_sol_per_trade.is_finite() || config.max_sol_per_trade <= 0.0 {
+        return Err(ValidationError::new("max_sol_per_trade")
+            .with_message("max_sol_per_trade is not positive".into()));
+    }
+    if !config.daily_loss_limit_sol.is_finite() || config.daily_loss_limit_sol < 0.0 {
+        return Err(ValidationError::new("daily_loss_limit_sol")
+            .with_message("daily_loss_limit_sol is negative".into()));
+    }
+    if config.enabled && !config.dry_run && config.keypair_path.is_empty() {
+        return Err(ValidationError::new("keypair_path")
+            .with_message("keypair_path is required to send transactions".into()));
+    }
+    Ok(())
+}
+
+fn lamports(sol: f64) -> u64 {
+    (sol * 10f64.powi(SOL_DECIMALS as i32)) as u64
+}
+
+/// sends the orders of the CopyTrade rules within the risk limits and tracks their confirmation.
+/// a position changes once its order is confirmed, at once in a dry run.
+pub struct Executor {
+    config: ExecutionConfig,
+    manager: Arc<ModelsManager>,
+    client: SolanaMonitor,
+    payer: Keypair,
+    buying: tokio::sync::Mutex<()>, // held while a buy is checked and its position added
+    inflight: Mutex<HashSet<String>>, // mints claimed by a buy or sell until its order settles
+}
+
+impl Executor {
+    /// a dry run without a keypair signs with a throwaway one
+    pub fn new(
+        config: &ExecutionConfig,
+        manager: Arc<ModelsManager>,
+        client: SolanaMonitor,
+    ) -> Result<Self> {
+        let payer = if config.keypair_path.is_empty() {
+            Keypair::new()
+        } else {
+            read_keypair_file(&config.keypair_path)
+                .map_err(|e| anyhow!("Failed to read keypair {}: {}", config.keypair_path, e))?
+        };
+        Ok(Self {
+            config: config.clone(),
+            manager,
+            client,
+            payer,
+            buying: tokio::sync::Mutex::new(()),
+            inflight: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// the pending or open position in `mint`
+    pub async fn position(&self, mint: &str) -> Result<Option<ExecutionPosition>> {
+        Ok(self
+            .manager
+            .get_active_execution_positions(self.config.dry_run, Some(mint))
+            .await?
+            .pop())
+    }
+
+    pub async fn positions(&self) -> Result<Vec<ExecutionPosition>> {
+        self.manager
+            .get_active_execution_positions(self.config.dry_run, None)
+            .await
+    }
+
+    fn is_inflight(&self, mint: &str) -> bool {
+        self.inflight.lock().unwrap().contains(mint)
+    }
+
+    /// false when another order holds `mint`
+    fn claim(&self, mint: &str) -> bool {
+        self.inflight.lock().unwrap().insert(mint.to_string())
+    }
+
+    fn release(&self, mint: &str) {
+        self.inflight.lock().unwrap().remove(mint);
+    }
+
+    /// release `mint` unless its order was sent, the confirmation releases that one
+    fn settle_claim(&self, mint: &str, order: &Result<Option<ExecutionOrder>>) {
+        let sent = matches!(order, Ok(Some(o)) if o.status == EXECUTION_ORDER_SENT);
+        if !sent {
+            self.release(mint);
+        }
+    }
+
+    /// settle the orders sent before a restart, fail the pending positions that never sent one
+    pub async fn recover(self: &Arc<Self>) -> Result<()> {
+        let orders = self
+            .manager
+            .get_execution_orders_by_status(EXECUTION_ORDER_SENT)
+            .await?;
+        for order in orders {
+            let Some(position) = self
+                .manager
+                .get_execution_position(order.position_id)
+                .await?
+            else {
+                continue;
+            };
+            info!(
+                "recover order of {}, signature: {}",
+                position.mint, order.signature
+            );
+            self.claim(&position.mint);
+            self.spawn_confirm(order, position.mint);
+        }
+        for position in self.positions().await? {
+            if position.status == EXECUTION_POSITION_PENDING && !self.is_inflight(&position.mint) {
+                warn!(
+                    "no order sent for the pending position in {}",
+                    position.mint
+                );
+                self.fail_position(position.id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// why a buy of `mint` at `now` is held back by the risk limits
+    async fn check_buy(&self, mint: &str, now: i64) -> Result<Option<String>> {
+        if self.config.blacklist.iter().any(|m| m == mint) {
+            return Ok(Some("blacklisted".to_string()));
+        }
+        if self.is_inflight(mint) {
+            return Ok(Some("an order is waiting for confirmation".to_string()));
+        }
+        let positions = self.positions().await?;
+        if positions.iter().any(|p| p.mint == mint) {
+            return Ok(Some("already holding".to_string()));
+        }
+        if positions.len() >= self.config.max_open_positions {
+            return Ok(Some(format!("{} positions open", positions.len())));
+        }
+        if self.config.daily_loss_limit_sol > 0.0 {
+            let day_start = now - now.rem_euclid(86400);
+            let pnl = self
+                .manager
+                .get_execution_realized_pnl(self.config.dry_run, day_start)
+                .await?;
+            if pnl <= -(lamports(self.config.daily_loss_limit_sol) as i64) {
+                return Ok(Some(format!("daily loss of {} lamports", -pnl)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// buy `mint` for `sol_amount` SOL into the curve copying `wallet`, None when a risk limit holds it back
+    pub async fn buy(
+        self: &Arc<Self>,
+        wallet: &str,
+        mint: &str,
+        sol_amount: f64,
+        now: i64,
+    ) -> Result<Option<ExecutionOrder>> {
+        // the pending position counts for the next buy, checked and added as one
+        let position_id = {
+            let _buying = self.buying.lock().await;
+            if let Some(reason) = self.check_buy(mint, now).await? {
+                info!("skip buy of {} copying {}: {}", mint, wallet, reason);
+                return Ok(None);
+            }
+            self.claim(mint);
+            let position = ExecutionPosition {
+                wallet: wallet.to_string(),
+                mint: mint.to_string(),
+                dry_run: self.config.dry_run,
+                status: EXECUTION_POSITION_PENDING.to_string(),
+                opened_at: now,
+                ..Default::default()
+            };
+            match self.manager.add_execution_position(&position).await {
+                Ok(id) => id,
+                Err(e) => {
+                    self.release(mint);
+                    return Err(e);
+                }
+            }
+        };
+        let order = self
+            .open(position_id, mint, sol_amount, now)
+            .await
+            .map(Some);
+        if order.is_err() {
+            if let Err(e) = self.fail_position(position_id).await {
+                error!("fail position error: {:?}, mint: {}", e, mint);
+            }
+        }
+        self.settle_claim(mint, &order);
+        order
+    }
+
+    async fn open(
+        self: &Arc<Self>,
+        position_id: i64,
+        mint: &str,
+        sol_amount: f64,
+        now: i64,
+    ) -> Result<ExecutionOrder> {
+        // the fee comes on top of the SOL into the curve
+        let max = lamports(self.config.max_sol_per_trade) * 10_000 / (10_000 + PUMP_FEE_BPS);
+        let sol = lamports(sol_amount).min(max);
+        let market = self.client.get_pump_market(mint).await?;
+        let quote = self.config.tx.quote_buy(&market.curve, sol);
+        if quote.token_amount == 0 {
+            return Err(anyhow!("No tokens left in the curve of {}", mint));
+        }
+        self.submit(position_id, mint, &market, &quote, "copy", now)
+            .await
+    }
+
+    /// sell `token_amount` of the open position in `mint`, None when there is none or an order is in flight
+    pub async fn sell(
+        self: &Arc<Self>,
+        mint: &str,
+        token_amount: u64,
+        reason: &str,
+        now: i64,
+    ) -> Result<Option<ExecutionOrder>> {
+        if !self.claim(mint) {
+            info!(
+                "skip sell of {}: an order is waiting for confirmation",
+                mint
+            );
+            return Ok(None);
+        }
+        let order = self.close(mint, token_amount, reason, now).await;
+        self.settle_claim(mint, &order);
+        order
+    }
+
+    async fn close(
+        self: &Arc<Self>,
+        mint: &str,
+        token_amount: u64,
+        reason: &str,
+        now: i64,
+    ) -> Result<Option<ExecutionOrder>> {
+        let Some(position) = self
+            .position(mint)
+            .await?
+            .filter(|p| p.status == EXECUTION_POSITION_OPEN)
+        else {
+            return Ok(None);
+        };
+        let tokens = token_amount.min(position.token_amount as u64);
+        if tokens == 0 {
+            return Ok(None);
+        }
+        let market = self.client.get_pump_market(mint).await?;
+        let quote = self.config.tx.quote_sell(&market.curve, tokens);
+        self.submit(position.id, mint, &market, &quote, reason, now)
+            .await
+            .map(Some)
+    }
+
+    async fn submit(
+        self: &Arc<Self>,
+        position_id: i64,
+        mint: &str,
+        market: &PumpMarket,
+        quote: &PumpQuote,
+        reason: &str,
+        now: i64,
+    ) -> Result<ExecutionOrder> {
+        let (blockhash, last_valid_block_height) = self.client.get_latest_blockhash().await?;
+        let tx = self.config.tx.build_tx(
+            &self.payer,
+            &Pubkey::from_str(mint)?,
+            market,
+            quote,
+            blockhash,
+        );
+        let mut order = ExecutionOrder {
+            position_id,
+            is_buy: quote.is_buy,
+            token_amount: quote.token_amount as i64,
+            sol_amount: quote.sol_amount as i64,
+            sol_limit: quote.sol_limit as i64,
+            signature: tx.signatures[0].to_string(),
+            last_valid_block_height: last_valid_block_height as i64,
+            status: EXECUTION_ORDER_SENT.to_string(),
+            reason: reason.to_string(),
+            created_at: now,
+            ..Default::default()
+        };
+        let side = if quote.is_buy { "buy" } else { "sell" };
+        if self.config.dry_run {
+            info!(
+                "dry run {} {} of {} for {} lamports, limit {}, tx: {}",
+                side,
+                quote.token_amount,
+                mint,
+                quote.sol_amount,
+                quote.sol_limit,
+                serialize_tx(&tx)?
+            );
+            order.status = EXECUTION_ORDER_DRY_RUN.to_string();
+            order.id = self.manager.add_execution_order(&order).await?;
+            self.apply(&mut order).await?;
+            return Ok(order);
+        }
+
+        if let Err(e) = self.client.send_tx(&tx).await {
+            order.id = self.manager.add_execution_order(&order).await?;
+            self.fail(&mut order, e.to_string()).await?;
+            return Err(e);
+        }
+        info!(
+            "sent {} {} of {} for {} lamports, limit {}, signature: {}",
+            side, quote.token_amount, mint, quote.sol_amount, quote.sol_limit, order.signature
+        );
+        order.id = self.manager.add_execution_order(&order).await?;
+        self.spawn_confirm(order.clone(), mint.to_string());
+        Ok(order)
+    }
+
+    /// confirm the sent order of `mint` in a new task, `mint` is released once it settles
+    fn spawn_confirm(self: &Arc<Self>, order: ExecutionOrder, mint: String) {
+        let executor = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = executor.confirm(order, &mint).await {
+                error!("confirm order error: {:?}, mint: {}", e, mint);
+            }
+            executor.release(&mint);
+        });
+    }
+
+    /// wait for the sent order to land, then apply its fill. it failed once the block height
+    /// passed the last valid one of its blockhash without a status
+    async fn confirm(&self, mut order: ExecutionOrder, mint: &str) -> Result<()> {
+        let signature = order.signature.parse()?;
+        loop {
+            // read before the status, a status still missing after it never comes
+            let height = self.client.get_block_height().await;
+            match self.client.get_signature_status(&signature).await {
+                Ok(Some(Ok(()))) => {
+                    info!("order confirmed, signature: {}", order.signature);
+                    order.status = EXECUTION_ORDER_CONFIRMED.to_string();
+                    self.fill(&mut order, mint).await;
+                    return self.apply(&mut order).await;
+                }
+                Ok(Some(Err(e))) => return self.fail(&mut order, e).await,
+                Ok(None) => match height {
+                    Ok(h) if h as i64 > order.last_valid_block_height => {
+                        let e = format!(
+                            "blockhash expired after block height {}",
+                            order.last_valid_block_height
+                        );
+                        return self.fail(&mut order, e).await;
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("get block height error: {:?}, {}", e, order.signature),
+                },
+                Err(e) => warn!("get signature status error: {:?}, {}", e, order.signature),
+            }
+            tokio::time::sleep(CONFIRM_POLL_INTERVAL).await;
+        }
+    }
+
+    /// replace the quote of the landed order with its pump.fun trade, the fee included.
+    /// the quote is kept when the trade can't be read
+    async fn fill(&self, order: &mut ExecutionOrder, mint: &str) {
+        let payer = self.payer.pubkey().to_string();
+        for attempt in 1..=FILL_ATTEMPTS {
+            match self.client.get_trade_events(&order.signature).await {
+                Ok(trades) => {
+                    let Some(trade) = trades
+                        .into_iter()
+                        .find(|t| t.user == payer && t.mint == mint && t.is_buy == order.is_buy)
+                    else {
+                        warn!("no trade of the order, signature: {}", order.signature);
+                        return;
+                    };
+                    let fee = trade.sol_amount * PUMP_FEE_BPS / 10_000;
+                    order.token_amount = trade.token_amount as i64;
+                    order.sol_amount = if order.is_buy {
+                        trade.sol_amount + fee
+                    } else {
+                        trade.sol_amount - fee
+                    } as i64;
+                    return;
+                }
+                Err(e) => warn!(
+                    "get fill error: {:?}, attempt {}, signature: {}",
+                    e, attempt, order.signature
+                ),
+            }
+            tokio::time::sleep(CONFIRM_POLL_INTERVAL).await;
+        }
+    }
+
+    /// the order went through, update its position
+    async fn apply(&self, order: &mut ExecutionOrder) -> Result<()> {
+        let mut position = self
+            .manager
+            .get_execution_position(order.position_id)
+            .await?
+            .ok_or_else(|| anyhow!("No execution position: {}", order.position_id))?;
+        if order.is_buy {
+            position.token_amount += order.token_amount;
+            position.cost += order.sol_amount;
+            position.status = EXECUTION_POSITION_OPEN.to_string();
+        } else {
+            let holding = position.token_amount.max(1) as i128;
+            let cost = (position.cost as i128 * order.token_amount as i128 / holding) as i64;
+            let pnl = order.sol_amount - cost;
+            position.token_amount -= order.token_amount;
+            position.cost -= cost;
+            position.realized_pnl += pnl;
+            order.realized_pnl = Some(pnl);
+            if position.token_amount <= 0 {
+                position.status = EXECUTION_POSITION_CLOSED.to_string();
+                position.closed_at = Some(order.created_at);
+            }
+        }
+        self.manager.update_execution_position(&position).await?;
+        self.manager.update_execution_order(order).await
+    }
+
+    async fn fail(&self, order: &mut ExecutionOrder, e: String) -> Result<()> {
+        error!("order failed: {}, signature: {}", e, order.signature);
+        order.status = EXECUTION_ORDER_FAILED.to_string();
+        order.error = Some(e);
+        self.manager.update_execution_order(order).await?;
+        self.fail_position(order.position_id).await
+    }
+
+    /// a buy that never landed leaves no position
+    async fn fail_position(&self, position_id: i64) -> Result<()> {
+        if let Some(mut position) = self
+            .manager
+            .get_execution_position(position_id)
+            .await?
+            .filter(|p| p.status == EXECUTION_POSITION_PENDING)
+        {
+            position.status = EXECUTION_POSITION_FAILED.to_string();
+            self.manager.update_execution_position(&position).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alert::alert_channel,
+        sol_client::TradeEventData,
+        test_support::{trade_event_log, MockSolana},
+    };
+    use solana_sdk::signature::{write_keypair_file, Signature};
+
+    #[tokio::test]
+    async fn test_execution_dry_run_limits() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let (sender, _receiver) = alert_channel();
+        let ctx = mock.context(sender).await?;
+        let wallet = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x";
+        let mint = "7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump";
+        let other = "DezXAZ8z7PnrnRJjz2wXBoRgixCCWcnWFpSGJN2B263";
+        mock.add_bonding_curve(mint, 30_000_000_000);
+        mock.add_bonding_curve(other, 30_000_000_000);
+
+        let config: ExecutionConfig = toml::from_str(
+            r#"
+            enabled = true
+            max_sol_per_trade = 0.5
+            max_open_positions = 1
+            daily_loss_limit_sol = 0.1
+            blacklist = ["BadMint"]
+            "#,
+        )?;
+        config.validate()?;
+        assert!(config.dry_run);
+        let executor = Arc::new(Executor::new(
+            &config,
+            ctx.manager.clone(),
+            SolanaMonitor::new(&mock.wss_url, &mock.rpc_url),
+        )?);
+        let now = 1_734_616_564;
+
+        assert!(executor.buy(wallet, "BadMint", 1.0, now).await?.is_none());
+        // two buys at once hold one position
+        let (a, b) = tokio::join!(
+            executor.buy(wallet, mint, 1.0, now),
+            executor.buy(wallet, other, 0.1, now)
+        );
+        let (buy, skipped) = (a?, b?);
+        assert!(skipped.is_none());
+        // cut down to max_sol_per_trade, the fee included
+        let buy = buy.expect("no buy");
+        assert_eq!(buy.status, EXECUTION_ORDER_DRY_RUN);
+        assert_eq!(buy.sol_amount, 499_999_999);
+        let position = executor.position(mint).await?.expect("no position");
+        assert_eq!(position.status, EXECUTION_POSITION_OPEN);
+        assert_eq!(position.token_amount, buy.token_amount);
+        // holding already, and max_open_positions
+        assert!(executor.buy(wallet, mint, 0.1, now).await?.is_none());
+        assert!(executor.buy(wallet, other, 0.1, now).await?.is_none());
+
+        // the price halved, sold at a loss past the daily limit
+        mock.add_bonding_curve(mint, 15_000_000_000);
+        let sell = executor
+            .sell(mint, u64::MAX, "StopLoss", now + 60)
+            .await?
+            .expect("no sell");
+        assert!(sell.realized_pnl.is_some_and(|p| p < -100_000_000));
+        assert!(executor.position(mint).await?.is_none());
+        assert!(executor.buy(wallet, other, 0.1, now + 120).await?.is_none());
+        // the next day
+        assert!(executor
+            .buy(wallet, other, 0.1, now + 86400)
+            .await?
+            .is_some());
+
+        let orders = ctx.manager.get_execution_orders(now).await?;
+        assert_eq!(orders.len(), 3);
+        assert_eq!(orders[1].reason, "StopLoss");
+        assert_eq!(orders[1].realized_pnl, sell.realized_pnl);
+        Ok(())
+    }
+
+    /// the order with `id` once it is no longer sent
+    async fn settled(manager: &ModelsManager, since: i64, id: i64) -> Result<ExecutionOrder> {
+        for _ in 0..100 {
+            let order = manager
+                .get_execution_orders(since)
+                .await?
+                .into_iter()
+                .find(|o| o.id == id)
+                .ok_or_else(|| anyhow!("No execution order: {}", id))?;
+            if order.status != EXECUTION_ORDER_SENT {
+                return Ok(order);
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        Err(anyhow!("Order {} not settled", id))
+    }
+
+    #[tokio::test]
+    async fn test_execution_confirm() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let (sender, _receiver) = alert_channel();
+        let ctx = mock.context(sender).await?;
+        let wallet = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x";
+        let mint = "7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump";
+        let other = "DezXAZ8z7PnrnRJjz2wXBoRgixCCWcnWFpSGJN2B263";
+        mock.add_bonding_curve(mint, 30_000_000_000);
+        mock.add_bonding_curve(other, 30_000_000_000);
+
+        // the restarted executor signs with the same wallet
+        let keypair_path = std::env::temp_dir().join(format!("{}.json", Pubkey::new_unique()));
+        write_keypair_file(&Keypair::new(), &keypair_path)
+            .map_err(|e| anyhow!("Failed to write keypair: {}", e))?;
+        let config = ExecutionConfig {
+            enabled: true,
+            dry_run: false,
+            keypair_path: keypair_path.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        let executor = Arc::new(Executor::new(
+            &config,
+            ctx.manager.clone(),
+            SolanaMonitor::new(&mock.wss_url, &mock.rpc_url),
+        )?);
+        let payer = executor.payer.pubkey().to_string();
+        let now = 1_734_616_564;
+
+        // recorded at the fill of the landed transaction, not the quote
+        let buy = executor.buy(wallet, mint, 0.1, now).await?.expect("no buy");
+        assert_eq!(buy.status, EXECUTION_ORDER_SENT);
+        assert_eq!(buy.last_valid_block_height, 100);
+        assert_eq!(mock.sent(), vec![buy.signature.clone()]);
+        assert!(executor.sell(mint, u64::MAX, "copy", now).await?.is_none());
+        let trade = TradeEventData {
+            mint: mint.to_string(),
+            sol_amount: 90_000_000,
+            token_amount: buy.token_amount as u64 - 1_000_000,
+            is_buy: true,
+            user: payer.clone(),
+            timestamp: now,
+            ..Default::default()
+        };
+        mock.confirm_transaction(&buy.signature, vec![trade_event_log(&trade)]);
+        let buy = settled(&ctx.manager, now, buy.id).await?;
+        assert_eq!(buy.status, EXECUTION_ORDER_CONFIRMED);
+        assert_eq!(buy.token_amount, trade.token_amount as i64);
+        assert_eq!(buy.sol_amount, 90_900_000);
+        let position = executor.position(mint).await?.expect("no position");
+        assert_eq!(position.status, EXECUTION_POSITION_OPEN);
+        assert_eq!(position.token_amount, buy.token_amount);
+        assert_eq!(position.cost, 90_900_000);
+
+        // no status past the last valid block height
+        mock.set_block_height(101);
+        let expired = executor
+            .buy(wallet, other, 0.1, now)
+            .await?
+            .expect("no buy");
+        let expired = settled(&ctx.manager, now, expired.id).await?;
+        assert_eq!(expired.status, EXECUTION_ORDER_FAILED);
+        assert!(executor.position(other).await?.is_none());
+
+        // a sell sent and a buy never sent before a restart
+        mock.set_block_height(0);
+        let mut sell = ExecutionOrder {
+            position_id: position.id,
+            token_amount: position.token_amount,
+            sol_amount: 100_000_000,
+            signature: Signature::new_unique().to_string(),
+            last_valid_block_height: 100,
+            status: EXECUTION_ORDER_SENT.to_string(),
+            reason: "copy".to_string(),
+            created_at: now,
+            ..Default::default()
+        };
+        sell.id = ctx.manager.add_execution_order(&sell).await?;
+        let pending = ctx
+            .manager
+            .add_execution_position(&ExecutionPosition {
+                wallet: wallet.to_string(),
+                mint: other.to_string(),
+                status: EXECUTION_POSITION_PENDING.to_string(),
+                opened_at: now,
+                ..Default::default()
+            })
+            .await?;
+        let trade = TradeEventData {
+            is_buy: false,
+            token_amount: position.token_amount as u64,
+            sol_amount: 101_000_000,
+            ..trade
+        };
+        mock.confirm_transaction(&sell.signature, vec![trade_event_log(&trade)]);
+        let restarted = Arc::new(Executor::new(
+            &config,
+            ctx.manager.clone(),
+            SolanaMonitor::new(&mock.wss_url, &mock.rpc_url),
+        )?);
+        restarted.recover().await?;
+        let sell = settled(&ctx.manager, now, sell.id).await?;
+        assert_eq!(sell.status, EXECUTION_ORDER_CONFIRMED);
+        assert_eq!(sell.sol_amount, 99_990_000);
+        assert_eq!(sell.realized_pnl, Some(99_990_000 - 90_900_000));
+        let failed = ctx.manager.get_execution_position(pending).await?;
+        assert!(failed.is_some_and(|p| p.status == EXECUTION_POSITION_FAILED));
+        assert!(restarted.positions().await?.is_empty());
+        std::fs::remove_file(keypair_path)?;
+        Ok(())
+    }
+}
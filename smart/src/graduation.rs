@@ -0,0 +1,320 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use anyhow::Result;
+use serde::Deserialize;
+use solana_client::rpc_response::{Response, RpcLogsResponse};
+use tokio::{sync::mpsc, task::JoinHandle};
+use tracing::{error, info};
+use validator::{Validate, ValidationError};
+
+use crate::{
+    alert::{send_alert, Alert, AlertKind},
+    models::CurveProgress,
+    strategies::{spawn_log_subscribe, MonitorContext, RecentSignatures, StrategyEvent},
+};
+
+/// rule names of the bonding curve alerts
+pub const GRADUATION_RULE: &str = "Graduation";
+pub const MIGRATION_RULE: &str = "Migration";
+
+#[derive(Clone, Debug, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+#[validate(schema(function = "validate_graduation_config"))]
+pub struct GraduationConfig {
+    pub enabled: bool,        // 持仓代币的 bonding curve 进度告警
+    pub thresholds: Vec<f64>, // progress percentages, each alerted once per mint
+    #[validate(range(min = 1))]
+    pub refresh_secs: u64, // how often the active spl_token mints are reloaded
+}
+
+impl Default for GraduationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            thresholds: vec![50.0, 80.0, 95.0],
+            refresh_secs: 60,
+        }
+    }
+}
+
+fn validate_graduation_config(config: &GraduationConfig) -> Result<(), ValidationError> {
+    if config
+        .thresholds
+        .iter()
+        .any(|t| !t.is_finite() || *t <= 0.0 || *t > 100.0)
+    {
+        return Err(ValidationError::new("thresholds")
+            .with_message("thresholds are percentages in (0, 100]".into()));
+    }
+    Ok(())
+}
+
+/// follow the bonding curves of the mints held by the monitors
+pub struct GraduationWatcher {
+    config: GraduationConfig,
+    progress: HashMap<String, CurveProgress>, // active mint -> progress alerted
+    subscriptions: HashMap<String, JoinHandle<()>>, // mint -> logs subscription
+}
+
+impl GraduationWatcher {
+    pub fn new(config: GraduationConfig) -> Self {
+        Self {
+            config,
+            progress: HashMap::new(),
+            subscriptions: HashMap::new(),
+        }
+    }
+
+    /// follow the curves of the active mints, stop following the others
+    pub async fn refresh(
+        &mut self,
+        ctx: &MonitorContext,
+        sender: &mpsc::Sender<Response<RpcLogsResponse>>,
+    ) -> Result<()> {
+        let active: HashSet<String> = ctx
+            .manager
+            .get_active_spl_mints()
+            .await?
+            .into_iter()
+            .collect();
+        self.progress.retain(|mint, _| active.contains(mint));
+        for mint in active {
+            if self.progress.contains_key(&mint) {
+                continue;
+            }
+            let progress = ctx
+                .manager
+                .get_curve_progress(&mint)
+                .await?
+                .unwrap_or_else(|| CurveProgress {
+                    mint: mint.clone(),
+                    ..Default::default()
+                });
+            self.progress.insert(mint, progress);
+        }
+        self.sync_subscriptions(ctx, sender);
+        Ok(())
+    }
+
+    fn unsubscribe_all(&mut self) {
+        for (_, handle) in self.subscriptions.drain() {
+            handle.abort();
+        }
+    }
+
+    fn sync_subscriptions(
+        &mut self,
+        ctx: &MonitorContext,
+        sender: &mpsc::Sender<Response<RpcLogsResponse>>,
+    ) {
+        // a completed curve has no trades anymore
+        let progress = &self.progress;
+        self.subscriptions.retain(|mint, handle| {
+            let keep = progress.get(mint).is_some_and(|p| !p.completed);
+            if !keep {
+                info!("stop watching the curve of {}", mint);
+                handle.abort();
+            }
+            keep
+        });
+        for (mint, p) in &self.progress {
+            if p.completed {
+                continue;
+            }
+            self.subscriptions
+                .entry(mint.clone())
+                .or_insert_with_key(|mint| {
+                    info!("watching the curve of {}, alerted {}%", mint, p.threshold);
+                    spawn_log_subscribe(mint, ctx, sender.clone())
+                });
+        }
+    }
+
+    /// alert on the thresholds crossed and the completion of a followed curve
+    pub async fn on_event(
+        &mut self,
+        ctx: &MonitorContext,
+        sender: &mpsc::Sender<Response<RpcLogsResponse>>,
+        event: &StrategyEvent,
+    ) -> Result<()> {
+        for trade in &event.trades {
+            let Some(p) = self.progress.get_mut(&trade.mint) else {
+                continue;
+            };
+            if p.completed {
+                continue;
+            }
+            let progress = trade.curve_progress();
+            // a trade jumping over several thresholds alerts the highest
+            let Some(threshold) = self
+                .config
+                .thresholds
+                .iter()
+                .copied()
+                .filter(|t| *t > p.threshold && progress >= *t)
+                .reduce(f64::max)
+            else {
+                continue;
+            };
+            p.threshold = threshold;
+            ctx.manager.upsert_curve_progress(p).await?;
+            send_alert(
+                &ctx.alerts,
+                Alert {
+                    address: trade.mint.clone(),
+                    rule: GRADUATION_RULE.to_string(),
+                    mint: trade.mint.clone(),
+                    signature: event.signature.clone(),
+                    kind: AlertKind::Graduation {
+                        threshold,
+                        progress,
+                        real_sol_reserves: trade.real_sol_reserves,
+                        price: trade.price(),
+                    },
+                    suppressed: 0,
+                },
+            )
+            .await;
+        }
+        for complete in &event.completes {
+            let Some(p) = self.progress.get_mut(&complete.mint) else {
+                continue;
+            };
+            if p.completed {
+                continue;
+            }
+            p.completed = true;
+            ctx.manager.upsert_curve_progress(p).await?;
+            send_alert(
+                &ctx.alerts,
+                Alert {
+                    address: complete.mint.clone(),
+                    rule: MIGRATION_RULE.to_string(),
+                    mint: complete.mint.clone(),
+                    signature: event.signature.clone(),
+                    kind: AlertKind::Migration {
+                        bonding_curve: complete.bonding_curve.clone(),
+                    },
+                    suppressed: 0,
+                },
+            )
+            .await;
+        }
+        self.sync_subscriptions(ctx, sender);
+        Ok(())
+    }
+}
+
+/// alert on the curve progress of the active mints, reloaded every `refresh_secs`
+pub async fn run_graduation_watcher(ctx: &MonitorContext, config: &GraduationConfig) -> Result<()> {
+    let (sender, mut receiver) = mpsc::channel::<Response<RpcLogsResponse>>(1000);
+    let mut watcher = GraduationWatcher::new(config.clone());
+    let mut recent = RecentSignatures::new(1000);
+    let mut refresh = tokio::time::interval(Duration::from_secs(config.refresh_secs));
+    loop {
+        tokio::select! {
+            log = ctx.next_log(&mut receiver) => {
+                let Some(log) = log else { break };
+                if !recent.insert(&log.value.signature) {
+                    continue;
+                }
+                let event = StrategyEvent::from(log);
+                if let Err(e) = watcher.on_event(ctx, &sender, &event).await {
+                    error!("graduation watcher error: {:?}, signature: {}", e, event.signature);
+                }
+            }
+            _ = refresh.tick(), if !ctx.is_shutdown() => {
+                if let Err(e) = watcher.refresh(ctx, &sender).await {
+                    error!("graduation watcher refresh error: {:?}", e);
+                }
+            }
+        }
+    }
+    watcher.unsubscribe_all();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alert::alert_channel, test_support::MockSolana};
+    use base64::{engine::general_purpose::STANDARD as base64, Engine};
+    use serde_json::json;
+    use solana_sdk::pubkey::Pubkey;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn test_graduation_alert() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let mint = "7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump";
+        // 13% then 59% of the curve sold
+        mock.push_logs_fixture(mint, "pump_buy_logs.json");
+        mock.push_logs_fixture(mint, "pump_other_buy_logs.json");
+        let curve = Pubkey::new_unique();
+        let mut data = vec![95, 114, 97, 156, 212, 46, 152, 8];
+        data.extend(Pubkey::new_unique().to_bytes());
+        data.extend(Pubkey::from_str(mint)?.to_bytes());
+        data.extend(curve.to_bytes());
+        data.extend(1_734_617_000i64.to_le_bytes());
+        mock.push_logs(
+            mint,
+            json!({
+                "context": {"slot": 308458000},
+                "value": {
+                    "signature": "complete",
+                    "err": null,
+                    "logs": [format!("Program data: {}", base64.encode(data))],
+                },
+            }),
+        );
+
+        let (alerts, mut alert_receiver) = alert_channel();
+        let ctx = mock.context(alerts).await?;
+        ctx.manager.add_new_spl_token(mint, "holder", "Buy").await?;
+        let config = GraduationConfig {
+            enabled: true,
+            thresholds: vec![50.0, 80.0],
+            refresh_secs: 60,
+        };
+        assert!(config.validate().is_ok());
+        let manager = ctx.manager.clone();
+        tokio::spawn(async move { run_graduation_watcher(&ctx, &config).await });
+
+        let alert = alert_receiver.recv().await.expect("no alert received");
+        assert_eq!(alert.rule, GRADUATION_RULE);
+        let AlertKind::Graduation {
+            threshold,
+            progress,
+            ..
+        } = alert.kind
+        else {
+            panic!("not a graduation alert");
+        };
+        assert_eq!(threshold, 50.0);
+        assert!((progress - 59.15).abs() < 0.01);
+
+        let alert = alert_receiver.recv().await.expect("no alert received");
+        assert_eq!(alert.rule, MIGRATION_RULE);
+        let AlertKind::Migration { bonding_curve } = alert.kind else {
+            panic!("not a migration alert");
+        };
+        assert_eq!(bonding_curve, curve.to_string());
+
+        let stored = manager
+            .get_curve_progress(mint)
+            .await?
+            .expect("no progress");
+        assert_eq!(stored.threshold, 50.0);
+        assert!(stored.completed);
+
+        let invalid = GraduationConfig {
+            thresholds: vec![120.0],
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+        Ok(())
+    }
+}
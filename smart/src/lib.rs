@@ -1,7 +1,21 @@
 pub mod abi;
+pub mod alert;
+pub mod backtest;
 pub mod config;
+pub mod cooldown;
 pub mod daemon;
+pub mod dev_watch;
+pub mod discovery;
+pub mod execution;
+pub mod graduation;
 pub mod models;
+pub mod reload;
+pub mod scoring;
+pub mod shutdown;
+pub mod sniper;
 pub mod sol_client;
 pub mod strategies;
+pub mod supervisor;
+#[cfg(test)]
+pub mod test_support;
 //pub mod web;
@@ -1,6 +1,6 @@
 use std::env;
 
-use smart::{config, daemon};
+use smart::{alert, backtest, config, daemon, discovery, models, scoring, sniper, sol_client, strategies};
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use tokio::fs;
@@ -23,6 +23,30 @@ struct Cli {
 enum Commands {
     Daemon, // daemon command
     Web, // web服务
+    Discover, // 从记录的交易中发现聪明钱包
+    Candidates {
+        #[arg(short, long, default_value = models::CANDIDATE_STATUS_PENDING)]
+        status: String,
+    }, // 查看候选钱包
+    Approve { address: String }, // 批准候选钱包, monitored from the next daemon start or reload
+    Reject { address: String }, // 拒绝候选钱包
+    Score, // 计算钱包评分
+    Snipers { mint: String }, // 查看代币的狙击和捆绑买入
+    Monitors, // 查看运行中的 daemon 的监控任务状态
+    Backtest {
+        #[arg(long)]
+        from: i64, // 回测开始时间, unix seconds
+        #[arg(long)]
+        to: Option<i64>, // 回测结束时间, defaults to now
+    }, // 用记录的交易回测配置中的监控规则
+    Paper {
+        #[arg(long, default_value_t = 0)]
+        since: i64, // 显示此时间之后的成交, unix seconds
+    }, // 查看模拟跟单的持仓和盈亏
+    Orders {
+        #[arg(long, default_value_t = 0)]
+        since: i64, // 显示此时间之后的订单, unix seconds
+    }, // 查看跟单交易的订单和持仓
 }
 
 #[tokio::main]
@@ -43,10 +67,172 @@ async fn main() -> Result<()> {
         Some(Commands::Web) => {
             // smart::web::start_server().await?;
         }
+        Some(Commands::Discover) => {
+            let known = c.monitors.iter().map(|m| m.address.clone()).collect();
+            let now = chrono::Utc::now().timestamp();
+            let manager = models::get_global_manager().await;
+            for w in discovery::discover_wallets(manager, &c.discovery, now, &known).await? {
+                println!("{} early in {}: {}", w.address, w.mints.len(), w.mints.join(","));
+            }
+        }
+        Some(Commands::Candidates { status }) => {
+            for w in models::get_global_manager().await.get_wallet_candidates(&status).await? {
+                println!("{} {} early in {}: {}", w.status, w.address, w.early_count, w.mints);
+            }
+        }
+        Some(Commands::Score) => {
+            let now = chrono::Utc::now().timestamp();
+            let manager = models::get_global_manager().await;
+            for wallet in c.watched_wallets() {
+                for s in scoring::score_wallet(manager, &c.scoring, &wallet, now).await? {
+                    println!("{:?}", s);
+                }
+            }
+        }
+        Some(Commands::Snipers { mint }) => {
+            let manager = models::get_global_manager().await;
+            let Some(report) = sniper::sniper_report(manager, &mint).await? else {
+                println!("launch of {} not seen", mint);
+                return Ok(());
+            };
+            for b in &report.buys {
+                println!("{} +{} slots {} tokens, funder: {:?}", b.user, b.slot_offset, b.token_amount, b.funder);
+            }
+            for c in &report.clusters {
+                println!("bundle in slot {} funded by {}: {}", c.slot, c.funder, c.users.join(","));
+            }
+            println!("sniped supply: {:.2}%, bundled supply: {:.2}%", report.sniped_supply(), report.bundled_supply());
+        }
+        Some(Commands::Monitors) => {
+            for s in models::get_global_manager().await.get_monitor_statuses().await? {
+                println!("{} {} {} {} restarts: {}, last error: {}", s.state, s.rule_type, s.target, s.address, s.restarts, s.last_error.as_deref().unwrap_or("-"));
+            }
+        }
+        Some(Commands::Backtest { from, to }) => {
+            let to = to.unwrap_or_else(|| chrono::Utc::now().timestamp());
+            let (alerts, _) = alert::alert_channel();
+            let shutdown = tokio::sync::watch::channel(false).1;
+            let ctx = strategies::MonitorContext::from_global(alerts, shutdown).await;
+            for a in backtest::run_backtest(&ctx, &c.monitors, from, to).await? {
+                let returns: Vec<String> = a
+                    .returns
+                    .iter()
+                    .map(|(h, r)| match r {
+                        Some(r) => format!("+{}s: {:.2}%", h, r * 100.0),
+                        None => format!("+{}s: -", h),
+                    })
+                    .collect();
+                println!(
+                    "{} {}, price: {:?}, {}",
+                    a.timestamp,
+                    a.alert,
+                    a.price,
+                    returns.join(", ")
+                );
+            }
+        }
+        Some(Commands::Paper { since }) => {
+            let manager = models::get_global_manager().await;
+            for f in manager.get_paper_fills(since).await? {
+                println!(
+                    "{} position {} {} {} tokens for {} lamports at {:.10} SOL, slippage {:.2}%, {}, pnl: {:?}",
+                    f.timestamp,
+                    f.position_id,
+                    if f.is_buy { "buy" } else { "sell" },
+                    f.token_amount,
+                    f.sol_amount,
+                    f.price,
+                    f.slippage,
+                    f.reason,
+                    f.realized_pnl
+                );
+            }
+            let open = manager
+                .get_paper_positions(None, models::PAPER_POSITION_OPEN)
+                .await?;
+            let mut unrealized = 0;
+            for p in &open {
+                // what selling the holding into the last recorded curve would bring
+                let value = match manager.get_last_trade(&p.mint).await? {
+                    Some(t) => {
+                        let curve =
+                            sol_client::curve::BondingCurve::from_trade(&t.to_trade_event());
+                        strategies::PaperFillQuote::sell(&curve, p.token_amount as u64).sol_amount
+                            as i64
+                    }
+                    None => 0,
+                };
+                unrealized += value - p.cost;
+                println!(
+                    "open {} copying {}: {} tokens, cost {} lamports, value {} lamports, realized {} lamports",
+                    p.mint, p.wallet, p.token_amount, p.cost, value, p.realized_pnl
+                );
+            }
+            let closed = manager
+                .get_paper_positions(None, models::PAPER_POSITION_CLOSED)
+                .await?;
+            let realized: i64 = open.iter().chain(&closed).map(|p| p.realized_pnl).sum();
+            println!(
+                "realized pnl: {} lamports, unrealized pnl: {} lamports",
+                realized, unrealized
+            );
+        }
+        Some(Commands::Orders { since }) => {
+            let manager = models::get_global_manager().await;
+            for o in manager.get_execution_orders(since).await? {
+                println!(
+                    "{} position {} {} {} tokens for {} lamports, limit {}, {}, {} {}, pnl: {:?}, error: {:?}",
+                    o.created_at,
+                    o.position_id,
+                    if o.is_buy { "buy" } else { "sell" },
+                    o.token_amount,
+                    o.sol_amount,
+                    o.sol_limit,
+                    o.reason,
+                    o.status,
+                    o.signature,
+                    o.realized_pnl,
+                    o.error
+                );
+            }
+            let dry_run = c.execution.dry_run;
+            for p in manager
+                .get_active_execution_positions(dry_run, None)
+                .await?
+            {
+                println!(
+                    "{} {} copying {}: {} tokens, cost {} lamports, realized {} lamports",
+                    p.status, p.mint, p.wallet, p.token_amount, p.cost, p.realized_pnl
+                );
+            }
+            println!(
+                "realized pnl: {} lamports, dry run: {}",
+                manager.get_execution_realized_pnl(dry_run, since).await?,
+                dry_run
+            );
+        }
+        Some(Commands::Approve { address }) => {
+            set_candidate_status(&address, models::CANDIDATE_STATUS_APPROVED).await?;
+        }
+        Some(Commands::Reject { address }) => {
+            set_candidate_status(&address, models::CANDIDATE_STATUS_REJECTED).await?;
+        }
         None => {
             println!("Please specify a subcommand");
         }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+async fn set_candidate_status(address: &str, status: &str) -> Result<()> {
+    address
+        .parse::<solana_sdk::pubkey::Pubkey>()
+        .map_err(|e| anyhow::anyhow!("Invalid address {}: {}", address, e))?;
+    let manager = models::get_global_manager().await;
+    if !manager.update_wallet_candidate_status(address, status).await? {
+        return Err(anyhow::anyhow!("No candidate: {}", address));
+    }
+    println!("{} {}", address, status);
+    Ok(())
+}
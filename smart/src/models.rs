@@ -1,11 +1,15 @@
 use std::sync::Arc;
 
 use anyhow::Result;
-use serde::Serialize;
-use sqlx::SqlitePool;
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
 use tokio::sync::OnceCell;
+use tracing::warn;
 
-use crate::config::get_global_config;
+use crate::{
+    config::get_global_config,
+    sol_client::{ui_amount, TradeEventData, PUMP_TOKEN_DECIMALS, SOL_DECIMALS},
+};
 
 pub struct ModelsManager {
     pool: SqlitePool,
@@ -14,6 +18,23 @@ impl ModelsManager {
     pub fn new(pool: SqlitePool) -> Self {
         Self { pool }
     }
+
+    /// a migrated in-memory database, e.g. the scratch state of a backtest
+    pub async fn open_memory() -> Result<Self> {
+        // every connection to sqlite::memory: opens its own database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+        let manager = Self::new(pool);
+        manager.migrate().await?;
+        Ok(manager)
+    }
+
+    /// wait for the queries in flight, then close the connections
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
 }
 
 pub static GLOBAL_MANAGER: OnceCell<Arc<ModelsManager>> = OnceCell::const_new();
@@ -31,18 +52,31 @@ pub async fn get_global_manager() -> &'static Arc<ModelsManager> {
         .await
 }
 
+impl ModelsManager {
+    /// run the migrations under `migrations/`
+    pub async fn migrate(&self) -> Result<()> {
+        sqlx::migrate!("../migrations").run(&self.pool).await?;
+        Ok(())
+    }
+}
+
 // -- create spl_token table in sqlite3
 // CREATE TABLE spl_token (
-//     mint TEXT PRIMARY KEY, -- mint address
+//     id INTEGER PRIMARY KEY AUTOINCREMENT,
+//     mint TEXT NOT NULL, -- mint address
 //     smart_address TEXT NOT NULL, -- smart address, who related to this token
 //     monitor_status TEXT NOT NULL DEFAULT 'active', -- monitor status
 //     strategy_name TEXT NOT NULL, -- strategy name
-//     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, -- created at
-//     updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP -- updated at
+//     created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')), -- created at
+//     updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')) -- updated at
 // );
 
+pub const MONITOR_STATUS_ACTIVE: &str = "active";
+pub const MONITOR_STATUS_CLOSED: &str = "closed";
+
 #[derive(Debug, sqlx::FromRow, Serialize)]
 pub struct SplToken {
+    pub id: i64,
     pub mint: String,
     pub smart_address: String,
     pub monitor_status: String,
@@ -81,4 +115,1011 @@ impl ModelsManager {
         sqlx::query(&sql_str).execute(&self.pool).await?;
         Ok(())
     }
+    pub async fn get_spl_tokens(
+        &self,
+        smart_address: &str,
+        strategy_name: &str,
+        monitor_status: &str,
+    ) -> Result<Vec<SplToken>> {
+        let sql_str = format!(
+            "SELECT * FROM spl_token WHERE smart_address = '{}'
+            AND strategy_name = '{}'
+            AND monitor_status = '{}'",
+            smart_address, strategy_name, monitor_status
+        );
+        let rows = sqlx::query_as::<_, SplToken>(&sql_str)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    /// the mints held by any monitor
+    pub async fn get_active_spl_mints(&self) -> Result<Vec<String>> {
+        let sql_str = format!(
+            "SELECT DISTINCT mint FROM spl_token WHERE monitor_status = '{}'",
+            MONITOR_STATUS_ACTIVE
+        );
+        let rows: Vec<(String,)> = sqlx::query_as(&sql_str).fetch_all(&self.pool).await?;
+        Ok(rows.into_iter().map(|(mint,)| mint).collect())
+    }
+
+    pub async fn update_spl_token_status(
+        &self,
+        mint: &str,
+        smart_address: &str,
+        strategy_name: &str,
+        monitor_status: &str,
+    ) -> Result<()> {
+        let sql_str = format!(
+            "UPDATE spl_token SET monitor_status = '{}', updated_at = strftime('%s', 'now')
+            WHERE mint = '{}'
+            AND smart_address = '{}'
+            AND strategy_name = '{}'
+            AND monitor_status != '{}'",
+            monitor_status, mint, smart_address, strategy_name, monitor_status
+        );
+        sqlx::query(&sql_str).execute(&self.pool).await?;
+        Ok(())
+    }
+}
+
+pub const CANDIDATE_STATUS_PENDING: &str = "pending";
+pub const CANDIDATE_STATUS_APPROVED: &str = "approved";
+pub const CANDIDATE_STATUS_REJECTED: &str = "rejected";
+
+#[derive(Debug, sqlx::FromRow, Serialize)]
+pub struct TradeRecord {
+    pub id: i64,
+    pub signature: String,
+    pub mint: String,
+    pub user: String,
+    pub is_buy: bool,
+    pub sol_amount: i64,
+    pub token_amount: i64,
+    pub timestamp: i64,
+    pub virtual_sol_reserves: i64,
+    pub virtual_token_reserves: i64,
+    pub real_sol_reserves: i64,
+    pub real_token_reserves: i64,
+}
+
+impl TradeRecord {
+    /// the trade as parsed from the transaction logs
+    pub fn to_trade_event(&self) -> TradeEventData {
+        TradeEventData {
+            mint: self.mint.clone(),
+            sol_amount: self.sol_amount as u64,
+            token_amount: self.token_amount as u64,
+            is_buy: self.is_buy,
+            user: self.user.clone(),
+            timestamp: self.timestamp,
+            virtual_sol_reserves: self.virtual_sol_reserves as u64,
+            virtual_token_reserves: self.virtual_token_reserves as u64,
+            real_sol_reserves: self.real_sol_reserves as u64,
+            real_token_reserves: self.real_token_reserves as u64,
+        }
+    }
+
+    /// token price in SOL after this trade
+    pub fn price(&self) -> f64 {
+        if self.virtual_token_reserves == 0 {
+            return 0.0;
+        }
+        ui_amount(self.virtual_sol_reserves as u64, SOL_DECIMALS)
+            / ui_amount(self.virtual_token_reserves as u64, PUMP_TOKEN_DECIMALS)
+    }
+}
+
+#[derive(Debug, sqlx::FromRow, Serialize)]
+pub struct WalletCandidate {
+    pub id: i64,
+    pub address: String,
+    pub early_count: i64,
+    pub mints: String,
+    pub status: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl ModelsManager {
+    /// store the trades of a transaction, a trade already stored is ignored
+    pub async fn add_trade_events(&self, signature: &str, trades: &[TradeEventData]) -> Result<()> {
+        for t in trades {
+            let sql_str = format!(
+                "INSERT OR IGNORE INTO trade_event (signature, mint, user, is_buy, sol_amount,
+                token_amount, timestamp, virtual_sol_reserves, virtual_token_reserves,
+                real_sol_reserves, real_token_reserves)
+                VALUES ('{}', '{}', '{}', {}, {}, {}, {}, {}, {}, {}, {})",
+                signature,
+                t.mint,
+                t.user,
+                t.is_buy as i64,
+                t.sol_amount,
+                t.token_amount,
+                t.timestamp,
+                t.virtual_sol_reserves,
+                t.virtual_token_reserves,
+                t.real_sol_reserves,
+                t.real_token_reserves
+            );
+            sqlx::query(&sql_str).execute(&self.pool).await?;
+        }
+        Ok(())
+    }
+
+    /// mints traded since `since` that graduated (the curve sold out) or reached `market_cap` SOL
+    pub async fn get_winner_mints(&self, since: i64, market_cap: f64) -> Result<Vec<String>> {
+        // market cap in SOL = price * 1e9 tokens = vsol / 1e9 / (vtok / 1e6) * 1e9
+        let sql_str = format!(
+            "SELECT mint FROM trade_event WHERE timestamp >= {}
+            GROUP BY mint
+            HAVING MIN(real_token_reserves) = 0
+            OR MAX(CAST(virtual_sol_reserves AS REAL) * 1000000.0 / virtual_token_reserves) >= {}
+            ORDER BY MIN(timestamp)",
+            since, market_cap
+        );
+        let rows: Vec<(String,)> = sqlx::query_as(&sql_str).fetch_all(&self.pool).await?;
+        Ok(rows.into_iter().map(|(mint,)| mint).collect())
+    }
+
+    /// the first `limit` distinct buyers of `mint`
+    pub async fn get_early_buyers(&self, mint: &str, limit: usize) -> Result<Vec<String>> {
+        let sql_str = format!(
+            "SELECT user FROM trade_event WHERE mint = '{}' AND is_buy = 1
+            GROUP BY user
+            ORDER BY MIN(timestamp), MIN(id)
+            LIMIT {}",
+            mint, limit
+        );
+        let rows: Vec<(String,)> = sqlx::query_as(&sql_str).fetch_all(&self.pool).await?;
+        Ok(rows.into_iter().map(|(user,)| user).collect())
+    }
+
+    /// add a pending candidate, or refresh the stats of an existing one and keep its status
+    pub async fn upsert_wallet_candidate(
+        &self,
+        address: &str,
+        early_count: i64,
+        mints: &str,
+    ) -> Result<()> {
+        let sql_str = format!(
+            "INSERT INTO wallet_candidate (address, early_count, mints)
+            VALUES ('{}', {}, '{}')
+            ON CONFLICT (address) DO UPDATE SET early_count = excluded.early_count,
+            mints = excluded.mints, updated_at = strftime('%s', 'now')",
+            address, early_count, mints
+        );
+        sqlx::query(&sql_str).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    pub async fn get_wallet_candidates(&self, status: &str) -> Result<Vec<WalletCandidate>> {
+        // the status comes from the command line
+        let sql_str = "SELECT * FROM wallet_candidate WHERE status = ?
+            ORDER BY early_count DESC, address";
+        let rows = sqlx::query_as::<_, WalletCandidate>(sql_str)
+            .bind(status)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    /// returns false if there is no such candidate
+    pub async fn update_wallet_candidate_status(
+        &self,
+        address: &str,
+        status: &str,
+    ) -> Result<bool> {
+        // the address comes from the command line
+        let sql_str = format!(
+            "UPDATE wallet_candidate SET status = '{}', updated_at = strftime('%s', 'now')
+            WHERE address = ?",
+            status
+        );
+        let result = sqlx::query(&sql_str)
+            .bind(address)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow, Serialize)]
+pub struct WalletScore {
+    pub wallet: String,
+    pub window_secs: i64,
+    pub realized_pnl: f64,   // SOL
+    pub unrealized_pnl: f64, // SOL, holdings at the last recorded price
+    pub win_rate: Option<f64>,
+    pub median_roi: Option<f64>,
+    pub avg_hold_secs: Option<f64>,
+    pub trade_count: i64,
+    pub computed_at: i64,
+}
+
+impl ModelsManager {
+    /// the trades of `user` since `since`, oldest first
+    pub async fn get_user_trades(&self, user: &str, since: i64) -> Result<Vec<TradeRecord>> {
+        let sql_str = format!(
+            "SELECT * FROM trade_event WHERE user = '{}' AND timestamp >= {}
+            ORDER BY timestamp, id",
+            user, since
+        );
+        let rows = sqlx::query_as::<_, TradeRecord>(&sql_str)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    /// at most `limit` trades in `[from, to]` after the `(timestamp, id)` of `after`,
+    /// in the order they were recorded
+    pub async fn get_trades_between(
+        &self,
+        from: i64,
+        to: i64,
+        after: Option<(i64, i64)>,
+        limit: i64,
+    ) -> Result<Vec<TradeRecord>> {
+        let (after_timestamp, after_id) = after.unwrap_or((from, 0));
+        let sql_str = format!(
+            "SELECT * FROM trade_event WHERE timestamp >= {} AND timestamp <= {}
+            AND (timestamp > {} OR (timestamp = {} AND id > {}))
+            ORDER BY timestamp, id LIMIT {}",
+            from, to, after_timestamp, after_timestamp, after_id, limit
+        );
+        let rows = sqlx::query_as::<_, TradeRecord>(&sql_str)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    /// the trades of `mint` in `[from, to]`, in the order they were recorded
+    pub async fn get_mint_trades_between(
+        &self,
+        mint: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<TradeRecord>> {
+        let sql_str = format!(
+            "SELECT * FROM trade_event WHERE mint = '{}' AND timestamp >= {} AND timestamp <= {}
+            ORDER BY timestamp, id",
+            mint, from, to
+        );
+        let rows = sqlx::query_as::<_, TradeRecord>(&sql_str)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    /// the time of the last trade in `[from, to]`
+    pub async fn get_last_trade_time(&self, from: i64, to: i64) -> Result<Option<i64>> {
+        let sql_str = format!(
+            "SELECT MAX(timestamp) FROM trade_event WHERE timestamp >= {} AND timestamp <= {}",
+            from, to
+        );
+        let row: (Option<i64>,) = sqlx::query_as(&sql_str).fetch_one(&self.pool).await?;
+        Ok(row.0)
+    }
+
+    /// the latest recorded trade of `mint`
+    pub async fn get_last_trade(&self, mint: &str) -> Result<Option<TradeRecord>> {
+        let sql_str = format!(
+            "SELECT * FROM trade_event WHERE mint = '{}'
+            ORDER BY timestamp DESC, id DESC LIMIT 1",
+            mint
+        );
+        let row = sqlx::query_as::<_, TradeRecord>(&sql_str)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row)
+    }
+
+    pub async fn upsert_wallet_score(&self, score: &WalletScore) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO wallet_score (wallet, window_secs, realized_pnl, unrealized_pnl,
+            win_rate, median_roi, avg_hold_secs, trade_count, computed_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (wallet, window_secs) DO UPDATE SET
+            realized_pnl = excluded.realized_pnl, unrealized_pnl = excluded.unrealized_pnl,
+            win_rate = excluded.win_rate, median_roi = excluded.median_roi,
+            avg_hold_secs = excluded.avg_hold_secs, trade_count = excluded.trade_count,
+            computed_at = excluded.computed_at",
+        )
+        .bind(&score.wallet)
+        .bind(score.window_secs)
+        .bind(score.realized_pnl)
+        .bind(score.unrealized_pnl)
+        .bind(score.win_rate)
+        .bind(score.median_roi)
+        .bind(score.avg_hold_secs)
+        .bind(score.trade_count)
+        .bind(score.computed_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_wallet_score(
+        &self,
+        wallet: &str,
+        window_secs: i64,
+    ) -> Result<Option<WalletScore>> {
+        let sql_str = format!(
+            "SELECT wallet, window_secs, realized_pnl, unrealized_pnl, win_rate, median_roi,
+            avg_hold_secs, trade_count, computed_at
+            FROM wallet_score WHERE wallet = '{}' AND window_secs = {}",
+            wallet, window_secs
+        );
+        let row = sqlx::query_as::<_, WalletScore>(&sql_str)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow, Serialize)]
+pub struct PositionLot {
+    pub wallet: String,
+    pub mint: String,
+    pub signature: String,
+    pub is_buy: bool,
+    pub sol_amount: i64,   // lamports into or out of the bonding curve
+    pub token_amount: i64, // raw units
+    pub pump_fee: i64,     // lamports
+    pub network_fee: i64,  // lamports
+    pub timestamp: i64,
+}
+
+impl ModelsManager {
+    /// store a lot, a lot already stored is ignored
+    pub async fn add_position_lot(&self, lot: &PositionLot) -> Result<()> {
+        let sql_str = format!(
+            "INSERT OR IGNORE INTO position_lot (wallet, mint, signature, is_buy, sol_amount,
+            token_amount, pump_fee, network_fee, timestamp)
+            VALUES ('{}', '{}', '{}', {}, {}, {}, {}, {}, {})",
+            lot.wallet,
+            lot.mint,
+            lot.signature,
+            lot.is_buy as i64,
+            lot.sol_amount,
+            lot.token_amount,
+            lot.pump_fee,
+            lot.network_fee,
+            lot.timestamp
+        );
+        sqlx::query(&sql_str).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// the lots of `wallet` in `mint`, oldest first
+    pub async fn get_position_lots(&self, wallet: &str, mint: &str) -> Result<Vec<PositionLot>> {
+        let sql_str = format!(
+            "SELECT wallet, mint, signature, is_buy, sol_amount, token_amount, pump_fee,
+            network_fee, timestamp
+            FROM position_lot WHERE wallet = '{}' AND mint = '{}'
+            ORDER BY timestamp, id",
+            wallet, mint
+        );
+        let rows = sqlx::query_as::<_, PositionLot>(&sql_str)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow, Serialize)]
+pub struct PositionWatermark {
+    pub wallet: String,
+    pub mint: String,
+    pub strategy_name: String,
+    pub entry_price: f64,
+    pub high_price: f64,
+    pub take_profit_fired: bool,
+    pub stop_loss_fired: bool,
+    pub trailing_stop_fired: bool,
+}
+
+impl ModelsManager {
+    pub async fn upsert_position_watermark(&self, w: &PositionWatermark) -> Result<()> {
+        let sql_str = format!(
+            "INSERT INTO position_watermark (wallet, mint, strategy_name, entry_price, high_price,
+            take_profit_fired, stop_loss_fired, trailing_stop_fired)
+            VALUES ('{}', '{}', '{}', {}, {}, {}, {}, {})
+            ON CONFLICT (wallet, mint, strategy_name) DO UPDATE SET
+            entry_price = excluded.entry_price, high_price = excluded.high_price,
+            take_profit_fired = excluded.take_profit_fired,
+            stop_loss_fired = excluded.stop_loss_fired,
+            trailing_stop_fired = excluded.trailing_stop_fired,
+            updated_at = strftime('%s', 'now')",
+            w.wallet,
+            w.mint,
+            w.strategy_name,
+            w.entry_price,
+            w.high_price,
+            w.take_profit_fired as i64,
+            w.stop_loss_fired as i64,
+            w.trailing_stop_fired as i64
+        );
+        sqlx::query(&sql_str).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    pub async fn get_position_watermarks(
+        &self,
+        wallet: &str,
+        strategy_name: &str,
+    ) -> Result<Vec<PositionWatermark>> {
+        let sql_str = format!(
+            "SELECT wallet, mint, strategy_name, entry_price, high_price, take_profit_fired,
+            stop_loss_fired, trailing_stop_fired
+            FROM position_watermark WHERE wallet = '{}' AND strategy_name = '{}'",
+            wallet, strategy_name
+        );
+        let rows = sqlx::query_as::<_, PositionWatermark>(&sql_str)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    pub async fn delete_position_watermark(
+        &self,
+        wallet: &str,
+        mint: &str,
+        strategy_name: &str,
+    ) -> Result<()> {
+        let sql_str = format!(
+            "DELETE FROM position_watermark
+            WHERE wallet = '{}' AND mint = '{}' AND strategy_name = '{}'",
+            wallet, mint, strategy_name
+        );
+        sqlx::query(&sql_str).execute(&self.pool).await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow, Serialize)]
+pub struct TokenLaunch {
+    pub mint: String,
+    pub creator: String,
+    pub slot: i64,
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow, Serialize)]
+pub struct SniperBuy {
+    pub mint: String,
+    pub signature: String,
+    pub user: String,
+    pub slot: i64,
+    pub slot_offset: i64,       // slots after the creation slot
+    pub sol_amount: i64,        // lamports
+    pub token_amount: i64,      // raw units
+    pub funder: Option<String>, // payer of the buyer's first transaction
+}
+
+impl ModelsManager {
+    /// store a launch, a launch already stored is ignored
+    pub async fn add_token_launch(&self, launch: &TokenLaunch) -> Result<()> {
+        let sql_str = format!(
+            "INSERT OR IGNORE INTO token_launch (mint, creator, slot, signature)
+            VALUES ('{}', '{}', {}, '{}')",
+            launch.mint, launch.creator, launch.slot, launch.signature
+        );
+        sqlx::query(&sql_str).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    pub async fn get_token_launch(&self, mint: &str) -> Result<Option<TokenLaunch>> {
+        // the mint comes from the command line
+        let sql_str = "SELECT mint, creator, slot, signature FROM token_launch WHERE mint = ?";
+        let row = sqlx::query_as::<_, TokenLaunch>(sql_str)
+            .bind(mint)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row)
+    }
+
+    /// store a sniper buy, a buy already stored is ignored
+    pub async fn add_sniper_buy(&self, buy: &SniperBuy) -> Result<()> {
+        let sql_str = format!(
+            "INSERT OR IGNORE INTO sniper_buy (mint, signature, user, slot, slot_offset,
+            sol_amount, token_amount, funder)
+            VALUES ('{}', '{}', '{}', {}, {}, {}, {}, ?)",
+            buy.mint,
+            buy.signature,
+            buy.user,
+            buy.slot,
+            buy.slot_offset,
+            buy.sol_amount,
+            buy.token_amount
+        );
+        sqlx::query(&sql_str)
+            .bind(&buy.funder)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// set the funder looked up after the buy was stored
+    pub async fn set_sniper_buy_funder(
+        &self,
+        signature: &str,
+        user: &str,
+        funder: &str,
+    ) -> Result<()> {
+        let sql_str = format!(
+            "UPDATE sniper_buy SET funder = '{}' WHERE signature = '{}' AND user = '{}'",
+            funder, signature, user
+        );
+        sqlx::query(&sql_str).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// the sniper buys of `mint`, in slot order
+    pub async fn get_sniper_buys(&self, mint: &str) -> Result<Vec<SniperBuy>> {
+        // the mint comes from the command line
+        let sql_str = "SELECT mint, signature, user, slot, slot_offset, sol_amount, token_amount,
+            funder FROM sniper_buy WHERE mint = ? ORDER BY slot, id";
+        let rows = sqlx::query_as::<_, SniperBuy>(sql_str)
+            .bind(mint)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow, Serialize)]
+pub struct CurveProgress {
+    pub mint: String,
+    pub threshold: f64, // highest threshold alerted, percent
+    pub completed: bool,
+}
+
+impl ModelsManager {
+    pub async fn upsert_curve_progress(&self, p: &CurveProgress) -> Result<()> {
+        let sql_str = format!(
+            "INSERT INTO curve_progress (mint, threshold, completed) VALUES ('{}', {}, {})
+            ON CONFLICT (mint) DO UPDATE SET
+            threshold = excluded.threshold, completed = excluded.completed,
+            updated_at = strftime('%s', 'now')",
+            p.mint, p.threshold, p.completed as i64
+        );
+        sqlx::query(&sql_str).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    pub async fn get_curve_progress(&self, mint: &str) -> Result<Option<CurveProgress>> {
+        let sql_str = format!(
+            "SELECT mint, threshold, completed FROM curve_progress WHERE mint = '{}'",
+            mint
+        );
+        let row = sqlx::query_as::<_, CurveProgress>(&sql_str)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row)
+    }
+}
+
+pub const MONITOR_STATE_RUNNING: &str = "running";
+pub const MONITOR_STATE_RESTARTING: &str = "restarting";
+pub const MONITOR_STATE_FAILED: &str = "failed";
+pub const MONITOR_STATE_STOPPED: &str = "stopped";
+
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow, Serialize)]
+pub struct MonitorStatus {
+    pub address: String,
+    pub rule_type: String,
+    pub target: String,
+    pub state: String,
+    pub restarts: i64, // restarts since the monitor last ran healthy
+    pub last_error: Option<String>,
+    pub updated_at: i64,
+}
+
+impl ModelsManager {
+    pub async fn upsert_monitor_status(&self, s: &MonitorStatus) -> Result<()> {
+        // the address of a consensus group is its name, free text
+        let sql_str =
+            "INSERT INTO monitor_status (address, rule_type, target, state, restarts, last_error)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT (address, rule_type, target) DO UPDATE SET
+            state = excluded.state, restarts = excluded.restarts, last_error = excluded.last_error,
+            updated_at = strftime('%s', 'now')";
+        sqlx::query(sql_str)
+            .bind(&s.address)
+            .bind(&s.rule_type)
+            .bind(&s.target)
+            .bind(&s.state)
+            .bind(s.restarts)
+            .bind(&s.last_error)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_monitor_statuses(&self) -> Result<Vec<MonitorStatus>> {
+        let sql_str = "SELECT address, rule_type, target, state, restarts, last_error, updated_at
+            FROM monitor_status ORDER BY address, rule_type, target";
+        let rows = sqlx::query_as::<_, MonitorStatus>(sql_str)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    /// forget the monitors of an earlier run
+    pub async fn clear_monitor_statuses(&self) -> Result<()> {
+        sqlx::query("DELETE FROM monitor_status")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// the key of a saved monitor state
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckpointKey<'a> {
+    pub address: &'a str,
+    pub rule_type: &'a str,
+    pub target: &'a str,
+    pub name: &'a str, // the saved part, e.g. the strategy or its cooldown
+}
+
+impl ModelsManager {
+    pub async fn save_checkpoint<T: Serialize>(
+        &self,
+        key: &CheckpointKey<'_>,
+        state: &T,
+    ) -> Result<()> {
+        // a group name is free text
+        let sql_str = "INSERT INTO strategy_checkpoint (address, rule_type, target, name, state)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (address, rule_type, target, name) DO UPDATE SET
+            state = excluded.state, updated_at = strftime('%s', 'now')";
+        sqlx::query(sql_str)
+            .bind(key.address)
+            .bind(key.rule_type)
+            .bind(key.target)
+            .bind(key.name)
+            .bind(serde_json::to_string(state)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// remove and return the saved state, a crash after it was taken starts empty.
+    ///
+    /// a state that no longer parses, e.g. saved by an older version, is dropped
+    pub async fn take_checkpoint<T: DeserializeOwned>(
+        &self,
+        key: &CheckpointKey<'_>,
+    ) -> Result<Option<T>> {
+        let sql_str = "DELETE FROM strategy_checkpoint
+            WHERE address = ? AND rule_type = ? AND target = ? AND name = ?
+            RETURNING state";
+        let state: Option<String> = sqlx::query_scalar(sql_str)
+            .bind(key.address)
+            .bind(key.rule_type)
+            .bind(key.target)
+            .bind(key.name)
+            .fetch_optional(&self.pool)
+            .await?;
+        let Some(state) = state else {
+            return Ok(None);
+        };
+        match serde_json::from_str(&state) {
+            Ok(state) => Ok(Some(state)),
+            Err(e) => {
+                warn!("checkpoint dropped: {:?}, key: {:?}", e, key);
+                Ok(None)
+            }
+        }
+    }
+}
+
+pub const PAPER_POSITION_OPEN: &str = "open";
+pub const PAPER_POSITION_CLOSED: &str = "closed";
+/// `paper_fill.reason` of the fills following the wallet, the exits name their rule
+pub const PAPER_FILL_COPY: &str = "copy";
+
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow, Serialize)]
+pub struct PaperPosition {
+    pub id: i64,
+    pub wallet: String,
+    pub mint: String,
+    pub token_amount: i64, // held, raw units
+    pub cost: i64,         // lamports paid for the holding, fees included
+    pub realized_pnl: i64, // lamports
+    pub entry_price: f64,
+    pub high_price: f64,
+    pub status: String,
+    pub opened_at: i64,
+    pub closed_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow, Serialize)]
+pub struct PaperFill {
+    pub id: i64,
+    pub position_id: i64,
+    pub signature: String,
+    pub is_buy: bool,
+    pub sol_amount: i64, // lamports paid or received, fee included
+    pub token_amount: i64,
+    pub fee: i64,
+    pub price: f64,    // average fill price in SOL
+    pub slippage: f64, // percent
+    pub reason: String,
+    pub realized_pnl: Option<i64>,
+    pub timestamp: i64,
+}
+
+impl ModelsManager {
+    /// store a new position, returns its id
+    pub async fn add_paper_position(&self, p: &PaperPosition) -> Result<i64> {
+        let sql_str = format!(
+            "INSERT INTO paper_position (wallet, mint, token_amount, cost, realized_pnl,
+            entry_price, high_price, status, opened_at)
+            VALUES ('{}', '{}', {}, {}, {}, {}, {}, '{}', {})",
+            p.wallet,
+            p.mint,
+            p.token_amount,
+            p.cost,
+            p.realized_pnl,
+            p.entry_price,
+            p.high_price,
+            p.status,
+            p.opened_at
+        );
+        let result = sqlx::query(&sql_str).execute(&self.pool).await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn update_paper_position(&self, p: &PaperPosition) -> Result<()> {
+        let sql_str = format!(
+            "UPDATE paper_position SET token_amount = {}, cost = {}, realized_pnl = {},
+            entry_price = {}, high_price = {}, status = '{}', closed_at = ?
+            WHERE id = {}",
+            p.token_amount, p.cost, p.realized_pnl, p.entry_price, p.high_price, p.status, p.id
+        );
+        sqlx::query(&sql_str)
+            .bind(p.closed_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// the positions in `status`, of every wallet if `wallet` is None
+    pub async fn get_paper_positions(
+        &self,
+        wallet: Option<&str>,
+        status: &str,
+    ) -> Result<Vec<PaperPosition>> {
+        let mut sql_str = format!("SELECT * FROM paper_position WHERE status = '{}'", status);
+        if let Some(wallet) = wallet {
+            sql_str.push_str(&format!(" AND wallet = '{}'", wallet));
+        }
+        sql_str.push_str(" ORDER BY opened_at, id");
+        let rows = sqlx::query_as::<_, PaperPosition>(&sql_str)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    pub async fn add_paper_fill(&self, f: &PaperFill) -> Result<()> {
+        let sql_str = format!(
+            "INSERT INTO paper_fill (position_id, signature, is_buy, sol_amount, token_amount,
+            fee, price, slippage, reason, realized_pnl, timestamp)
+            VALUES ({}, '{}', {}, {}, {}, {}, {}, {}, '{}', ?, {})",
+            f.position_id,
+            f.signature,
+            f.is_buy as i64,
+            f.sol_amount,
+            f.token_amount,
+            f.fee,
+            f.price,
+            f.slippage,
+            f.reason,
+            f.timestamp
+        );
+        sqlx::query(&sql_str)
+            .bind(f.realized_pnl)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// the fills since `since`, oldest first
+    pub async fn get_paper_fills(&self, since: i64) -> Result<Vec<PaperFill>> {
+        let sql_str = format!(
+            "SELECT * FROM paper_fill WHERE timestamp >= {} ORDER BY timestamp, id",
+            since
+        );
+        let rows = sqlx::query_as::<_, PaperFill>(&sql_str)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+}
+
+pub const EXECUTION_POSITION_PENDING: &str = "pending";
+pub const EXECUTION_POSITION_OPEN: &str = "open";
+pub const EXECUTION_POSITION_CLOSED: &str = "closed";
+pub const EXECUTION_POSITION_FAILED: &str = "failed";
+pub const EXECUTION_ORDER_DRY_RUN: &str = "dry_run";
+pub const EXECUTION_ORDER_SENT: &str = "sent";
+pub const EXECUTION_ORDER_CONFIRMED: &str = "confirmed";
+pub const EXECUTION_ORDER_FAILED: &str = "failed";
+
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow, Serialize)]
+pub struct ExecutionPosition {
+    pub id: i64,
+    pub wallet: String,
+    pub mint: String,
+    pub token_amount: i64, // held, raw units
+    pub cost: i64,         // lamports paid for the holding, fees included
+    pub realized_pnl: i64, // lamports
+    pub dry_run: bool,
+    pub status: String,
+    pub opened_at: i64,
+    pub closed_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow, Serialize)]
+pub struct ExecutionOrder {
+    pub id: i64,
+    pub position_id: i64,
+    pub is_buy: bool,
+    pub token_amount: i64, // quoted until the fill is confirmed
+    pub sol_amount: i64, // lamports paid or received, fee included, quoted until the fill is confirmed
+    pub sol_limit: i64,  // max_sol_cost of a buy, min_sol_output of a sell
+    pub signature: String,
+    pub last_valid_block_height: i64, // the transaction can't land after this block
+    pub status: String,
+    pub reason: String,
+    pub realized_pnl: Option<i64>,
+    pub error: Option<String>,
+    pub created_at: i64,
+}
+
+impl ModelsManager {
+    /// store a new position, returns its id
+    pub async fn add_execution_position(&self, p: &ExecutionPosition) -> Result<i64> {
+        let sql_str = format!(
+            "INSERT INTO execution_position (wallet, mint, token_amount, cost, realized_pnl,
+            dry_run, status, opened_at)
+            VALUES ('{}', '{}', {}, {}, {}, {}, '{}', {})",
+            p.wallet,
+            p.mint,
+            p.token_amount,
+            p.cost,
+            p.realized_pnl,
+            p.dry_run as i64,
+            p.status,
+            p.opened_at
+        );
+        let result = sqlx::query(&sql_str).execute(&self.pool).await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn update_execution_position(&self, p: &ExecutionPosition) -> Result<()> {
+        let sql_str = format!(
+            "UPDATE execution_position SET token_amount = {}, cost = {}, realized_pnl = {},
+            status = '{}', closed_at = ? WHERE id = {}",
+            p.token_amount, p.cost, p.realized_pnl, p.status, p.id
+        );
+        sqlx::query(&sql_str)
+            .bind(p.closed_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_execution_position(&self, id: i64) -> Result<Option<ExecutionPosition>> {
+        let sql_str = format!("SELECT * FROM execution_position WHERE id = {}", id);
+        let row = sqlx::query_as::<_, ExecutionPosition>(&sql_str)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row)
+    }
+
+    /// the pending and open positions, of one mint if `mint` is set
+    pub async fn get_active_execution_positions(
+        &self,
+        dry_run: bool,
+        mint: Option<&str>,
+    ) -> Result<Vec<ExecutionPosition>> {
+        let mut sql_str = format!(
+            "SELECT * FROM execution_position WHERE status IN ('{}', '{}') AND dry_run = {}",
+            EXECUTION_POSITION_PENDING, EXECUTION_POSITION_OPEN, dry_run as i64
+        );
+        if let Some(mint) = mint {
+            sql_str.push_str(&format!(" AND mint = '{}'", mint));
+        }
+        sql_str.push_str(" ORDER BY opened_at, id");
+        let rows = sqlx::query_as::<_, ExecutionPosition>(&sql_str)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    /// store a new order, returns its id
+    pub async fn add_execution_order(&self, o: &ExecutionOrder) -> Result<i64> {
+        let sql_str = format!(
+            "INSERT INTO execution_order (position_id, is_buy, token_amount, sol_amount, sol_limit,
+            signature, last_valid_block_height, status, reason, realized_pnl, error, created_at)
+            VALUES ({}, {}, {}, {}, {}, '{}', {}, '{}', '{}', ?, ?, {})",
+            o.position_id,
+            o.is_buy as i64,
+            o.token_amount,
+            o.sol_amount,
+            o.sol_limit,
+            o.signature,
+            o.last_valid_block_height,
+            o.status,
+            o.reason,
+            o.created_at
+        );
+        let result = sqlx::query(&sql_str)
+            .bind(o.realized_pnl)
+            .bind(&o.error)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn update_execution_order(&self, o: &ExecutionOrder) -> Result<()> {
+        let sql_str = format!(
+            "UPDATE execution_order SET token_amount = {}, sol_amount = {}, status = '{}',
+            realized_pnl = ?, error = ?, updated_at = strftime('%s', 'now') WHERE id = {}",
+            o.token_amount, o.sol_amount, o.status, o.id
+        );
+        sqlx::query(&sql_str)
+            .bind(o.realized_pnl)
+            .bind(&o.error)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// the orders since `since`, oldest first
+    pub async fn get_execution_orders(&self, since: i64) -> Result<Vec<ExecutionOrder>> {
+        let sql_str = format!(
+            "SELECT * FROM execution_order WHERE created_at >= {} ORDER BY created_at, id",
+            since
+        );
+        let rows = sqlx::query_as::<_, ExecutionOrder>(&sql_str)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    /// the orders in `status`, oldest first
+    pub async fn get_execution_orders_by_status(
+        &self,
+        status: &str,
+    ) -> Result<Vec<ExecutionOrder>> {
+        let sql_str = format!(
+            "SELECT * FROM execution_order WHERE status = '{}' ORDER BY created_at, id",
+            status
+        );
+        let rows = sqlx::query_as::<_, ExecutionOrder>(&sql_str)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    /// lamports realized by the applied sells since `since`, a loss is negative
+    pub async fn get_execution_realized_pnl(&self, dry_run: bool, since: i64) -> Result<i64> {
+        let status = if dry_run {
+            EXECUTION_ORDER_DRY_RUN
+        } else {
+            EXECUTION_ORDER_CONFIRMED
+        };
+        let sql_str = format!(
+            "SELECT COALESCE(SUM(realized_pnl), 0) FROM execution_order
+            WHERE status = '{}' AND created_at >= {}",
+            status, since
+        );
+        let pnl: i64 = sqlx::query_scalar(&sql_str).fetch_one(&self.pool).await?;
+        Ok(pnl)
+    }
 }
@@ -0,0 +1,330 @@
+use std::{env, sync::Arc, time::Duration, time::SystemTime};
+
+use anyhow::Result;
+use futures_util::future::join_all;
+use serde::Deserialize;
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::watch,
+    task::JoinHandle,
+};
+use tracing::{error, info, warn};
+use validator::Validate;
+
+use crate::{
+    config::{load_config, Config},
+    models::{ModelsManager, MonitorStatus},
+    strategies::{MonitorContext, MonitorRule},
+    supervisor::{supervise, StatusReporter, SupervisorConfig},
+};
+
+#[derive(Clone, Debug, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReloadConfig {
+    pub watch: bool, // 监听配置文件修改, SIGHUP always reloads
+    #[validate(range(min = 1))]
+    pub poll_secs: u64, // how often the modification time of the file is checked
+    #[validate(range(min = 1))]
+    pub stop_timeout_secs: u64, // how long a stopped monitor may take to shut its strategy down
+}
+
+impl Default for ReloadConfig {
+    fn default() -> Self {
+        Self {
+            watch: true,
+            poll_secs: 5,
+            stop_timeout_secs: 10,
+        }
+    }
+}
+
+/// how the monitors of a reloaded config differ from the running ones
+#[derive(Debug, Default, PartialEq)]
+pub struct MonitorDiff {
+    pub added: Vec<MonitorRule>,
+    pub removed: Vec<MonitorRule>,
+    pub changed: Vec<(MonitorRule, MonitorRule)>, // (old, new) of the same address, strategy and target
+}
+
+impl MonitorDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+fn same_monitor(a: &MonitorRule, b: &MonitorRule) -> bool {
+    a.address == b.address && a.rule_type == b.rule_type && a.target == b.target
+}
+
+/// equal rules are unchanged, a rule with the address, strategy and target of a removed one changed
+pub fn diff_monitors(old: &[MonitorRule], new: &[MonitorRule]) -> MonitorDiff {
+    let mut removed: Vec<MonitorRule> = Vec::new();
+    let mut added: Vec<MonitorRule> = new.to_vec();
+    for rule in old {
+        match added.iter().position(|r| r == rule) {
+            Some(i) => {
+                added.remove(i);
+            }
+            None => removed.push(rule.clone()),
+        }
+    }
+    let mut diff = MonitorDiff::default();
+    for rule in removed {
+        match added.iter().position(|r| same_monitor(r, &rule)) {
+            Some(i) => diff.changed.push((rule, added.remove(i))),
+            None => diff.removed.push(rule),
+        }
+    }
+    diff.added = added;
+    diff
+}
+
+struct RunningMonitor {
+    rule: MonitorRule,
+    stop: watch::Sender<bool>,
+    handle: JoinHandle<()>,
+    status: Arc<StatusReporter>,
+}
+
+/// the running monitors of the daemon, one supervised task each
+pub struct MonitorSet {
+    ctx: MonitorContext,
+    stop_timeout: Duration,
+    supervisor: SupervisorConfig,
+    running: Vec<RunningMonitor>,
+}
+
+impl MonitorSet {
+    pub fn new(ctx: MonitorContext, config: &ReloadConfig, supervisor: &SupervisorConfig) -> Self {
+        Self {
+            ctx,
+            stop_timeout: Duration::from_secs(config.stop_timeout_secs),
+            supervisor: supervisor.clone(),
+            running: Vec::new(),
+        }
+    }
+
+    pub fn rules(&self) -> Vec<MonitorRule> {
+        self.running.iter().map(|m| m.rule.clone()).collect()
+    }
+
+    /// the state of every running monitor
+    pub fn statuses(&self) -> Vec<MonitorStatus> {
+        self.running.iter().map(|m| m.status.get()).collect()
+    }
+
+    pub fn start(&mut self, rule: MonitorRule) {
+        info!("monitor: {:?}", rule);
+        let (stop, stopped) = watch::channel(false);
+        let status = Arc::new(StatusReporter::new(
+            self.ctx.manager.clone(),
+            MonitorStatus {
+                address: rule.address.clone(),
+                rule_type: rule.rule_type.clone(),
+                target: rule.target.as_str().to_string(),
+                ..Default::default()
+            },
+        ));
+        let ctx = self.ctx.clone();
+        let supervisor = self.supervisor.clone();
+        let m = rule.clone();
+        let s = status.clone();
+        let handle = tokio::spawn(async move {
+            let name = format!("{} monitor of {}", m.rule_type, m.address);
+            supervise(&name, &supervisor, &s, stopped, |mut stopped| {
+                let m = m.clone();
+                let ctx = ctx.clone();
+                async move {
+                    let stopped = async move {
+                        let _ = stopped.wait_for(|s| *s).await;
+                    };
+                    m.run_until(&ctx, stopped).await
+                }
+            })
+            .await
+        });
+        self.running.push(RunningMonitor {
+            rule,
+            stop,
+            handle,
+            status,
+        });
+    }
+
+    /// stop the first running monitor equal to `rule`, waiting for its strategy to shut down
+    pub async fn stop(&mut self, rule: &MonitorRule) {
+        let Some(i) = self.running.iter().position(|m| &m.rule == rule) else {
+            return;
+        };
+        let m = self.running.remove(i);
+        info!("stop monitor: {:?}", m.rule);
+        let _ = m.stop.send(true);
+        let mut handle = m.handle;
+        if tokio::time::timeout(self.stop_timeout, &mut handle)
+            .await
+            .is_err()
+        {
+            warn!("monitor did not stop in time, aborted: {:?}", m.rule);
+            handle.abort();
+        }
+    }
+
+    /// stop every monitor at once, waiting for them to drain their queued logs
+    pub async fn stop_all(&mut self) {
+        let running: Vec<RunningMonitor> = self.running.drain(..).collect();
+        for m in &running {
+            let _ = m.stop.send(true);
+        }
+        let timeout = self.stop_timeout;
+        join_all(running.into_iter().map(|m| async move {
+            let mut handle = m.handle;
+            if tokio::time::timeout(timeout, &mut handle).await.is_err() {
+                warn!("monitor did not stop in time, aborted: {:?}", m.rule);
+                handle.abort();
+            }
+        }))
+        .await;
+    }
+
+    /// stop the removed and changed monitors, then start the added, changed and failed ones
+    pub async fn apply(&mut self, rules: &[MonitorRule]) -> MonitorDiff {
+        // the failed monitors start again
+        self.running.retain(|m| !m.handle.is_finished());
+        let diff = diff_monitors(&self.rules(), rules);
+        for rule in diff
+            .removed
+            .iter()
+            .chain(diff.changed.iter().map(|(old, _)| old))
+        {
+            self.stop(rule).await;
+        }
+        for rule in diff
+            .added
+            .iter()
+            .chain(diff.changed.iter().map(|(_, new)| new))
+        {
+            self.start(rule.clone());
+        }
+        diff
+    }
+}
+
+/// the configured monitors and the monitors of the approved discovery candidates
+pub async fn monitor_rules(manager: &ModelsManager, c: &Config) -> Result<Vec<MonitorRule>> {
+    let mut rules = c.monitors.clone();
+    rules.extend(c.discovery.candidate_rules(manager, &c.monitors).await?);
+    Ok(rules)
+}
+
+async fn modified(path: &str) -> Option<SystemTime> {
+    tokio::fs::metadata(path).await.ok()?.modified().ok()
+}
+
+/// reload the monitors on SIGHUP, or when the config file changes if `watch` is set.
+/// an invalid config is logged and the running monitors are kept. every monitor is stopped on shutdown.
+pub async fn run_config_reloader(mut monitors: MonitorSet, config: &ReloadConfig) -> Result<()> {
+    let path = env::var("SMART_CONFIG")?;
+    let mut hangup = signal(SignalKind::hangup())?;
+    let mut poll = tokio::time::interval(Duration::from_secs(config.poll_secs));
+    let mut last_modified = modified(&path).await;
+    loop {
+        tokio::select! {
+            _ = monitors.ctx.stopped() => {
+                monitors.stop_all().await;
+                return Ok(());
+            }
+            _ = hangup.recv() => info!("SIGHUP received, reloading {}", path),
+            _ = poll.tick(), if config.watch => {
+                let m = modified(&path).await;
+                if m == last_modified {
+                    continue;
+                }
+                last_modified = m;
+                info!("{} changed, reloading", path);
+            }
+        }
+        let c = match load_config(&path).await {
+            Ok(c) => c,
+            Err(e) => {
+                error!("reload config error: {:?}, keeping the running monitors", e);
+                continue;
+            }
+        };
+        let rules = match monitor_rules(&monitors.ctx.manager, &c).await {
+            Ok(rules) => rules,
+            Err(e) => {
+                error!(
+                    "reload candidates error: {:?}, keeping the running monitors",
+                    e
+                );
+                continue;
+            }
+        };
+        let diff = monitors.apply(&rules).await;
+        if diff.is_empty() {
+            info!("monitors unchanged");
+        } else {
+            info!(
+                "monitors reloaded, added: {}, removed: {}, changed: {}",
+                diff.added.len(),
+                diff.removed.len(),
+                diff.changed.len()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alert::alert_channel, test_support::MockSolana};
+
+    fn rule(address: &str, rule_type: &str, price_below: Option<f64>) -> MonitorRule {
+        MonitorRule {
+            address: address.to_string(),
+            rule_type: rule_type.to_string(),
+            target: Default::default(),
+            conditions: crate::strategies::MonitorCondition {
+                price_below,
+                ..Default::default()
+            },
+            config: Default::default(),
+            cooldown: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_monitor_reload() -> Result<()> {
+        let a = rule("a", "Buy", None);
+        let b = rule("b", "Sell", None);
+        let c = rule("c", "Buy", Some(1.0));
+        let c2 = rule("c", "Buy", Some(2.0));
+        let d = rule("d", "Buy", None);
+        let diff = diff_monitors(
+            &[a.clone(), b.clone(), c.clone()],
+            &[a.clone(), c2.clone(), d.clone()],
+        );
+        assert_eq!(diff.removed, vec![b.clone()]);
+        assert_eq!(diff.changed, vec![(c.clone(), c2.clone())]);
+        assert_eq!(diff.added, vec![d.clone()]);
+        let same = [a.clone()];
+        assert!(diff_monitors(&same, &same).is_empty());
+
+        let mock = MockSolana::start().await?;
+        let (alerts, _alert_receiver) = alert_channel();
+        let ctx = mock.context(alerts).await?;
+        let mut monitors = MonitorSet::new(ctx, &ReloadConfig::default(), &Default::default());
+        monitors.apply(&[a.clone(), b.clone(), c.clone()]).await;
+        let a_handle = monitors.running[0].handle.abort_handle();
+        let b_handle = monitors.running[1].handle.abort_handle();
+
+        let diff = monitors.apply(&[a.clone(), c2.clone(), d.clone()]).await;
+        assert_eq!(diff.added, vec![d.clone()]);
+        assert_eq!(monitors.rules(), vec![a, d, c2]);
+        // the unchanged monitor keeps running, the removed one stopped
+        assert!(!a_handle.is_finished());
+        assert!(b_handle.is_finished());
+        assert_eq!(monitors.statuses()[0].address, "a");
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use anyhow::Result;
+use serde::Deserialize;
+use tracing::{error, info};
+use validator::Validate;
+
+use crate::{
+    models::{ModelsManager, TradeRecord, WalletScore},
+    sol_client::{curve::PUMP_FEE_BPS, ui_amount, PUMP_TOKEN_DECIMALS, SOL_DECIMALS},
+};
+
+#[derive(Clone, Debug, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScoringConfig {
+    pub interval_secs: u64, // 评分间隔, 0 disables scoring
+    #[validate(length(min = 1))]
+    pub windows_secs: Vec<u64>, // scored windows, conditions refer to them by window_secs
+    pub network_fee_lamports: u64, // transaction fee charged once per signature, the recorded trades don't have it
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 0,
+            windows_secs: vec![24 * 3600, 30 * 24 * 3600],
+            network_fee_lamports: 5000,
+        }
+    }
+}
+
+/// the trades of one wallet in one mint, lamports and raw token units
+#[derive(Debug, Default)]
+struct MintTrades {
+    bought_sol: u64,
+    bought_tokens: u64,
+    sold_sol: u64,
+    sold_tokens: u64,
+    first_buy: Option<i64>,
+    last_sell: Option<i64>,
+}
+
+/// score the trades of `wallet`, oldest first, with the holdings priced at `prices` (mint -> SOL).
+///
+/// the pump.fun fee and `network_fee` per transaction are added to the cost of the buys and taken
+/// from the proceeds of the sells. tokens bought before the window have no cost and only count as trades.
+pub fn score_trades(
+    wallet: &str,
+    window_secs: u64,
+    trades: &[TradeRecord],
+    prices: &HashMap<String, f64>,
+    network_fee: u64,
+    computed_at: i64,
+) -> WalletScore {
+    let mut mints: HashMap<&str, MintTrades> = HashMap::new();
+    let mut signatures: HashSet<&str> = HashSet::new();
+    for t in trades {
+        let m = mints.entry(&t.mint).or_default();
+        let sol = t.sol_amount as u64;
+        let mut fees = sol * PUMP_FEE_BPS / 10_000;
+        if signatures.insert(&t.signature) {
+            fees += network_fee;
+        }
+        if t.is_buy {
+            m.bought_sol += sol + fees;
+            m.bought_tokens += t.token_amount as u64;
+            m.first_buy.get_or_insert(t.timestamp);
+        } else {
+            m.sold_sol += sol.saturating_sub(fees);
+            m.sold_tokens += t.token_amount as u64;
+            m.last_sell = Some(t.timestamp);
+        }
+    }
+
+    let mut score = WalletScore {
+        wallet: wallet.to_string(),
+        window_secs: window_secs as i64,
+        trade_count: trades.len() as i64,
+        computed_at,
+        ..Default::default()
+    };
+    let (mut rois, mut holds, mut wins, mut sold) = (Vec::new(), Vec::new(), 0, 0);
+    for (mint, m) in &mints {
+        if m.bought_tokens == 0 {
+            continue;
+        }
+        let cost =
+            ui_amount(m.bought_sol, SOL_DECIMALS) / ui_amount(m.bought_tokens, PUMP_TOKEN_DECIMALS);
+        // a sell of more than was bought in the window only counts for the bought part
+        let sold_tokens = m.sold_tokens.min(m.bought_tokens);
+        let sold_sol = if m.sold_tokens == 0 {
+            0.0
+        } else {
+            ui_amount(m.sold_sol, SOL_DECIMALS) * sold_tokens as f64 / m.sold_tokens as f64
+        };
+        let realized = sold_sol - cost * ui_amount(sold_tokens, PUMP_TOKEN_DECIMALS);
+        let holding = ui_amount(m.bought_tokens - sold_tokens, PUMP_TOKEN_DECIMALS);
+        let unrealized = prices
+            .get(*mint)
+            .map_or(0.0, |price| (price - cost) * holding);
+        score.realized_pnl += realized;
+        score.unrealized_pnl += unrealized;
+        rois.push((realized + unrealized) / ui_amount(m.bought_sol, SOL_DECIMALS) * 100.0);
+        if sold_tokens > 0 {
+            sold += 1;
+            if realized > 0.0 {
+                wins += 1;
+            }
+            if let (Some(first_buy), Some(last_sell)) = (m.first_buy, m.last_sell) {
+                holds.push((last_sell - first_buy) as f64);
+            }
+        }
+    }
+    if sold > 0 {
+        score.win_rate = Some(wins as f64 / sold as f64 * 100.0);
+    }
+    score.median_roi = median(&mut rois);
+    if !holds.is_empty() {
+        score.avg_hold_secs = Some(holds.iter().sum::<f64>() / holds.len() as f64);
+    }
+    score
+}
+
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        Some((values[mid - 1] + values[mid]) / 2.0)
+    } else {
+        Some(values[mid])
+    }
+}
+
+/// score `wallet` over every configured window from the recorded trades and persist the scores
+pub async fn score_wallet(
+    manager: &ModelsManager,
+    config: &ScoringConfig,
+    wallet: &str,
+    now: i64,
+) -> Result<Vec<WalletScore>> {
+    let mut scores = Vec::new();
+    for window_secs in &config.windows_secs {
+        let trades = manager
+            .get_user_trades(wallet, now - *window_secs as i64)
+            .await?;
+        let mut prices = HashMap::new();
+        for t in &trades {
+            if prices.contains_key(&t.mint) {
+                continue;
+            }
+            if let Some(last) = manager.get_last_trade(&t.mint).await? {
+                prices.insert(t.mint.clone(), last.price());
+            }
+        }
+        let score = score_trades(
+            wallet,
+            *window_secs,
+            &trades,
+            &prices,
+            config.network_fee_lamports,
+            now,
+        );
+        manager.upsert_wallet_score(&score).await?;
+        scores.push(score);
+    }
+    Ok(scores)
+}
+
+/// rescore `wallets` every `interval_secs`
+pub async fn run_scoring(manager: &ModelsManager, config: &ScoringConfig, wallets: Vec<String>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+    loop {
+        interval.tick().await;
+        let now = chrono::Utc::now().timestamp();
+        for wallet in &wallets {
+            if let Err(e) = score_wallet(manager, config, wallet, now).await {
+                error!("score wallet error: {:?}, wallet: {}", e, wallet);
+            }
+        }
+        info!("scored {} wallets", wallets.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sol_client::TradeEventData, test_support::memory_manager};
+
+    fn trade(mint: &str, is_buy: bool, sol: u64, tokens: u64, timestamp: i64) -> TradeEventData {
+        TradeEventData {
+            mint: mint.to_string(),
+            sol_amount: sol,
+            token_amount: tokens,
+            is_buy,
+            user: "wallet".to_string(),
+            timestamp,
+            // 1 SOL for 1000 tokens
+            virtual_sol_reserves: 30_000_000_000,
+            virtual_token_reserves: 30_000_000_000,
+            real_sol_reserves: 0,
+            real_token_reserves: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_score_wallet() -> Result<()> {
+        let manager = memory_manager().await?;
+        let now = 1_734_600_000;
+        let sol = 1_000_000_000;
+        let tokens = 1_000_000_000; // 1000 tokens
+        let trades = [
+            // a: doubled, sold all after 100s
+            trade("a", true, sol, tokens, now - 1000),
+            trade("a", false, 2 * sol, tokens, now - 900),
+            // b: lost half, sold all after 300s
+            trade("b", true, sol, tokens, now - 800),
+            trade("b", false, sol / 2, tokens, now - 500),
+            // c: still held, bought at 2 SOL per 1000 tokens, now at 1
+            trade("c", true, 2 * sol, tokens, now - 400),
+            // d: before the window
+            trade("d", true, sol, tokens, now - 100_000),
+        ];
+        for (i, t) in trades.iter().enumerate() {
+            manager
+                .add_trade_events(&format!("sig{}", i), std::slice::from_ref(t))
+                .await?;
+        }
+
+        let config = ScoringConfig {
+            interval_secs: 0,
+            windows_secs: vec![3600],
+            network_fee_lamports: 5000,
+        };
+        let scores = score_wallet(&manager, &config, "wallet", now).await?;
+        let score = &scores[0];
+        assert_eq!(score.trade_count, 5);
+        // 1% pump.fun fee and 0.000005 SOL network fee on every trade
+        let (buy, sell) = (|s: f64| s * 1.01 + 0.000005, |s: f64| s * 0.99 - 0.000005);
+        let (a, b) = (sell(2.0) - buy(1.0), sell(0.5) - buy(1.0));
+        let c = 1.0 - buy(2.0);
+        assert!((score.realized_pnl - (a + b)).abs() < 1e-9);
+        assert!((score.unrealized_pnl - c).abs() < 1e-9);
+        assert_eq!(score.win_rate, Some(50.0));
+        // rois of about 96, -51 and -50
+        let median = score.median_roi.expect("no median roi");
+        assert!((median - c / buy(2.0) * 100.0).abs() < 1e-9);
+        assert_eq!(score.avg_hold_secs, Some(200.0));
+
+        let stored = manager.get_wallet_score("wallet", 3600).await?;
+        assert_eq!(stored.as_ref(), Some(score));
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::signal;
+use tracing::info;
+use validator::Validate;
+
+#[derive(Clone, Debug, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShutdownConfig {
+    #[validate(range(min = 1))]
+    pub drain_secs: u64, // 退出时等待任务处理完队列的时间, then they are aborted
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self { drain_secs: 10 }
+    }
+}
+
+impl ShutdownConfig {
+    pub fn drain_timeout(&self) -> Duration {
+        Duration::from_secs(self.drain_secs)
+    }
+}
+
+/// completes on SIGINT or SIGTERM, for the daemon and the web server
+pub async fn shutdown_signal() {
+    let ctl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("Failed to listen for ctrl-c signal");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for terminate signal")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctl_c => {},
+        _ = terminate => {},
+    }
+
+    info!("signal received, shutting down");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alert::alert_channel,
+        strategies::{MonitorRule, BUY_STRATEGY},
+        test_support::MockSolana,
+    };
+    use anyhow::Result;
+    use tokio::sync::{mpsc, watch};
+
+    #[tokio::test]
+    async fn test_shutdown_drain() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let (alerts, _alert_receiver) = alert_channel();
+        let mut ctx = mock.context(alerts).await?;
+        let (shutdown_sender, shutdown) = watch::channel(false);
+        ctx.shutdown = shutdown;
+
+        let (sender, mut receiver) = mpsc::channel(10);
+        sender.send(1).await?;
+        assert_eq!(ctx.next_log(&mut receiver).await, Some(1));
+        sender.send(2).await?;
+        sender.send(3).await?;
+        shutdown_sender.send(true)?;
+        // the queued items are still handed out, nothing new gets in
+        assert_eq!(ctx.next_log(&mut receiver).await, Some(2));
+        assert!(sender.send(4).await.is_err());
+        assert_eq!(ctx.next_log(&mut receiver).await, Some(3));
+        assert_eq!(ctx.next_log(&mut receiver).await, None);
+
+        // a stopped monitor shuts its strategy down and returns
+        let rule = MonitorRule {
+            address: "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x".to_string(),
+            rule_type: BUY_STRATEGY.to_string(),
+            target: Default::default(),
+            conditions: Default::default(),
+            config: Default::default(),
+            cooldown: None,
+        };
+        let stop = tokio::time::sleep(Duration::from_millis(100));
+        tokio::time::timeout(Duration::from_secs(5), rule.run_until(&ctx, stop)).await??;
+        Ok(())
+    }
+}
@@ -0,0 +1,453 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    time::Duration,
+};
+
+use anyhow::Result;
+use serde::Deserialize;
+use solana_client::rpc_response::{Response, RpcLogsResponse};
+use tokio::{sync::mpsc, time::timeout};
+use tracing::{info, warn};
+use validator::Validate;
+
+use crate::{
+    models::{ModelsManager, SniperBuy, TokenLaunch},
+    sol_client::{client::SolanaMonitor, PUMP_PROGRAM_ID, PUMP_TOKEN_TOTAL_SUPPLY},
+    strategies::{spawn_log_subscribe, MonitorContext, StrategyEvent},
+};
+
+#[derive(Clone, Debug, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct SniperConfig {
+    pub enabled: bool, // 监听 pump.fun 新币的早期买入
+    #[validate(range(max = 150))]
+    pub max_slots: u64, // a buy within n slots after the creation slot is a snipe, 0 is the creation slot only
+    pub lookup_funders: bool, // look up who funded every sniper, needed for the bundle clusters
+    #[validate(range(min = 1))]
+    pub funder_timeout_secs: u64, // a lookup taking longer leaves the funder unknown
+    #[validate(range(min = 1))]
+    pub funder_queue_size: usize, // snipers waiting for a lookup, more are not looked up
+    #[validate(range(min = 1))]
+    pub funder_cache_size: usize, // funders remembered, the oldest are evicted
+}
+
+impl Default for SniperConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_slots: 2,
+            lookup_funders: true,
+            funder_timeout_secs: 10,
+            funder_queue_size: 1000,
+            funder_cache_size: 10_000,
+        }
+    }
+}
+
+/// buyers of one slot funded by the same account, most likely one bundle
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleCluster {
+    pub slot: i64,
+    pub funder: String,
+    pub users: Vec<String>,
+    pub token_amount: u64, // raw units
+}
+
+/// the early supply of a mint
+#[derive(Debug, Clone, PartialEq)]
+pub struct SniperReport {
+    pub mint: String,
+    pub buys: Vec<SniperBuy>,
+    pub clusters: Vec<BundleCluster>,
+}
+
+impl SniperReport {
+    pub fn new(mint: &str, buys: Vec<SniperBuy>) -> Self {
+        let mut grouped: BTreeMap<(i64, &str), Vec<&SniperBuy>> = BTreeMap::new();
+        for b in &buys {
+            if let Some(funder) = &b.funder {
+                grouped.entry((b.slot, funder)).or_default().push(b);
+            }
+        }
+        let clusters = grouped
+            .into_iter()
+            .filter_map(|((slot, funder), group)| {
+                let mut users: Vec<String> = group.iter().map(|b| b.user.clone()).collect();
+                users.sort();
+                users.dedup();
+                if users.len() < 2 {
+                    return None;
+                }
+                Some(BundleCluster {
+                    slot,
+                    funder: funder.to_string(),
+                    users,
+                    token_amount: group.iter().map(|b| b.token_amount as u64).sum(),
+                })
+            })
+            .collect();
+        Self {
+            mint: mint.to_string(),
+            buys,
+            clusters,
+        }
+    }
+
+    /// percent of the total supply bought by snipers
+    pub fn sniped_supply(&self) -> f64 {
+        let tokens: u64 = self.buys.iter().map(|b| b.token_amount as u64).sum();
+        tokens as f64 / PUMP_TOKEN_TOTAL_SUPPLY as f64 * 100.0
+    }
+
+    /// percent of the total supply bought by bundle clusters
+    pub fn bundled_supply(&self) -> f64 {
+        let tokens: u64 = self.clusters.iter().map(|c| c.token_amount).sum();
+        tokens as f64 / PUMP_TOKEN_TOTAL_SUPPLY as f64 * 100.0
+    }
+}
+
+/// the sniper report of `mint`, None when its launch was not seen
+pub async fn sniper_report(manager: &ModelsManager, mint: &str) -> Result<Option<SniperReport>> {
+    if manager.get_token_launch(mint).await?.is_none() {
+        return Ok(None);
+    }
+    let buys = manager.get_sniper_buys(mint).await?;
+    Ok(Some(SniperReport::new(mint, buys)))
+}
+
+/// flag the buys landing within `max_slots` of their token's creation slot
+pub struct SniperDetector {
+    config: SniperConfig,
+    launches: HashMap<String, TokenLaunch>, // mint -> launch still in its sniping window
+}
+
+impl SniperDetector {
+    pub fn new(config: SniperConfig) -> Self {
+        Self {
+            config,
+            launches: HashMap::new(),
+        }
+    }
+
+    /// store the launches and sniper buys of a transaction, returns the sniper buys.
+    /// the funders are left to `FunderLookup`, off the program stream
+    pub async fn on_event(
+        &mut self,
+        manager: &ModelsManager,
+        event: &StrategyEvent,
+    ) -> Result<Vec<SniperBuy>> {
+        let slot = event.slot as i64;
+        // events arrive about in slot order, a launch past its window can't be sniped anymore
+        let max_slots = self.config.max_slots as i64;
+        self.launches.retain(|_, l| l.slot + max_slots >= slot);
+
+        for create in &event.creates {
+            let launch = TokenLaunch {
+                mint: create.mint.clone(),
+                creator: create.user.clone(),
+                slot,
+                signature: event.signature.clone(),
+            };
+            manager.add_token_launch(&launch).await?;
+            self.launches.insert(launch.mint.clone(), launch);
+        }
+
+        let mut buys = Vec::new();
+        for trade in event.trades.iter().filter(|t| t.is_buy) {
+            let Some(launch) = self.launches.get(&trade.mint) else {
+                continue;
+            };
+            // the dev buy is the creator's own supply, not a snipe
+            if trade.user == launch.creator || slot < launch.slot {
+                continue;
+            }
+            let buy = SniperBuy {
+                mint: trade.mint.clone(),
+                signature: event.signature.clone(),
+                user: trade.user.clone(),
+                slot,
+                slot_offset: slot - launch.slot,
+                sol_amount: trade.sol_amount as i64,
+                token_amount: trade.token_amount as i64,
+                funder: None,
+            };
+            manager.add_sniper_buy(&buy).await?;
+            info!(
+                "sniper buy {} of {} tokens, {} slots after the launch of {}",
+                buy.user,
+                trade.token_ui_amount(),
+                buy.slot_offset,
+                buy.mint
+            );
+            buys.push(buy);
+        }
+        Ok(buys)
+    }
+}
+
+/// looks up the funders of the sniper buys with a timeout, remembering the latest `funder_cache_size`
+pub struct FunderLookup {
+    solana_client: SolanaMonitor,
+    timeout: Duration,
+    cache_size: usize,
+    funders: HashMap<String, Option<String>>, // wallet -> funder
+    order: VecDeque<String>,                  // cached wallets, oldest first
+}
+
+impl FunderLookup {
+    pub fn new(solana_client: SolanaMonitor, config: &SniperConfig) -> Self {
+        Self {
+            solana_client,
+            timeout: Duration::from_secs(config.funder_timeout_secs),
+            cache_size: config.funder_cache_size,
+            funders: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// the funder of `wallet`, None when it has none or the lookup failed
+    pub async fn funder(&mut self, wallet: &str) -> Option<String> {
+        if let Some(funder) = self.funders.get(wallet) {
+            return funder.clone();
+        }
+        let funder = match timeout(self.timeout, self.solana_client.get_funder(wallet)).await {
+            Ok(Ok(funder)) => funder,
+            Ok(Err(e)) => {
+                warn!("get funder error: {:?}, wallet: {}", e, wallet);
+                return None;
+            }
+            Err(_) => {
+                warn!("get funder timed out, wallet: {}", wallet);
+                return None;
+            }
+        };
+        if self.order.len() >= self.cache_size {
+            if let Some(oldest) = self.order.pop_front() {
+                self.funders.remove(&oldest);
+            }
+        }
+        self.order.push_back(wallet.to_string());
+        self.funders.insert(wallet.to_string(), funder.clone());
+        funder
+    }
+
+    /// store the funder of `buy`, returns it
+    pub async fn resolve(
+        &mut self,
+        manager: &ModelsManager,
+        buy: &SniperBuy,
+    ) -> Result<Option<String>> {
+        let funder = self.funder(&buy.user).await;
+        if let Some(funder) = &funder {
+            manager
+                .set_sniper_buy_funder(&buy.signature, &buy.user, funder)
+                .await?;
+        }
+        Ok(funder)
+    }
+
+    pub fn cached(&self) -> usize {
+        self.funders.len()
+    }
+}
+
+/// look up the funders of the queued sniper buys and warn on the bundles they reveal
+async fn run_funder_lookups(
+    manager: &ModelsManager,
+    mut lookup: FunderLookup,
+    mut receiver: mpsc::Receiver<SniperBuy>,
+) {
+    while let Some(buy) = receiver.recv().await {
+        let funder = match lookup.resolve(manager, &buy).await {
+            Ok(Some(funder)) => funder,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("store funder error: {:?}, signature: {}", e, buy.signature);
+                continue;
+            }
+        };
+        let Ok(Some(report)) = sniper_report(manager, &buy.mint).await else {
+            continue;
+        };
+        for c in &report.clusters {
+            if c.slot == buy.slot && c.funder == funder {
+                warn!(
+                    "bundle in {}: {} buyers funded by {} in slot {}",
+                    buy.mint,
+                    c.users.len(),
+                    c.funder,
+                    c.slot
+                );
+            }
+        }
+    }
+}
+
+/// flag the sniper buys of every new pump.fun token until the program subscription ends
+pub async fn run_sniper_detector(ctx: &MonitorContext, config: &SniperConfig) -> Result<()> {
+    let (sender, mut receiver) = mpsc::channel::<Response<RpcLogsResponse>>(1000);
+    let subscription = spawn_log_subscribe(PUMP_PROGRAM_ID, ctx, sender);
+    // the funder lookups page through the history of every sniper, they run beside the stream
+    let (funder_sender, funder_receiver) = mpsc::channel::<SniperBuy>(config.funder_queue_size);
+    let lookups = config.lookup_funders.then(|| {
+        let lookup = FunderLookup::new(SolanaMonitor::new(&ctx.wss_url, &ctx.rpc_url), config);
+        let manager = ctx.manager.clone();
+        tokio::spawn(async move { run_funder_lookups(&manager, lookup, funder_receiver).await })
+    });
+    let mut detector = SniperDetector::new(config.clone());
+    while let Some(log) = ctx.next_log(&mut receiver).await {
+        let event = StrategyEvent::from(log);
+        if event.creates.is_empty() && event.trades.is_empty() {
+            continue;
+        }
+        match detector.on_event(&ctx.manager, &event).await {
+            Ok(buys) if lookups.is_some() => {
+                for buy in buys {
+                    if let Err(e) = funder_sender.try_send(buy) {
+                        warn!("funder lookup queue full, skipped: {}", e.into_inner().user);
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!(
+                "sniper detector error: {:?}, signature: {}",
+                e, event.signature
+            ),
+        }
+    }
+    if let Some(lookups) = lookups {
+        lookups.abort();
+    }
+    if ctx.is_shutdown() {
+        subscription.abort();
+        return Ok(());
+    }
+    subscription.await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{memory_manager, MockSolana};
+    use base64::{engine::general_purpose::STANDARD as base64, Engine};
+    use serde_json::json;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn create_data(mint: &Pubkey, creator: &Pubkey) -> String {
+        let mut data = vec![27, 114, 169, 77, 222, 235, 99, 118];
+        for s in ["Token", "TKN", "https://example.com/token.json"] {
+            data.extend((s.len() as u32).to_le_bytes());
+            data.extend(s.as_bytes());
+        }
+        data.extend(mint.to_bytes());
+        data.extend(Pubkey::new_unique().to_bytes());
+        data.extend(creator.to_bytes());
+        format!("Program data: {}", base64.encode(data))
+    }
+
+    fn buy_data(mint: &Pubkey, user: &Pubkey, token_amount: u64) -> String {
+        let mut data = vec![189, 219, 127, 211, 78, 230, 97, 238];
+        data.extend(mint.to_bytes());
+        data.extend(1_000_000_000u64.to_le_bytes());
+        data.extend(token_amount.to_le_bytes());
+        data.push(1);
+        data.extend(user.to_bytes());
+        data.extend(1_734_616_564i64.to_le_bytes());
+        for reserves in [
+            30_000_000_000u64,
+            1_073_000_000_000_000,
+            0,
+            793_100_000_000_000,
+        ] {
+            data.extend(reserves.to_le_bytes());
+        }
+        format!("Program data: {}", base64.encode(data))
+    }
+
+    fn event(signature: &str, slot: u64, logs: Vec<String>) -> Result<StrategyEvent> {
+        let log: Response<RpcLogsResponse> = serde_json::from_value(json!({
+            "context": {"slot": slot},
+            "value": {"signature": signature, "err": null, "logs": logs},
+        }))?;
+        Ok(log.into())
+    }
+
+    #[tokio::test]
+    async fn test_sniper_detector() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let manager = memory_manager().await?;
+        let client = SolanaMonitor::new(&mock.wss_url, &mock.rpc_url);
+        let (mint, creator) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (a, b, c, late) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        // a and b were funded by the same wallet, c has no history
+        let funding = mock.add_transaction_fixture("pump_buy_tx.json");
+        mock.add_signatures(&a.to_string(), &[(&funding, 1_734_600_000)]);
+        mock.add_signatures(&b.to_string(), &[(&funding, 1_734_600_000)]);
+
+        let config = SniperConfig {
+            enabled: true,
+            max_slots: 1,
+            funder_cache_size: 2,
+            ..Default::default()
+        };
+        let mut detector = SniperDetector::new(config.clone());
+        let mut lookup = FunderLookup::new(client, &config);
+        let tokens = 10_000_000_000_000; // 1% of the supply
+
+        // the launch with the dev buy and a bundled buy in the creation slot
+        let launch = event(
+            "create",
+            100,
+            vec![
+                create_data(&mint, &creator),
+                buy_data(&mint, &creator, 5 * tokens),
+                buy_data(&mint, &a, tokens),
+            ],
+        )?;
+        assert_eq!(launch.creates.len(), 1);
+        assert_eq!(launch.creates[0].mint, mint.to_string());
+        assert_eq!(launch.creates[0].user, creator.to_string());
+        assert_eq!(launch.creates[0].symbol, "TKN");
+        let mut buys = detector.on_event(&manager, &launch).await?;
+        assert_eq!(buys.len(), 1);
+        assert_eq!(buys[0].slot_offset, 0);
+        assert_eq!(buys[0].funder, None);
+
+        for (sig, slot, user) in [("b", 100, &b), ("c", 101, &c), ("late", 102, &late)] {
+            let e = event(sig, slot, vec![buy_data(&mint, user, tokens)])?;
+            buys.extend(detector.on_event(&manager, &e).await?);
+        }
+        for buy in &buys {
+            lookup.resolve(&manager, buy).await?;
+        }
+        // the lookup of a was evicted
+        assert_eq!(lookup.cached(), 2);
+
+        let report = sniper_report(&manager, &mint.to_string())
+            .await?
+            .expect("no launch");
+        assert_eq!(report.buys.len(), 3);
+        assert_eq!(report.buys[2].funder, None);
+        assert!((report.sniped_supply() - 3.0).abs() < 1e-9);
+        let mut users = vec![a.to_string(), b.to_string()];
+        users.sort();
+        assert_eq!(
+            report.clusters,
+            vec![BundleCluster {
+                slot: 100,
+                funder: "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x".to_string(),
+                users,
+                token_amount: 2 * tokens,
+            }]
+        );
+        assert!((report.bundled_supply() - 2.0).abs() < 1e-9);
+        assert!(sniper_report(&manager, "unknown").await?.is_none());
+        Ok(())
+    }
+}
@@ -2,17 +2,35 @@ use anyhow::Result;
 use futures_util::{SinkExt, StreamExt};
 use serde_json::{json, Value};
 use solana_client::{
-    rpc_client::RpcClient,
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
     rpc_config,
-    rpc_response::{Response, RpcLogsResponse},
+    rpc_response::{Response, RpcConfirmedTransactionStatusWithSignature, RpcLogsResponse},
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, hash::Hash, pubkey::Pubkey, signature::Signature,
+    transaction::Transaction,
+};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta,
+    EncodedTransaction, UiMessage, UiTransactionEncoding, UiTransactionStatusMeta,
+    UiTransactionTokenBalance,
 };
-use solana_sdk::{commitment_config::CommitmentConfig, signature::Signature};
-use solana_transaction_status::{UiTransactionEncoding, UiTransactionStatusMeta};
 use std::str::FromStr;
 use tokio::sync::mpsc::Sender;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 use tracing::{debug, error, info};
 
+use super::{
+    jupiter::{parse_jupiter_swap, JupiterSwap},
+    parse_create_events, parse_trade_events,
+    pump::{PumpAccounts, PumpMarket},
+    CreateEventData, TradeEventData,
+};
+
+const SIGNATURE_PAGE_SIZE: usize = 1000;
+const MAX_SIGNATURE_PAGES: usize = 10;
+
 pub struct SolanaMonitor {
     websocket_url: String,
     rpc_client: RpcClient,
@@ -29,7 +47,7 @@ impl SolanaMonitor {
     pub async fn start_log_subscribe(
         &self,
         address: &str,
-        sender: Sender<RpcLogsResponse>,
+        sender: Sender<Response<RpcLogsResponse>>,
     ) -> Result<()> {
         // let (a, b) = PubsubClient::logs_subscribe(url, filter, config).await?;
         info!("Started monitoring address: {}", address);
@@ -64,12 +82,13 @@ impl SolanaMonitor {
                                 serde_json::from_value::<Response<RpcLogsResponse>>(result.clone())
                             {
                                 if log.value.err.is_none() {
-                                    if let Err(e) = sender.send(log.value.clone()).await {
+                                    if let Err(e) = sender.send(log.clone()).await {
                                         error!("Error sending message: {:?}", e);
                                     } else {
                                         info!(
-                                            "Send message: {:?}, capital: {}",
+                                            "Send message: {:?}, slot: {}, capital: {}",
                                             log.value,
+                                            log.context.slot,
                                             sender.capacity()
                                         );
                                     }
@@ -98,17 +117,29 @@ impl SolanaMonitor {
         Ok(())
     }
 
-    pub async fn get_tx(&self, sig: &str) -> Result<UiTransactionStatusMeta> {
+    pub async fn get_transaction(
+        &self,
+        sig: &str,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
         // 实现获取交易信息
         let sig = Signature::from_str(sig)?;
-        let tx = self.rpc_client.get_transaction_with_config(
-            &sig,
-            rpc_config::RpcTransactionConfig {
-                encoding: Some(UiTransactionEncoding::Json),
-                commitment: Some(CommitmentConfig::confirmed()),
-                max_supported_transaction_version: None,
-            },
-        )?;
+        let tx = self
+            .rpc_client
+            .get_transaction_with_config(
+                &sig,
+                rpc_config::RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Json),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    // aggregator swaps are mostly v0 transactions with lookup tables
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .await?;
+        Ok(tx)
+    }
+
+    pub async fn get_tx(&self, sig: &str) -> Result<UiTransactionStatusMeta> {
+        let tx = self.get_transaction(sig).await?;
         if let Some(meta) = tx.transaction.meta {
             if let Some(e) = meta.err {
                 anyhow::bail!("Transaction error: {:?}", e);
@@ -118,7 +149,188 @@ impl SolanaMonitor {
         anyhow::bail!("Transaction not found")
     }
 
-    pub fn parse_buy_info(&self, meta: UiTransactionStatusMeta) -> Result<()> {
+    /// the current reserves of the bonding curve of `mint`, with its creator and the fee recipient
+    pub async fn get_pump_market(&self, mint: &str) -> Result<PumpMarket> {
+        let accounts = PumpAccounts::new(&Pubkey::from_str(mint)?);
+        let curve = self
+            .rpc_client
+            .get_account_data(&accounts.bonding_curve)
+            .await?;
+        let global = self.rpc_client.get_account_data(&accounts.global).await?;
+        PumpMarket::from_account_data(&curve, &global)
+    }
+
+    /// the latest blockhash, with the last block height a transaction signed with it can land at
+    pub async fn get_latest_blockhash(&self) -> Result<(Hash, u64)> {
+        Ok(self
+            .rpc_client
+            .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+            .await?)
+    }
+
+    pub async fn get_block_height(&self) -> Result<u64> {
+        Ok(self
+            .rpc_client
+            .get_block_height_with_commitment(CommitmentConfig::confirmed())
+            .await?)
+    }
+
+    /// the pump.fun trades of a landed transaction
+    pub async fn get_trade_events(&self, sig: &str) -> Result<Vec<TradeEventData>> {
+        let meta = self.get_tx(sig).await?;
+        let OptionSerializer::Some(logs) = meta.log_messages else {
+            return Ok(vec![]);
+        };
+        Ok(parse_trade_events(&logs)
+            .into_iter()
+            .map(|e| e.data)
+            .collect())
+    }
+
+    /// run `tx` without sending it, returns its logs
+    pub async fn simulate_tx(&self, tx: &Transaction) -> Result<Vec<String>> {
+        let result = self.rpc_client.simulate_transaction(tx).await?.value;
+        if let Some(e) = result.err {
+            anyhow::bail!("Simulation error: {:?}, logs: {:?}", e, result.logs);
+        }
+        Ok(result.logs.unwrap_or_default())
+    }
+
+    /// send `tx` after a preflight simulation, returns its signature without waiting for it
+    pub async fn send_tx(&self, tx: &Transaction) -> Result<Signature> {
+        Ok(self.rpc_client.send_transaction(tx).await?)
+    }
+
+    /// None until `signature` is processed, then whether the transaction succeeded.
+    /// the history is searched too, the order may be older than the status cache
+    pub async fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> Result<Option<Result<(), String>>> {
+        let status = self
+            .rpc_client
+            .get_signature_status_with_commitment_and_history(
+                signature,
+                CommitmentConfig::confirmed(),
+                true,
+            )
+            .await?;
+        Ok(status.map(|r| r.map_err(|e| e.to_string())))
+    }
+
+    /// get the jupiter swap of a transaction as one normalized trade
+    pub async fn get_jupiter_swap(&self, sig: &str) -> Result<Option<JupiterSwap>> {
+        let tx = self.get_transaction(sig).await?;
+        if let Some(e) = tx.transaction.meta.as_ref().and_then(|m| m.err.as_ref()) {
+            anyhow::bail!("Transaction error: {:?}", e);
+        }
+        parse_jupiter_swap(&tx)
+    }
+
+    /// raw token balance of `owner` for `mint` before and after a transaction
+    pub async fn get_token_balances(
+        &self,
+        sig: &str,
+        owner: &str,
+        mint: &str,
+    ) -> Result<(u64, u64)> {
+        let meta = self.get_tx(sig).await?;
+        let find = |balances: &OptionSerializer<Vec<UiTransactionTokenBalance>>| -> Result<u64> {
+            if let OptionSerializer::Some(balances) = balances {
+                for b in balances {
+                    if b.mint == mint && b.owner == OptionSerializer::Some(owner.to_string()) {
+                        return Ok(b.ui_token_amount.amount.parse()?);
+                    }
+                }
+            }
+            // no token account means no balance
+            Ok(0)
+        };
+        Ok((
+            find(&meta.pre_token_balances)?,
+            find(&meta.post_token_balances)?,
+        ))
+    }
+
+    /// block time of the oldest transaction of `mint`, None when it has more than `MAX_SIGNATURE_PAGES` pages
+    pub async fn get_token_created_at(&self, mint: &str) -> Result<Option<i64>> {
+        Ok(self
+            .get_oldest_signature(mint)
+            .await?
+            .and_then(|oldest| oldest.block_time))
+    }
+
+    /// fee payer of the oldest transaction of `wallet`, the account that funded it.
+    /// None when the wallet paid for it itself or its history is too long.
+    pub async fn get_funder(&self, wallet: &str) -> Result<Option<String>> {
+        let Some(oldest) = self.get_oldest_signature(wallet).await? else {
+            return Ok(None);
+        };
+        let tx = self.get_transaction(&oldest.signature).await?;
+        let EncodedTransaction::Json(ui_tx) = &tx.transaction.transaction else {
+            return Ok(None);
+        };
+        let UiMessage::Raw(message) = &ui_tx.message else {
+            return Ok(None);
+        };
+        Ok(message
+            .account_keys
+            .first()
+            .filter(|payer| *payer != wallet)
+            .cloned())
+    }
+
+    /// the `CreateEvent` of `mint` with the signature and slot of its creation transaction
+    pub async fn get_create_event(
+        &self,
+        mint: &str,
+    ) -> Result<Option<(String, u64, CreateEventData)>> {
+        let Some(oldest) = self.get_oldest_signature(mint).await? else {
+            return Ok(None);
+        };
+        let tx = self.get_transaction(&oldest.signature).await?;
+        let logs = match tx.transaction.meta.map(|m| m.log_messages) {
+            Some(OptionSerializer::Some(logs)) => logs,
+            _ => return Ok(None),
+        };
+        Ok(parse_create_events(&logs)
+            .into_iter()
+            .find(|e| e.mint == mint)
+            .map(|e| (oldest.signature, tx.slot, e)))
+    }
+
+    async fn get_oldest_signature(
+        &self,
+        address: &str,
+    ) -> Result<Option<RpcConfirmedTransactionStatusWithSignature>> {
+        let address = Pubkey::from_str(address)?;
+        let mut before = None;
+        for _ in 0..MAX_SIGNATURE_PAGES {
+            let mut page = self
+                .rpc_client
+                .get_signatures_for_address_with_config(
+                    &address,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until: None,
+                        limit: Some(SIGNATURE_PAGE_SIZE),
+                        commitment: Some(CommitmentConfig::confirmed()),
+                    },
+                )
+                .await?;
+            let full = page.len() == SIGNATURE_PAGE_SIZE;
+            let Some(oldest) = page.pop() else {
+                return Ok(None);
+            };
+            if !full {
+                return Ok(Some(oldest));
+            }
+            before = Some(Signature::from_str(&oldest.signature)?);
+        }
+        Ok(None)
+    }
+
+    pub fn parse_buy_info(&self, _meta: UiTransactionStatusMeta) -> Result<()> {
         Ok(())
     }
 }
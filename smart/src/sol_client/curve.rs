@@ -0,0 +1,136 @@
+//! constant product math of the pump.fun bonding curve, before the pump.fun fee
+
+use super::TradeEventData;
+
+/// pump.fun takes 1% of the SOL of every trade
+pub const PUMP_FEE_BPS: u64 = 100;
+
+/// the reserves of a bonding curve, raw units
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BondingCurve {
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+    pub real_token_reserves: u64, // tokens left to sell, a buy gets at most these
+}
+
+impl BondingCurve {
+    /// the curve right after `trade`
+    pub fn from_trade(trade: &TradeEventData) -> Self {
+        Self {
+            virtual_sol_reserves: trade.virtual_sol_reserves,
+            virtual_token_reserves: trade.virtual_token_reserves,
+            real_token_reserves: trade.real_token_reserves,
+        }
+    }
+
+    /// tokens received for `sol` lamports into the curve
+    pub fn buy_quote(&self, sol: u64) -> u64 {
+        let (vsr, vtr) = (
+            self.virtual_sol_reserves as u128,
+            self.virtual_token_reserves as u128,
+        );
+        if vsr + sol as u128 == 0 {
+            return 0;
+        }
+        let tokens = (vtr * sol as u128 / (vsr + sol as u128)) as u64;
+        tokens.min(self.real_token_reserves)
+    }
+
+    /// lamports into the curve buying exactly `tokens`, rounded up
+    pub fn buy_cost(&self, tokens: u64) -> u64 {
+        let (vsr, vtr) = (
+            self.virtual_sol_reserves as u128,
+            self.virtual_token_reserves as u128,
+        );
+        if tokens as u128 >= vtr {
+            return u64::MAX;
+        }
+        let left = vtr - tokens as u128;
+        ((vsr * tokens as u128).div_ceil(left)) as u64
+    }
+
+    /// the lamports a buy of `sol` spends, less once the tokens left cap it
+    pub fn buy_spend(&self, sol: u64) -> u64 {
+        let tokens = self.buy_quote(sol);
+        if tokens < self.real_token_reserves {
+            return sol;
+        }
+        sol.min(self.buy_cost(tokens))
+    }
+
+    /// lamports received for selling `tokens` to the curve
+    pub fn sell_quote(&self, tokens: u64) -> u64 {
+        let (vsr, vtr) = (
+            self.virtual_sol_reserves as u128,
+            self.virtual_token_reserves as u128,
+        );
+        if vtr + tokens as u128 == 0 {
+            return 0;
+        }
+        (vsr * tokens as u128 / (vtr + tokens as u128)) as u64
+    }
+
+    /// the curve after a buy of `tokens` for `sol` lamports
+    pub fn buy(&self, sol: u64, tokens: u64) -> Self {
+        Self {
+            virtual_sol_reserves: self.virtual_sol_reserves + sol,
+            virtual_token_reserves: self.virtual_token_reserves - tokens,
+            real_token_reserves: self.real_token_reserves - tokens,
+        }
+    }
+
+    /// the curve after a sell of `tokens` for `sol` lamports
+    pub fn sell(&self, tokens: u64, sol: u64) -> Self {
+        Self {
+            virtual_sol_reserves: self.virtual_sol_reserves - sol,
+            virtual_token_reserves: self.virtual_token_reserves + tokens,
+            real_token_reserves: self.real_token_reserves + tokens,
+        }
+    }
+
+    /// how far the average price of trading `tokens` for `sol` lamports is from the spot price, percent
+    pub fn slippage(&self, sol: u64, tokens: u64) -> f64 {
+        if tokens == 0 || self.virtual_token_reserves == 0 || self.virtual_sol_reserves == 0 {
+            return 0.0;
+        }
+        let spot = self.virtual_sol_reserves as f64 / self.virtual_token_reserves as f64;
+        let average = sol as f64 / tokens as f64;
+        (average - spot).abs() / spot * 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_bonding_curve_quotes() -> Result<()> {
+        // the curve before the buy of 1253951806 lamports for 37809162736217 tokens
+        let curve = BondingCurve {
+            virtual_sol_reserves: 32053044742,
+            virtual_token_reserves: 1004272769359248,
+            real_token_reserves: 724372769359248,
+        };
+        let tokens = curve.buy_quote(1253951806);
+        assert!(tokens.abs_diff(37809162736217) < 10_000);
+
+        let after = curve.buy(1253951806, 37809162736217);
+        assert_eq!(after.virtual_sol_reserves, 33306996548);
+        assert_eq!(after.virtual_token_reserves, 966463606623031);
+        // selling it back costs the rounding only, the fee is not in the curve
+        assert_eq!(after.sell_quote(37809162736217), 1253951805);
+
+        // a bigger buy moves the price further
+        let small = curve.slippage(1_000_000_000, curve.buy_quote(1_000_000_000));
+        let big = curve.slippage(10_000_000_000, curve.buy_quote(10_000_000_000));
+        assert!(small > 0.0 && small < big);
+        // the last tokens of the curve, for what they cost and not the whole buy
+        assert_eq!(curve.buy_quote(u64::MAX / 2), curve.real_token_reserves);
+        let spend = curve.buy_spend(u64::MAX / 2);
+        assert_eq!(curve.buy_quote(spend), curve.real_token_reserves);
+        assert!(curve.buy_quote(spend - 1) < curve.real_token_reserves);
+        assert_eq!(curve.buy_spend(1253951806), 1253951806);
+        Ok(())
+    }
+}
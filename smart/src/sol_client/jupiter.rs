@@ -0,0 +1,421 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{bs58, pubkey::Pubkey};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta,
+    EncodedTransaction, UiInstruction, UiMessage,
+};
+
+pub const JUPITER_V6_PROGRAM_ID: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+/// wrapped SOL, the SOL side of a swap
+pub const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+// anchor emits events through a self cpi, the instruction data starts with this tag
+const EVENT_IX_TAG: [u8; 8] = [228, 69, 165, 46, 81, 203, 154, 29];
+// sha256("event:SwapEvent")[..8]
+const SWAP_EVENT_DISCRIMINATOR: [u8; 8] = [64, 198, 205, 232, 38, 8, 113, 226];
+
+// sha256("global:<instruction name>")[..8]
+const ROUTE_DISCRIMINATOR: [u8; 8] = [229, 23, 203, 151, 122, 227, 173, 42];
+const ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR: [u8; 8] = [150, 86, 71, 116, 167, 93, 14, 104];
+const SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR: [u8; 8] = [193, 32, 155, 51, 65, 214, 156, 129];
+const SHARED_ACCOUNTS_ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR: [u8; 8] =
+    [230, 121, 143, 80, 119, 159, 106, 170];
+const EXACT_OUT_ROUTE_DISCRIMINATOR: [u8; 8] = [208, 51, 239, 151, 123, 43, 237, 92];
+const SHARED_ACCOUNTS_EXACT_OUT_ROUTE_DISCRIMINATOR: [u8; 8] =
+    [176, 209, 105, 168, 154, 125, 69, 62];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SwapEvent {
+    pub amm: String,
+    pub input_mint: String,
+    pub input_amount: u64,
+    pub output_mint: String,
+    pub output_amount: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RouteKind {
+    Route,
+    RouteWithTokenLedger,
+    SharedAccountsRoute,
+    SharedAccountsRouteWithTokenLedger,
+    ExactOutRoute,
+    SharedAccountsExactOutRoute,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteInstruction {
+    pub kind: RouteKind,
+    pub in_amount: Option<u64>, // exact in routes, None when read from token ledger
+    pub quoted_out_amount: Option<u64>, // exact in routes
+    pub out_amount: Option<u64>, // exact out routes
+    pub quoted_in_amount: Option<u64>, // exact out routes
+    pub slippage_bps: u16,
+    pub platform_fee_bps: u8,
+}
+
+/// one jupiter swap transaction normalized into a single trade
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JupiterSwap {
+    pub signature: String,
+    pub user: String, // fee payer, the wallet who signed the swap
+    pub input_mint: String,
+    pub input_amount: u64,
+    pub output_mint: String,
+    pub output_amount: u64,
+    pub amms: Vec<String>, // amm programs used by the route, in order
+    pub route: Option<RouteInstruction>,
+    pub events: Vec<SwapEvent>,
+}
+
+impl JupiterSwap {
+    /// SOL spent for a token
+    pub fn is_buy(&self) -> bool {
+        self.input_mint == WSOL_MINT && self.output_mint != WSOL_MINT
+    }
+
+    /// a token sold for SOL
+    pub fn is_sell(&self) -> bool {
+        self.output_mint == WSOL_MINT && self.input_mint != WSOL_MINT
+    }
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Option<String> {
+    let arr: [u8; 32] = data.get(offset..offset + 32)?.try_into().ok()?;
+    Some(Pubkey::new_from_array(arr).to_string())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+/// decode the data of an anchor event cpi instruction into a `SwapEvent`
+pub fn parse_swap_event(data: &[u8]) -> Option<SwapEvent> {
+    /* 数据布局如下：
+       event_ix_tag: 8 byte
+       event_flag: 8 byte
+       amm: 32 byte
+       inputMint: 32 byte
+       inputAmount: 8 byte
+       outputMint: 32 byte
+       outputAmount: 8 byte
+    */
+    if data.len() < 128 || data[..8] != EVENT_IX_TAG || data[8..16] != SWAP_EVENT_DISCRIMINATOR {
+        return None;
+    }
+    Some(SwapEvent {
+        amm: read_pubkey(data, 16)?,
+        input_mint: read_pubkey(data, 48)?,
+        input_amount: read_u64(data, 80)?,
+        output_mint: read_pubkey(data, 88)?,
+        output_amount: read_u64(data, 120)?,
+    })
+}
+
+/// decode a jupiter route instruction.
+///
+/// the route plan in the middle is a vec of swap enums with a lot of variants, so only the
+/// fixed size fields at the end of the data are read, the amms come from the swap events.
+pub fn parse_route_instruction(data: &[u8]) -> Option<RouteInstruction> {
+    // discriminator + route plan length + the fixed size fields
+    if data.len() < 8 + 4 + 11 {
+        return None;
+    }
+    let discriminator: [u8; 8] = data[..8].try_into().ok()?;
+    let kind = match discriminator {
+        ROUTE_DISCRIMINATOR => RouteKind::Route,
+        ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR => RouteKind::RouteWithTokenLedger,
+        SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR => RouteKind::SharedAccountsRoute,
+        SHARED_ACCOUNTS_ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR => {
+            RouteKind::SharedAccountsRouteWithTokenLedger
+        }
+        EXACT_OUT_ROUTE_DISCRIMINATOR => RouteKind::ExactOutRoute,
+        SHARED_ACCOUNTS_EXACT_OUT_ROUTE_DISCRIMINATOR => RouteKind::SharedAccountsExactOutRoute,
+        _ => return None,
+    };
+
+    // every route ends with slippage_bps: u16 and platform_fee_bps: u8
+    let len = data.len();
+    let platform_fee_bps = data[len - 1];
+    let slippage_bps = read_u16(data, len - 3)?;
+    let mut route = RouteInstruction {
+        kind,
+        in_amount: None,
+        quoted_out_amount: None,
+        out_amount: None,
+        quoted_in_amount: None,
+        slippage_bps,
+        platform_fee_bps,
+    };
+    match kind {
+        RouteKind::Route | RouteKind::SharedAccountsRoute => {
+            route.in_amount = Some(read_u64(data, len - 19)?);
+            route.quoted_out_amount = Some(read_u64(data, len - 11)?);
+        }
+        RouteKind::RouteWithTokenLedger | RouteKind::SharedAccountsRouteWithTokenLedger => {
+            route.quoted_out_amount = Some(read_u64(data, len - 11)?);
+        }
+        RouteKind::ExactOutRoute | RouteKind::SharedAccountsExactOutRoute => {
+            route.out_amount = Some(read_u64(data, len - 19)?);
+            route.quoted_in_amount = Some(read_u64(data, len - 11)?);
+        }
+    }
+    Some(route)
+}
+
+/// merge the swap events of one transaction into a single trade.
+///
+/// the input is what the first hops spent and the output what the last hops received: a first hop
+/// spends a mint no earlier hop received, a last hop receives a mint no later hop spends.
+/// split routes have several of each, a round trip of one mint counts its two ends once.
+pub fn normalize_swap_events(
+    events: &[SwapEvent],
+) -> Option<(String, u64, String, u64, Vec<String>)> {
+    let input_mint = events.first()?.input_mint.clone();
+    let output_mint = events.last()?.output_mint.clone();
+    let input_amount = events
+        .iter()
+        .enumerate()
+        .filter(|(i, e)| {
+            e.input_mint == input_mint
+                && !events[..*i].iter().any(|p| p.output_mint == e.input_mint)
+        })
+        .map(|(_, e)| e.input_amount)
+        .sum();
+    let output_amount = events
+        .iter()
+        .enumerate()
+        .filter(|(i, e)| {
+            e.output_mint == output_mint
+                && !events[i + 1..]
+                    .iter()
+                    .any(|n| n.input_mint == e.output_mint)
+        })
+        .map(|(_, e)| e.output_amount)
+        .sum();
+    let mut amms: Vec<String> = Vec::new();
+    for e in events {
+        if !amms.contains(&e.amm) {
+            amms.push(e.amm.clone());
+        }
+    }
+    Some((input_mint, input_amount, output_mint, output_amount, amms))
+}
+
+/// find the jupiter route and swap events in a json encoded transaction.
+///
+/// returns `Ok(None)` when the transaction did not swap through jupiter.
+pub fn parse_jupiter_swap(
+    tx: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<Option<JupiterSwap>> {
+    let EncodedTransaction::Json(ui_tx) = &tx.transaction.transaction else {
+        return Err(anyhow!("Transaction is not json encoded"));
+    };
+    let UiMessage::Raw(message) = &ui_tx.message else {
+        return Err(anyhow!("Transaction message is not raw"));
+    };
+    let meta = tx
+        .transaction
+        .meta
+        .as_ref()
+        .ok_or_else(|| anyhow!("Transaction meta not found"))?;
+
+    // v0 transactions load part of the accounts from lookup tables
+    let mut account_keys = message.account_keys.clone();
+    if let OptionSerializer::Some(loaded) = &meta.loaded_addresses {
+        account_keys.extend(loaded.writable.iter().cloned());
+        account_keys.extend(loaded.readonly.iter().cloned());
+    }
+    let is_jupiter = |program_id_index: u8| {
+        account_keys
+            .get(program_id_index as usize)
+            .is_some_and(|k| k == JUPITER_V6_PROGRAM_ID)
+    };
+
+    // the route may be a top level instruction or called by another program
+    let mut jupiter_ix_data: Vec<Vec<u8>> = message
+        .instructions
+        .iter()
+        .filter(|ix| is_jupiter(ix.program_id_index))
+        .filter_map(|ix| bs58::decode(&ix.data).into_vec().ok())
+        .collect();
+    if let OptionSerializer::Some(inner) = &meta.inner_instructions {
+        for ix in inner.iter().flat_map(|i| i.instructions.iter()) {
+            if let UiInstruction::Compiled(ix) = ix {
+                if is_jupiter(ix.program_id_index) {
+                    if let Ok(data) = bs58::decode(&ix.data).into_vec() {
+                        jupiter_ix_data.push(data);
+                    }
+                }
+            }
+        }
+    }
+
+    let route = jupiter_ix_data
+        .iter()
+        .find_map(|d| parse_route_instruction(d));
+    let events: Vec<SwapEvent> = jupiter_ix_data
+        .iter()
+        .filter_map(|d| parse_swap_event(d))
+        .collect();
+    let Some((input_mint, input_amount, output_mint, output_amount, amms)) =
+        normalize_swap_events(&events)
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(JupiterSwap {
+        signature: ui_tx.signatures.first().cloned().unwrap_or_default(),
+        user: account_keys.first().cloned().unwrap_or_default(),
+        input_mint,
+        input_amount,
+        output_mint,
+        output_amount,
+        amms,
+        route,
+        events,
+    }))
+}
+
+/// whether the logs of a transaction show a jupiter invocation
+pub fn is_jupiter_logs(logs: &[String]) -> bool {
+    let invoke_flag = format!("Program {} invoke", JUPITER_V6_PROGRAM_ID);
+    logs.iter().any(|l| l.starts_with(&invoke_flag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::hash;
+
+    fn event_data(
+        amm: &Pubkey,
+        input: &Pubkey,
+        in_amount: u64,
+        output: &Pubkey,
+        out_amount: u64,
+    ) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&EVENT_IX_TAG);
+        data.extend_from_slice(&SWAP_EVENT_DISCRIMINATOR);
+        data.extend_from_slice(amm.as_ref());
+        data.extend_from_slice(input.as_ref());
+        data.extend_from_slice(&in_amount.to_le_bytes());
+        data.extend_from_slice(output.as_ref());
+        data.extend_from_slice(&out_amount.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_discriminators() {
+        let d = |s: &str| -> [u8; 8] { hash(s.as_bytes()).to_bytes()[..8].try_into().unwrap() };
+        assert_eq!(SWAP_EVENT_DISCRIMINATOR, d("event:SwapEvent"));
+        // the tag is sha256("anchor:event")[..8] written as a little endian u64
+        let mut tag = d("anchor:event");
+        tag.reverse();
+        assert_eq!(EVENT_IX_TAG, tag);
+        assert_eq!(ROUTE_DISCRIMINATOR, d("global:route"));
+        assert_eq!(
+            ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR,
+            d("global:route_with_token_ledger")
+        );
+        assert_eq!(
+            SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR,
+            d("global:shared_accounts_route")
+        );
+        assert_eq!(
+            SHARED_ACCOUNTS_ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR,
+            d("global:shared_accounts_route_with_token_ledger")
+        );
+        assert_eq!(EXACT_OUT_ROUTE_DISCRIMINATOR, d("global:exact_out_route"));
+        assert_eq!(
+            SHARED_ACCOUNTS_EXACT_OUT_ROUTE_DISCRIMINATOR,
+            d("global:shared_accounts_exact_out_route")
+        );
+    }
+
+    #[test]
+    fn test_parse_swap_event_and_route() -> Result<()> {
+        let amm = Pubkey::new_unique();
+        let sol = Pubkey::new_unique();
+        let usdc = Pubkey::new_unique();
+        let event = parse_swap_event(&event_data(&amm, &sol, 1_000_000_000, &usdc, 190_000_000))
+            .ok_or_else(|| anyhow!("parse swap event failed"))?;
+        assert_eq!(event.amm, amm.to_string());
+        assert_eq!(event.input_mint, sol.to_string());
+        assert_eq!(event.input_amount, 1_000_000_000);
+        assert_eq!(event.output_mint, usdc.to_string());
+        assert_eq!(event.output_amount, 190_000_000);
+
+        // route with an empty route plan
+        let mut data = ROUTE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&1_000_000_000u64.to_le_bytes());
+        data.extend_from_slice(&190_500_000u64.to_le_bytes());
+        data.extend_from_slice(&50u16.to_le_bytes());
+        data.push(0);
+        let route = parse_route_instruction(&data).ok_or_else(|| anyhow!("parse route failed"))?;
+        assert_eq!(route.kind, RouteKind::Route);
+        assert_eq!(route.in_amount, Some(1_000_000_000));
+        assert_eq!(route.quoted_out_amount, Some(190_500_000));
+        assert_eq!(route.slippage_bps, 50);
+        assert_eq!(route.platform_fee_bps, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_split_route() -> Result<()> {
+        let (amm_a, amm_b, amm_c) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (sol, usdc, bonk) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        // 60% sol -> bonk directly, 40% sol -> usdc -> bonk
+        let events: Vec<SwapEvent> = [
+            event_data(&amm_a, &sol, 600, &bonk, 6_000),
+            event_data(&amm_b, &sol, 400, &usdc, 76),
+            event_data(&amm_c, &usdc, 76, &bonk, 3_900),
+        ]
+        .iter()
+        .filter_map(|d| parse_swap_event(d))
+        .collect();
+        let (input_mint, input_amount, output_mint, output_amount, amms) =
+            normalize_swap_events(&events).ok_or_else(|| anyhow!("normalize failed"))?;
+        assert_eq!(input_mint, sol.to_string());
+        assert_eq!(input_amount, 1_000);
+        assert_eq!(output_mint, bonk.to_string());
+        assert_eq!(output_amount, 9_900);
+        assert_eq!(amms.len(), 3);
+
+        // sol -> usdc -> sol -> bonk -> sol, the sol in the middle is not an end of the round trip
+        let events: Vec<SwapEvent> = [
+            event_data(&amm_a, &sol, 1_000, &usdc, 190),
+            event_data(&amm_b, &usdc, 190, &sol, 1_001),
+            event_data(&amm_a, &sol, 1_001, &bonk, 10_000),
+            event_data(&amm_c, &bonk, 10_000, &sol, 1_002),
+        ]
+        .iter()
+        .filter_map(|d| parse_swap_event(d))
+        .collect();
+        let (input_mint, input_amount, output_mint, output_amount, _) =
+            normalize_swap_events(&events).ok_or_else(|| anyhow!("normalize failed"))?;
+        assert_eq!(input_mint, output_mint);
+        assert_eq!(input_amount, 1_000);
+        assert_eq!(output_amount, 1_002);
+        Ok(())
+    }
+}
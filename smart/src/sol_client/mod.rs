@@ -1,13 +1,23 @@
 pub mod client;
+pub mod curve;
+pub mod jupiter;
+pub mod pump;
 
 use anyhow::{anyhow, Result};
 use base64::{engine::general_purpose::STANDARD as base64, Engine};
-use hex;
 use serde::{Deserialize, Serialize};
 use solana_sdk::pubkey::Pubkey;
-use tracing::{debug, info};
+use tracing::debug;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+pub const PUMP_PROGRAM_ID: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
+pub const SOL_DECIMALS: u32 = 9;
+pub const PUMP_TOKEN_DECIMALS: u32 = 6;
+// every pump.fun token is minted with 1 billion tokens, raw units
+pub const PUMP_TOKEN_TOTAL_SUPPLY: u64 = 1_000_000_000_000_000;
+// tokens sold on the bonding curve before it completes, raw units
+pub const PUMP_INITIAL_REAL_TOKEN_RESERVES: u64 = 793_100_000_000_000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TradeEventData {
     pub mint: String,
     pub sol_amount: u64,
@@ -21,12 +31,165 @@ pub struct TradeEventData {
     pub real_token_reserves: u64,
 }
 
+impl TradeEventData {
+    /// token price in SOL after this trade, from the bonding curve virtual reserves
+    pub fn price(&self) -> f64 {
+        if self.virtual_token_reserves == 0 {
+            return 0.0;
+        }
+        ui_amount(self.virtual_sol_reserves, SOL_DECIMALS)
+            / ui_amount(self.virtual_token_reserves, PUMP_TOKEN_DECIMALS)
+    }
+
+    /// percent of the curve supply sold after this trade, 100 once the curve is complete
+    pub fn curve_progress(&self) -> f64 {
+        let left = self
+            .real_token_reserves
+            .min(PUMP_INITIAL_REAL_TOKEN_RESERVES);
+        (PUMP_INITIAL_REAL_TOKEN_RESERVES - left) as f64 / PUMP_INITIAL_REAL_TOKEN_RESERVES as f64
+            * 100.0
+    }
+
+    pub fn sol_ui_amount(&self) -> f64 {
+        ui_amount(self.sol_amount, SOL_DECIMALS)
+    }
+
+    pub fn token_ui_amount(&self) -> f64 {
+        ui_amount(self.token_amount, PUMP_TOKEN_DECIMALS)
+    }
+}
+
+pub fn ui_amount(amount: u64, decimals: u32) -> f64 {
+    amount as f64 / 10f64.powi(decimals as i32)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TradeEvent {
     pub name: String,
     pub data: TradeEventData,
 }
 
+/// collect the pump.fun trade events from the `Program data` lines of a transaction's logs
+pub fn parse_trade_events(logs: &[String]) -> Vec<TradeEvent> {
+    logs.iter()
+        .filter(|l| l.starts_with("Program data: "))
+        .filter_map(|l| match parse_program_data(l) {
+            Ok(events) => Some(events),
+            Err(e) => {
+                debug!("skip program data: {:?}, error: {}", l, e);
+                None
+            }
+        })
+        .flatten()
+        .collect()
+}
+
+/// a token launch, decoded from a pump.fun `CreateEvent`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateEventData {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub mint: String,
+    pub bonding_curve: String,
+    pub user: String, // creator of the token
+}
+
+/// collect the pump.fun create events from the `Program data` lines of a transaction's logs
+pub fn parse_create_events(logs: &[String]) -> Vec<CreateEventData> {
+    logs.iter()
+        .filter_map(|l| l.strip_prefix("Program data: "))
+        .filter_map(|data| base64.decode(data).ok())
+        .filter(|decoded| decoded.len() > 8 && decoded[..8] == CREATE_EVENT_DISCRIMINATOR)
+        .filter_map(|decoded| match decode_create_event(&decoded[8..]) {
+            Ok(event) => Some(event),
+            Err(e) => {
+                debug!("skip create event: {:?}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+// sha256("event:CreateEvent")[..8]
+const CREATE_EVENT_DISCRIMINATOR: [u8; 8] = [27, 114, 169, 77, 222, 235, 99, 118];
+
+/* CreateEvent 是 borsh 编码, 字符串是 4 byte 长度 + utf8:
+   name: string
+   symbol: string
+   uri: string
+   mint: 32 byte
+   bondingCurve: 32 byte
+   user: 32 byte
+*/
+fn decode_create_event(mut data: &[u8]) -> Result<CreateEventData> {
+    let string = |data: &mut &[u8]| -> Result<String> {
+        let len = u32::from_le_bytes(take(data, 4)?.try_into()?) as usize;
+        Ok(String::from_utf8(take(data, len)?.to_vec())?)
+    };
+    let name = string(&mut data)?;
+    let symbol = string(&mut data)?;
+    let uri = string(&mut data)?;
+    let pubkey = |data: &mut &[u8]| -> Result<String> {
+        let arr: [u8; 32] = take(data, 32)?.try_into()?;
+        Ok(Pubkey::new_from_array(arr).to_string())
+    };
+    Ok(CreateEventData {
+        name,
+        symbol,
+        uri,
+        mint: pubkey(&mut data)?,
+        bonding_curve: pubkey(&mut data)?,
+        user: pubkey(&mut data)?,
+    })
+}
+
+/// the end of a bonding curve, decoded from a pump.fun `CompleteEvent`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompleteEventData {
+    pub user: String, // who bought the last tokens of the curve
+    pub mint: String,
+    pub bonding_curve: String,
+    pub timestamp: i64,
+}
+
+// sha256("event:CompleteEvent")[..8]
+const COMPLETE_EVENT_DISCRIMINATOR: [u8; 8] = [95, 114, 97, 156, 212, 46, 152, 8];
+
+/// collect the pump.fun complete events from the `Program data` lines of a transaction's logs
+pub fn parse_complete_events(logs: &[String]) -> Vec<CompleteEventData> {
+    logs.iter()
+        .filter_map(|l| l.strip_prefix("Program data: "))
+        .filter_map(|data| base64.decode(data).ok())
+        // 8 + 32 + 32 + 32 + 8 = 112
+        .filter(|decoded| decoded.len() == 112 && decoded[..8] == COMPLETE_EVENT_DISCRIMINATOR)
+        .map(|decoded| {
+            let pubkey = |i: usize| {
+                let arr: [u8; 32] = decoded[i..i + 32].try_into().unwrap();
+                Pubkey::new_from_array(arr).to_string()
+            };
+            CompleteEventData {
+                user: pubkey(8),
+                mint: pubkey(40),
+                bonding_curve: pubkey(72),
+                timestamp: i64::from_le_bytes(decoded[104..112].try_into().unwrap()),
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn take<'a>(data: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    if data.len() < n {
+        return Err(anyhow!("Invalid event data length: {}", data.len()));
+    }
+    let (head, rest) = data.split_at(n);
+    *data = rest;
+    Ok(head)
+}
+
+// sha256("event:TradeEvent")[..8]
+pub(crate) const TRADE_EVENT_DISCRIMINATOR: [u8; 8] = [189, 219, 127, 211, 78, 230, 97, 238];
+
 pub fn parse_program_data(program_data: &str) -> Result<Vec<TradeEvent>> {
     // Remove "Program data: " prefix
     let data = program_data
@@ -56,9 +219,10 @@ pub fn parse_program_data(program_data: &str) -> Result<Vec<TradeEvent>> {
             decoded.len()
         )));
     }
-    // decode
+    // decode, other events of the program share the log line format
     let trade_events: Vec<TradeEvent> = decoded
         .chunks(129)
+        .filter(|chunk| chunk[..8] == TRADE_EVENT_DISCRIMINATOR)
         .map(|chunk| {
             let mint_arr: [u8; 32] = chunk[8..40].try_into().unwrap();
             let mint = Pubkey::new_from_array(mint_arr).to_string();
@@ -131,7 +295,7 @@ mod tests {
         );
         assert_eq!(event.data.sol_amount, 1253951806);
         assert_eq!(event.data.token_amount, 37809162736217);
-        assert_eq!(event.data.is_buy, true);
+        assert!(event.data.is_buy);
         assert_eq!(
             event.data.user,
             "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x"
@@ -141,6 +305,7 @@ mod tests {
         assert_eq!(event.data.virtual_token_reserves, 966463606623031);
         assert_eq!(event.data.real_sol_reserves, 3306996548);
         assert_eq!(event.data.real_token_reserves, 686563606623031);
+        assert!((event.data.price() - 3.4463e-8).abs() < 1e-11);
 
         Ok(())
     }
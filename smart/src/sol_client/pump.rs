@@ -0,0 +1,432 @@
+//! pump.fun buy and sell transactions, priced from the bonding curve with slippage bounds
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as base64, Engine};
+use serde::Deserialize;
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_program,
+    transaction::Transaction,
+};
+use validator::Validate;
+
+use super::{
+    curve::{BondingCurve, PUMP_FEE_BPS},
+    take, PUMP_PROGRAM_ID,
+};
+
+pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+
+// sha256("global:buy")[..8]
+const BUY_DISCRIMINATOR: [u8; 8] = [102, 6, 61, 18, 1, 218, 235, 234];
+// sha256("global:sell")[..8]
+const SELL_DISCRIMINATOR: [u8; 8] = [51, 230, 133, 164, 1, 127, 131, 173];
+// sha256("account:BondingCurve")[..8]
+pub(crate) const BONDING_CURVE_DISCRIMINATOR: [u8; 8] = [23, 183, 248, 55, 96, 216, 172, 96];
+// sha256("account:Global")[..8]
+pub(crate) const GLOBAL_DISCRIMINATOR: [u8; 8] = [167, 232, 232, 177, 200, 108, 114, 127];
+
+fn pubkey(s: &str) -> Pubkey {
+    Pubkey::from_str(s).expect("Invalid builtin pubkey")
+}
+
+/// the associated token account of `owner` for `mint`
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    let token_program = pubkey(TOKEN_PROGRAM_ID);
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &pubkey(ASSOCIATED_TOKEN_PROGRAM_ID),
+    )
+    .0
+}
+
+/// create the associated token account of `owner` for `mint` unless it exists
+pub fn create_associated_token_account_idempotent(
+    payer: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: pubkey(ASSOCIATED_TOKEN_PROGRAM_ID),
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(associated_token_address(owner, mint), false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(pubkey(TOKEN_PROGRAM_ID), false),
+        ],
+        data: vec![1], // CreateIdempotent
+    }
+}
+
+/// the bonding curve of a mint with the accounts its trades pay the fees to, read from the chain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PumpMarket {
+    pub curve: BondingCurve,
+    pub creator: Pubkey, // of the bonding curve, paid through its creator vault
+    pub fee_recipient: Pubkey, // set in the global account
+}
+
+impl PumpMarket {
+    /// decode the `BondingCurve` account of the mint and the pump.fun `Global` account
+    pub fn from_account_data(curve: &[u8], global: &[u8]) -> Result<Self> {
+        let creator = curve
+            .get(49..81)
+            .ok_or_else(|| anyhow!("Bonding curve without a creator"))?;
+        let mut global = global;
+        if take(&mut global, 8)? != GLOBAL_DISCRIMINATOR {
+            return Err(anyhow!("Not a pump.fun global account"));
+        }
+        // initialized, authority
+        take(&mut global, 33)?;
+        let fee_recipient = take(&mut global, 32)?;
+        Ok(Self {
+            curve: BondingCurve::from_account_data(curve)?,
+            creator: Pubkey::try_from(creator)?,
+            fee_recipient: Pubkey::try_from(fee_recipient)?,
+        })
+    }
+}
+
+/// the accounts of the pump.fun trades of a mint
+#[derive(Debug, Clone, PartialEq)]
+pub struct PumpAccounts {
+    pub program: Pubkey,
+    pub global: Pubkey,
+    pub mint: Pubkey,
+    pub bonding_curve: Pubkey,
+    pub associated_bonding_curve: Pubkey,
+    pub event_authority: Pubkey,
+}
+
+impl PumpAccounts {
+    pub fn new(mint: &Pubkey) -> Self {
+        let program = pubkey(PUMP_PROGRAM_ID);
+        let pda = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &program).0;
+        let bonding_curve = pda(&[b"bonding-curve", mint.as_ref()]);
+        Self {
+            program,
+            global: pda(&[b"global"]),
+            mint: *mint,
+            bonding_curve,
+            associated_bonding_curve: associated_token_address(&bonding_curve, mint),
+            event_authority: pda(&[b"__event_authority"]),
+        }
+    }
+
+    /// the account collecting the creator fee of the curves of `creator`
+    pub fn creator_vault(&self, creator: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"creator-vault", creator.as_ref()], &self.program).0
+    }
+
+    /// buy `token_amount` raw tokens for at most `max_sol_cost` lamports, the fees included
+    pub fn buy(
+        &self,
+        user: &Pubkey,
+        market: &PumpMarket,
+        token_amount: u64,
+        max_sol_cost: u64,
+    ) -> Instruction {
+        let mut data = BUY_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&token_amount.to_le_bytes());
+        data.extend_from_slice(&max_sol_cost.to_le_bytes());
+        Instruction {
+            program_id: self.program,
+            accounts: vec![
+                AccountMeta::new_readonly(self.global, false),
+                AccountMeta::new(market.fee_recipient, false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new(self.bonding_curve, false),
+                AccountMeta::new(self.associated_bonding_curve, false),
+                AccountMeta::new(associated_token_address(user, &self.mint), false),
+                AccountMeta::new(*user, true),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(pubkey(TOKEN_PROGRAM_ID), false),
+                AccountMeta::new(self.creator_vault(&market.creator), false),
+                AccountMeta::new_readonly(self.event_authority, false),
+                AccountMeta::new_readonly(self.program, false),
+            ],
+            data,
+        }
+    }
+
+    /// sell `token_amount` raw tokens for at least `min_sol_output` lamports, the fees deducted
+    pub fn sell(
+        &self,
+        user: &Pubkey,
+        market: &PumpMarket,
+        token_amount: u64,
+        min_sol_output: u64,
+    ) -> Instruction {
+        let mut data = SELL_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&token_amount.to_le_bytes());
+        data.extend_from_slice(&min_sol_output.to_le_bytes());
+        Instruction {
+            program_id: self.program,
+            accounts: vec![
+                AccountMeta::new_readonly(self.global, false),
+                AccountMeta::new(market.fee_recipient, false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new(self.bonding_curve, false),
+                AccountMeta::new(self.associated_bonding_curve, false),
+                AccountMeta::new(associated_token_address(user, &self.mint), false),
+                AccountMeta::new(*user, true),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(self.creator_vault(&market.creator), false),
+                AccountMeta::new_readonly(pubkey(TOKEN_PROGRAM_ID), false),
+                AccountMeta::new_readonly(self.event_authority, false),
+                AccountMeta::new_readonly(self.program, false),
+            ],
+            data,
+        }
+    }
+}
+
+impl BondingCurve {
+    /// decode the pump.fun `BondingCurve` account, a completed curve trades on raydium
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        let mut data = data;
+        if take(&mut data, 8)? != BONDING_CURVE_DISCRIMINATOR {
+            return Err(anyhow!("Not a bonding curve account"));
+        }
+        let mut u64_le =
+            || -> Result<u64> { Ok(u64::from_le_bytes(take(&mut data, 8)?.try_into()?)) };
+        let virtual_token_reserves = u64_le()?;
+        let virtual_sol_reserves = u64_le()?;
+        let real_token_reserves = u64_le()?;
+        let _real_sol_reserves = u64_le()?;
+        let _token_total_supply = u64_le()?;
+        if take(&mut data, 1)?[0] != 0 {
+            return Err(anyhow!("Bonding curve is complete"));
+        }
+        Ok(Self {
+            virtual_sol_reserves,
+            virtual_token_reserves,
+            real_token_reserves,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct PumpTxConfig {
+    #[validate(range(max = 10_000))]
+    pub slippage_bps: u64, // 滑点, bounds max_sol_cost and min_sol_output
+    pub compute_unit_limit: u32,
+    pub compute_unit_price: u64, // priority fee, micro lamports per compute unit, 0 disables
+}
+
+impl Default for PumpTxConfig {
+    fn default() -> Self {
+        Self {
+            slippage_bps: 500,
+            compute_unit_limit: 100_000,
+            compute_unit_price: 100_000,
+        }
+    }
+}
+
+/// a pump.fun trade quoted from the curve
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PumpQuote {
+    pub is_buy: bool,
+    pub token_amount: u64,
+    pub sol_amount: u64, // expected lamports paid or received, the fee included
+    pub sol_limit: u64,  // max_sol_cost of a buy, min_sol_output of a sell
+}
+
+impl PumpTxConfig {
+    /// buy with `sol_amount` lamports into the curve, the fee comes on top.
+    ///
+    /// the last tokens of a curve cost what they cost, not the whole `sol_amount`
+    pub fn quote_buy(&self, curve: &BondingCurve, sol_amount: u64) -> PumpQuote {
+        let sol_amount = curve.buy_spend(sol_amount);
+        let token_amount = curve.buy_quote(sol_amount);
+        let cost = sol_amount + sol_amount * PUMP_FEE_BPS / 10_000;
+        PumpQuote {
+            is_buy: true,
+            token_amount,
+            sol_amount: cost,
+            sol_limit: (cost as u128 * (10_000 + self.slippage_bps) as u128 / 10_000) as u64,
+        }
+    }
+
+    pub fn quote_sell(&self, curve: &BondingCurve, token_amount: u64) -> PumpQuote {
+        let sol = curve.sell_quote(token_amount);
+        let output = sol - sol * PUMP_FEE_BPS / 10_000;
+        PumpQuote {
+            is_buy: false,
+            token_amount,
+            sol_amount: output,
+            sol_limit: (output as u128 * (10_000 - self.slippage_bps) as u128 / 10_000) as u64,
+        }
+    }
+
+    /// the signed transaction of `quote` in `mint`, a buy creates the token account first
+    pub fn build_tx(
+        &self,
+        payer: &Keypair,
+        mint: &Pubkey,
+        market: &PumpMarket,
+        quote: &PumpQuote,
+        recent_blockhash: Hash,
+    ) -> Transaction {
+        let user = payer.pubkey();
+        let accounts = PumpAccounts::new(mint);
+        let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+            self.compute_unit_limit,
+        )];
+        if self.compute_unit_price > 0 {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+                self.compute_unit_price,
+            ));
+        }
+        if quote.is_buy {
+            instructions.push(create_associated_token_account_idempotent(
+                &user, &user, mint,
+            ));
+            instructions.push(accounts.buy(&user, market, quote.token_amount, quote.sol_limit));
+        } else {
+            instructions.push(accounts.sell(&user, market, quote.token_amount, quote.sol_limit));
+        }
+        Transaction::new_signed_with_payer(&instructions, Some(&user), &[payer], recent_blockhash)
+    }
+}
+
+/// the wire format of `tx` in base64, as taken by `sendTransaction` and `simulateTransaction`
+pub fn serialize_tx(tx: &Transaction) -> Result<String> {
+    Ok(base64.encode(bincode::serialize(tx)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pump_buy_sell_tx() -> Result<()> {
+        let mint = pubkey("7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump");
+        let accounts = PumpAccounts::new(&mint);
+        assert_eq!(
+            accounts.global.to_string(),
+            "4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf"
+        );
+        assert_eq!(
+            accounts.event_authority.to_string(),
+            "Ce6TQqeHC9p8KetsN6JsjHK7UTZk7nasjjnr7XxXp9F1"
+        );
+
+        // the account after the buy of the fixture
+        let mut data = BONDING_CURVE_DISCRIMINATOR.to_vec();
+        for v in [
+            966463606623031u64,
+            33306996548,
+            686563606623031,
+            3306996548,
+            1_000_000_000_000_000,
+        ] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        data.push(0);
+        let creator = Pubkey::new_unique();
+        data.extend_from_slice(creator.as_ref());
+        let fee_recipient = pubkey("CebN5WGQ4jvEPvsVU4EoHEpgzq1VV7AbicfhtW4xC9iM");
+        let mut global = GLOBAL_DISCRIMINATOR.to_vec();
+        global.push(1);
+        global.extend_from_slice(&[0; 32]);
+        global.extend_from_slice(fee_recipient.as_ref());
+        let market = PumpMarket::from_account_data(&data, &global)?;
+        assert_eq!(market.creator, creator);
+        assert_eq!(market.fee_recipient, fee_recipient);
+        let curve = market.curve;
+        assert_eq!(curve.virtual_sol_reserves, 33306996548);
+        assert!(PumpMarket::from_account_data(&data[..49], &global).is_err());
+        data[48] = 1;
+        assert!(BondingCurve::from_account_data(&data).is_err());
+
+        // the account order of the pump.fun idl, the creator fee paid into the creator vault
+        let user = Pubkey::new_unique();
+        let metas = |ix: Instruction| -> Vec<(Pubkey, bool, bool)> {
+            ix.accounts
+                .into_iter()
+                .map(|a| (a.pubkey, a.is_writable, a.is_signer))
+                .collect()
+        };
+        let vault = accounts.creator_vault(&creator);
+        let mut expected = vec![
+            (accounts.global, false, false),
+            (fee_recipient, true, false),
+            (mint, false, false),
+            (accounts.bonding_curve, true, false),
+            (accounts.associated_bonding_curve, true, false),
+            (associated_token_address(&user, &mint), true, false),
+            (user, true, true),
+            (system_program::ID, false, false),
+            (pubkey(TOKEN_PROGRAM_ID), false, false),
+            (vault, true, false),
+            (accounts.event_authority, false, false),
+            (accounts.program, false, false),
+        ];
+        assert_eq!(metas(accounts.buy(&user, &market, 1, 2)), expected);
+        // the vault comes before the token program in a sell
+        expected.swap(8, 9);
+        assert_eq!(metas(accounts.sell(&user, &market, 1, 2)), expected);
+
+        let config = PumpTxConfig::default();
+        let buy = config.quote_buy(&curve, 1_000_000_000);
+        assert_eq!(buy.sol_amount, 1_010_000_000);
+        assert_eq!(buy.sol_limit, 1_060_500_000);
+        assert_eq!(buy.token_amount, curve.buy_quote(1_000_000_000));
+        let sell = config.quote_sell(&curve, buy.token_amount);
+        assert!(sell.sol_limit < sell.sol_amount && sell.sol_amount < buy.sol_amount);
+        // the last tokens of a curve cost less than the whole buy
+        let last = BondingCurve {
+            real_token_reserves: 1_000_000_000_000,
+            ..curve
+        };
+        let capped = config.quote_buy(&last, 1_000_000_000);
+        let spend = last.buy_cost(last.real_token_reserves);
+        assert_eq!(capped.token_amount, last.real_token_reserves);
+        assert_eq!(capped.sol_amount, spend + spend / 100);
+        assert!(capped.sol_limit < buy.sol_amount / 10);
+
+        let payer = Keypair::new();
+        let blockhash = Hash::new_unique();
+        let tx = config.build_tx(&payer, &mint, &market, &buy, blockhash);
+        tx.verify()?;
+        assert_eq!(tx.message.instructions.len(), 4);
+        let ix = &tx.message.instructions[3];
+        assert_eq!(
+            tx.message.account_keys[ix.program_id_index as usize],
+            accounts.program
+        );
+        assert_eq!(ix.data[..8], BUY_DISCRIMINATOR);
+        assert_eq!(ix.data[8..16], buy.token_amount.to_le_bytes());
+        assert_eq!(ix.data[16..], buy.sol_limit.to_le_bytes());
+        let keys: Vec<Pubkey> = ix
+            .accounts
+            .iter()
+            .map(|i| tx.message.account_keys[*i as usize])
+            .collect();
+        assert_eq!(keys[3], accounts.bonding_curve);
+        assert_eq!(keys[5], associated_token_address(&payer.pubkey(), &mint));
+        assert_eq!(keys[6], payer.pubkey());
+        assert_eq!(keys[9], vault);
+
+        let tx = config.build_tx(&payer, &mint, &market, &sell, blockhash);
+        assert_eq!(tx.message.instructions.len(), 3);
+        assert_eq!(tx.message.instructions[2].data[..8], SELL_DISCRIMINATOR);
+        // round trips through the wire format
+        let decoded: Transaction = bincode::deserialize(&base64.decode(serialize_tx(&tx)?)?)?;
+        assert_eq!(decoded, tx);
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::debug;
+
+use super::{
+    MonitorCondition, MonitorRule, PositionBook, RuleTarget, Strategy, StrategyContext,
+    StrategyEvent,
+};
+use crate::{alert::AlertKind, sol_client::SOL_DECIMALS};
+
+/// strategy name stored in `spl_token`
+pub const BUY_STRATEGY: &str = "Buy";
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuyConfig {
+    #[serde(default)]
+    pub min_sol_amount: f64, // ignore buys smaller than this, SOL
+}
+
+/// alert on every buy of the monitored wallet, or of any wallet in the monitored mint, inside the price range
+pub struct BuyStrategy {
+    conditions: MonitorCondition,
+    config: BuyConfig,
+    book: PositionBook, // for the wallet_pnl condition when adding to a position
+}
+
+impl BuyStrategy {
+    pub fn new(rule: &MonitorRule, config: BuyConfig) -> Result<Self> {
+        rule.check_target(BUY_STRATEGY, &[RuleTarget::Wallet, RuleTarget::Mint])?;
+        rule.conditions.check(BUY_STRATEGY, &[])?;
+        Ok(Self {
+            conditions: rule.conditions.clone(),
+            config,
+            book: PositionBook::default(),
+        })
+    }
+}
+
+#[async_trait]
+impl Strategy for BuyStrategy {
+    async fn on_event(&mut self, ctx: &StrategyContext, event: &StrategyEvent) -> Result<()> {
+        for trade in &event.trades {
+            // the logs mention the address, but the trade may belong to someone else
+            if !ctx.is_target(trade) {
+                continue;
+            }
+            let pnl = self
+                .book
+                .get(&trade.user, &trade.mint)
+                .and_then(|p| p.profit_percentage(trade.price()));
+            self.book.record(trade);
+            if !trade.is_buy || trade.sol_ui_amount() < self.config.min_sol_amount {
+                continue;
+            }
+            let price = trade.price();
+            let facts = ctx.trade_facts(trade, &self.conditions, pnl).await;
+            if !self.conditions.matches(&facts) {
+                debug!(
+                    "buy {:?} not match, mint: {}, address: {}",
+                    facts, trade.mint, ctx.address
+                );
+                continue;
+            }
+            ctx.alert_trade(
+                trade,
+                &event.signature,
+                AlertKind::Buy {
+                    sol_amount: trade.sol_amount,
+                    token_amount: trade.token_amount,
+                    price,
+                },
+            )
+            .await;
+        }
+        // the conditions are evaluated against pump.fun trades, only an unconditional rule alerts on swaps
+        if self.conditions == MonitorCondition::default() {
+            let min_sol = (self.config.min_sol_amount * 10f64.powi(SOL_DECIMALS as i32)) as u64;
+            ctx.alert_swaps(event, true, min_sol).await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alert::alert_channel, test_support::MockSolana};
+
+    #[tokio::test]
+    async fn test_buy_alert() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let address = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x";
+        mock.push_logs_fixture(address, "pump_buy_logs.json");
+
+        let rule = MonitorRule {
+            address: address.to_string(),
+            rule_type: BUY_STRATEGY.to_string(),
+            target: Default::default(),
+            conditions: MonitorCondition {
+                price_below: Some(0.0000001),
+                price_above: Some(0.00000001),
+                ..Default::default()
+            },
+            config: Default::default(),
+            cooldown: None,
+        };
+        let (sender, mut receiver) = alert_channel();
+        let ctx = mock.context(sender).await?;
+        tokio::spawn(async move { rule.run(&ctx).await });
+
+        let alert = receiver.recv().await.expect("no alert received");
+        assert_eq!(alert.rule, BUY_STRATEGY);
+        assert_eq!(alert.mint, "7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump");
+        assert_eq!(
+            alert.signature,
+            "24RBk6X2wAwu4nX8f11pW7VvbdjhDb82e2CcsA5XEf8Jmg1VfhU8MM3KLjQhkwUmKxz4sxbWLbxqvJuvVDdEGyam"
+        );
+        let AlertKind::Buy {
+            sol_amount,
+            token_amount,
+            ..
+        } = alert.kind
+        else {
+            panic!("not a buy alert");
+        };
+        assert_eq!(sol_amount, 1253951806);
+        assert_eq!(token_amount, 37809162736217);
+
+        let above = MonitorCondition {
+            price_above: Some(0.001),
+            ..Default::default()
+        };
+        assert!(!above.price_matches(3.4e-8));
+        assert!(MonitorCondition::default().price_matches(3.4e-8));
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_buy_when_token_age() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let address = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x";
+        let mint = "7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump";
+        // the buy is at 1734616564, the token was created 5 minutes before
+        mock.add_signatures(mint, &[("newer", 1734616500), ("create", 1734616264)]);
+        mock.push_logs_fixture(address, "pump_buy_logs.json");
+
+        let rule: MonitorRule = toml::from_str(&format!(
+            r#"
+            address = "{}"
+            rule_type = "Buy"
+            [conditions.when]
+            all = [
+                {{ field = "sol_amount", op = ">=", value = 1.0 }},
+                {{ field = "token_age", op = "<", value = 600 }},
+                {{ not = {{ field = "wallet_pnl", op = "<", value = 0 }} }},
+            ]
+            "#,
+            address
+        ))?;
+        let (sender, mut receiver) = alert_channel();
+        let ctx = mock.context(sender).await?;
+        tokio::spawn(async move { rule.run(&ctx).await });
+
+        let alert = receiver.recv().await.expect("no alert received");
+        assert_eq!(alert.mint, mint);
+        assert!(matches!(alert.kind, AlertKind::Buy { .. }));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_buy_jupiter_swap() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let address = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x";
+        let signature = mock.add_transaction_fixture("jupiter_swap_tx.json");
+        // no pump.fun event in the logs, the swap is decoded from the transaction
+        mock.push_logs(
+            address,
+            serde_json::json!({
+                "context": {"slot": 308456790},
+                "value": {
+                    "signature": signature,
+                    "err": null,
+                    "logs": [
+                        "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 invoke [1]",
+                        "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 success",
+                    ],
+                },
+            }),
+        );
+
+        let rule: MonitorRule = toml::from_str(&format!(
+            r#"
+            address = "{}"
+            rule_type = "Buy"
+            "#,
+            address
+        ))?;
+        let (sender, mut receiver) = alert_channel();
+        let ctx = mock.context(sender).await?;
+        tokio::spawn(async move { rule.run(&ctx).await });
+
+        let alert = receiver.recv().await.expect("no alert received");
+        assert_eq!(alert.mint, "DezXAZ8z7PnrnRJjz2wXBoRgixCCWcnWFpSGJN2B263");
+        assert_eq!(alert.kind.sol_amount(), Some(1_000_000_000));
+        assert!(matches!(alert.kind, AlertKind::Swap { .. }));
+        Ok(())
+    }
+}
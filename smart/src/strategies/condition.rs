@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+use crate::{
+    models::WalletScore,
+    sol_client::{ui_amount, TradeEventData, PUMP_TOKEN_DECIMALS, PUMP_TOKEN_TOTAL_SUPPLY},
+};
+
+/// deepest nesting accepted in a condition tree
+const MAX_CONDITION_DEPTH: usize = 16;
+
+/// a boolean expression over the facts of a trade, e.g. in toml:
+///
+/// ```toml
+/// [monitors.conditions.when]
+/// all = [
+///     { field = "sol_amount", op = ">=", value = 1.0 },
+///     { any = [
+///         { field = "market_cap", op = "<", value = 200.0 },
+///         { not = { field = "token_age", op = ">", value = 3600 } },
+///     ] },
+/// ]
+/// ```
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "RawCondition")]
+pub enum Condition {
+    All {
+        all: Vec<Condition>,
+    },
+    Any {
+        any: Vec<Condition>,
+    },
+    Not {
+        not: Box<Condition>,
+    },
+    Compare {
+        field: Field,
+        op: CompareOp,
+        value: f64,
+        // the scored window of a wallet score field
+        #[serde(default)]
+        window_secs: Option<u64>,
+    },
+}
+
+/// the keys of every condition node, so a misspelled one is an error naming it
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawCondition {
+    all: Option<Vec<Condition>>,
+    any: Option<Vec<Condition>>,
+    not: Option<Box<Condition>>,
+    field: Option<Field>,
+    op: Option<CompareOp>,
+    value: Option<f64>,
+    window_secs: Option<u64>,
+}
+
+impl TryFrom<RawCondition> for Condition {
+    type Error = String;
+
+    fn try_from(raw: RawCondition) -> Result<Self, Self::Error> {
+        let compare = raw.field.is_some()
+            || raw.op.is_some()
+            || raw.value.is_some()
+            || raw.window_secs.is_some();
+        match (raw.all, raw.any, raw.not, compare) {
+            (Some(all), None, None, false) => Ok(Condition::All { all }),
+            (None, Some(any), None, false) => Ok(Condition::Any { any }),
+            (None, None, Some(not), false) => Ok(Condition::Not { not }),
+            (None, None, None, true) => match (raw.field, raw.op, raw.value) {
+                (Some(field), Some(op), Some(value)) => Ok(Condition::Compare {
+                    field,
+                    op,
+                    value,
+                    window_secs: raw.window_secs,
+                }),
+                _ => Err("a comparison needs field, op and value".to_string()),
+            },
+            _ => Err("a condition is one of all, any, not or a comparison".to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    SolAmount,    // SOL of the trade
+    TokenAmount,  // tokens of the trade
+    Price,        // price in SOL after the trade
+    MarketCap,    // market cap in SOL after the trade
+    TokenAge,     // seconds between the token creation and the trade
+    WalletPnl,    // unrealized profit percentage of the wallet's position in the mint
+    SnipedSupply, // percentage of the supply bought within a few slots of the launch
+    // the persisted score over `window_secs` of the monitored wallet, or the trader for a mint rule
+    WinRate,       // percentage of sold tokens with a profit
+    MedianRoi,     // median percentage return per token
+    RealizedPnl,   // SOL
+    UnrealizedPnl, // SOL
+    AvgHoldSecs,   // seconds from the first buy to the last sell of a token
+    TradeCount,
+}
+
+impl Field {
+    pub fn is_score(&self) -> bool {
+        matches!(
+            self,
+            Field::WinRate
+                | Field::MedianRoi
+                | Field::RealizedPnl
+                | Field::UnrealizedPnl
+                | Field::AvgHoldSecs
+                | Field::TradeCount
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum CompareOp {
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Le,
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Ge,
+    #[serde(rename = "==")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+}
+
+/// the values a condition is evaluated against, None when unknown
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TradeFacts {
+    pub sol_amount: f64,
+    pub token_amount: f64,
+    pub price: f64,
+    pub market_cap: f64,
+    pub token_age: Option<f64>,
+    pub wallet_pnl: Option<f64>,
+    pub sniped_supply: Option<f64>, // None when the launch was not seen
+    pub scores: HashMap<u64, WalletScore>, // window_secs -> score of the wallet the rule scores
+}
+
+impl TradeFacts {
+    pub fn from_trade(trade: &TradeEventData) -> Self {
+        let price = trade.price();
+        Self {
+            sol_amount: trade.sol_ui_amount(),
+            token_amount: trade.token_ui_amount(),
+            price,
+            market_cap: price * ui_amount(PUMP_TOKEN_TOTAL_SUPPLY, PUMP_TOKEN_DECIMALS),
+            token_age: None,
+            wallet_pnl: None,
+            sniped_supply: None,
+            scores: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, field: Field, window_secs: Option<u64>) -> Option<f64> {
+        if field.is_score() {
+            let score = self.scores.get(&window_secs?)?;
+            return match field {
+                Field::WinRate => score.win_rate,
+                Field::MedianRoi => score.median_roi,
+                Field::RealizedPnl => Some(score.realized_pnl),
+                Field::UnrealizedPnl => Some(score.unrealized_pnl),
+                Field::AvgHoldSecs => score.avg_hold_secs,
+                _ => Some(score.trade_count as f64),
+            };
+        }
+        match field {
+            Field::SolAmount => Some(self.sol_amount),
+            Field::TokenAmount => Some(self.token_amount),
+            Field::Price => Some(self.price),
+            Field::MarketCap => Some(self.market_cap),
+            Field::TokenAge => self.token_age,
+            Field::WalletPnl => self.wallet_pnl,
+            Field::SnipedSupply => self.sniped_supply,
+            _ => None,
+        }
+    }
+}
+
+impl CompareOp {
+    fn apply(&self, left: f64, right: f64) -> bool {
+        match self {
+            CompareOp::Lt => left < right,
+            CompareOp::Le => left <= right,
+            CompareOp::Gt => left > right,
+            CompareOp::Ge => left >= right,
+            CompareOp::Eq => left == right,
+            CompareOp::Ne => left != right,
+        }
+    }
+}
+
+impl Condition {
+    /// a comparison against an unknown fact is false, so `not` of it is true
+    pub fn evaluate(&self, facts: &TradeFacts) -> bool {
+        match self {
+            Condition::All { all } => all.iter().all(|c| c.evaluate(facts)),
+            Condition::Any { any } => any.iter().any(|c| c.evaluate(facts)),
+            Condition::Not { not } => !not.evaluate(facts),
+            Condition::Compare {
+                field,
+                op,
+                value,
+                window_secs,
+            } => facts
+                .get(*field, *window_secs)
+                .is_some_and(|left| op.apply(left, *value)),
+        }
+    }
+
+    /// whether the tree compares `field`, to skip looking up facts no condition needs
+    pub fn uses(&self, field: Field) -> bool {
+        match self {
+            Condition::All { all } => all.iter().any(|c| c.uses(field)),
+            Condition::Any { any } => any.iter().any(|c| c.uses(field)),
+            Condition::Not { not } => not.uses(field),
+            Condition::Compare { field: f, .. } => *f == field,
+        }
+    }
+
+    /// the score windows the tree compares
+    pub fn score_windows(&self, windows: &mut Vec<u64>) {
+        match self {
+            Condition::All { all: children } | Condition::Any { any: children } => {
+                children.iter().for_each(|c| c.score_windows(windows))
+            }
+            Condition::Not { not } => not.score_windows(windows),
+            Condition::Compare {
+                field,
+                window_secs: Some(w),
+                ..
+            } if field.is_score() && !windows.contains(w) => windows.push(*w),
+            Condition::Compare { .. } => {}
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.validate_depth(1)
+    }
+
+    fn validate_depth(&self, depth: usize) -> Result<()> {
+        if depth > MAX_CONDITION_DEPTH {
+            return Err(anyhow!(
+                "Condition nested deeper than {}",
+                MAX_CONDITION_DEPTH
+            ));
+        }
+        match self {
+            Condition::All { all: children } | Condition::Any { any: children } => {
+                if children.is_empty() {
+                    return Err(anyhow!("Condition all/any has no children"));
+                }
+                children
+                    .iter()
+                    .try_for_each(|c| c.validate_depth(depth + 1))
+            }
+            Condition::Not { not } => not.validate_depth(depth + 1),
+            Condition::Compare {
+                field,
+                value,
+                window_secs,
+                ..
+            } => {
+                if !value.is_finite() {
+                    return Err(anyhow!("Condition on {:?} has no finite value", field));
+                }
+                if field.is_score() != window_secs.is_some() {
+                    return Err(anyhow!(
+                        "Condition on {:?} needs window_secs only for a score field",
+                        field
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Wrapper {
+        when: Condition,
+    }
+
+    fn parse(s: &str) -> Result<Condition> {
+        Ok(toml::from_str::<Wrapper>(s)?.when)
+    }
+
+    #[test]
+    fn test_condition_evaluate() -> Result<()> {
+        let c = parse(
+            r#"
+            [when]
+            all = [
+                { field = "sol_amount", op = ">=", value = 1.0 },
+                { any = [
+                    { field = "market_cap", op = "<", value = 200.0 },
+                    { not = { field = "token_age", op = ">", value = 3600 } },
+                ] },
+            ]
+            "#,
+        )?;
+        c.validate()?;
+        assert!(c.uses(Field::TokenAge));
+        assert!(!c.uses(Field::WalletPnl));
+
+        let mut facts = TradeFacts {
+            sol_amount: 2.0,
+            market_cap: 500.0,
+            ..Default::default()
+        };
+        // unknown token age, the comparison is false and its negation true
+        assert!(c.evaluate(&facts));
+        facts.token_age = Some(7200.0);
+        assert!(!c.evaluate(&facts));
+        facts.market_cap = 100.0;
+        assert!(c.evaluate(&facts));
+        facts.sol_amount = 0.5;
+        assert!(!c.evaluate(&facts));
+
+        let c = parse(
+            r#"
+            [when]
+            field = "win_rate"
+            window_secs = 2592000
+            op = ">"
+            value = 60.0
+            "#,
+        )?;
+        c.validate()?;
+        let mut windows = Vec::new();
+        c.score_windows(&mut windows);
+        assert_eq!(windows, vec![2592000]);
+        // no score yet
+        assert!(!c.evaluate(&facts));
+        facts.scores.insert(
+            2592000,
+            WalletScore {
+                win_rate: Some(75.0),
+                ..Default::default()
+            },
+        );
+        assert!(c.evaluate(&facts));
+        Ok(())
+    }
+
+    #[test]
+    fn test_condition_invalid() {
+        assert!(parse("[when]\nany = []").unwrap().validate().is_err());
+        assert!(parse("[when]\nfield = \"volume\"\nop = \">\"\nvalue = 1.0").is_err());
+        assert!(parse("[when]\nfield = \"price\"\nop = \"=>\"\nvalue = 1.0").is_err());
+        // misspelled keys name themselves, also deep in the tree
+        let e = parse("[when]\nfield = \"win_rate\"\nop = \">\"\nvalue = 60.0\nwindw_secs = 60")
+            .unwrap_err();
+        assert!(e.to_string().contains("`windw_secs`"), "{}", e);
+        let e = parse("[when]\nall = [{ nt = { field = \"price\", op = \">\", value = 1.0 } }]")
+            .unwrap_err();
+        assert!(e.to_string().contains("`nt`"), "{}", e);
+        assert!(parse("[when]\nall = []\nfield = \"price\"").is_err());
+        assert!(parse("[when]\nfield = \"price\"\nop = \">\"").is_err());
+        // a score field without its window, and a window on a trade field
+        assert!(
+            parse("[when]\nfield = \"win_rate\"\nop = \">\"\nvalue = 60.0")
+                .unwrap()
+                .validate()
+                .is_err()
+        );
+        assert!(
+            parse("[when]\nfield = \"price\"\nop = \">\"\nvalue = 1.0\nwindow_secs = 60")
+                .unwrap()
+                .validate()
+                .is_err()
+        );
+        let mut deep = Condition::Compare {
+            field: Field::Price,
+            op: CompareOp::Gt,
+            value: 0.0,
+            window_secs: None,
+        };
+        for _ in 0..MAX_CONDITION_DEPTH {
+            deep = Condition::Not {
+                not: Box::new(deep),
+            };
+        }
+        assert!(deep.validate().is_err());
+    }
+}
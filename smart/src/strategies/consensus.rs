@@ -0,0 +1,330 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_response::{Response, RpcLogsResponse};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info};
+use validator::{Validate, ValidationError};
+
+use super::{spawn_log_subscribe, MonitorContext, RecentSignatures, StrategyEvent};
+use crate::{
+    alert::{send_alert, Alert, AlertKind, ConsensusWallet},
+    models::CheckpointKey,
+    sol_client::{ui_amount, TradeEventData, PUMP_TOKEN_DECIMALS, SOL_DECIMALS},
+};
+
+/// rule name of the consensus alerts
+pub const CONSENSUS_STRATEGY: &str = "Consensus";
+
+/// alert when `min_wallets` of the group buy the same mint within `window_secs`
+#[derive(Clone, Debug, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+#[validate(schema(function = "validate_consensus_rule"))]
+pub struct ConsensusRule {
+    #[validate(length(min = 1))]
+    pub name: String, // 组名
+    #[validate(length(min = 2))]
+    pub wallets: Vec<String>, // 组内钱包
+    #[validate(range(min = 2))]
+    pub min_wallets: usize, // 触发所需的钱包数
+    #[validate(range(min = 1))]
+    pub window_secs: u64, // 时间窗口
+}
+
+fn validate_consensus_rule(rule: &ConsensusRule) -> Result<(), ValidationError> {
+    if rule.min_wallets > rule.wallets.len() {
+        return Err(ValidationError::new("min_wallets")
+            .with_message("min_wallets is larger than the group".into()));
+    }
+    let unique: HashSet<&String> = rule.wallets.iter().collect();
+    if unique.len() != rule.wallets.len() {
+        return Err(ValidationError::new("wallets").with_message("duplicate wallet".into()));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GroupBuy {
+    wallet: String,
+    sol_amount: u64,
+    token_amount: u64,
+    timestamp: i64,
+}
+
+/// the buys and alerted mints of a group kept over a restart
+#[derive(Serialize, Deserialize)]
+struct ConsensusCheckpoint {
+    buys: HashMap<String, VecDeque<GroupBuy>>,
+    fired: HashSet<String>,
+}
+
+/// the recent buys of a group per mint, on chain time so replayed trades behave the same
+pub struct ConsensusTracker {
+    min_wallets: usize,
+    window_secs: u64,
+    buys: HashMap<String, VecDeque<GroupBuy>>, // mint -> buys in the window, oldest first
+    fired: HashSet<String>,                    // mints alerted since they reached min_wallets
+}
+
+impl ConsensusTracker {
+    pub fn new(min_wallets: usize, window_secs: u64) -> Self {
+        Self {
+            min_wallets,
+            window_secs,
+            buys: HashMap::new(),
+            fired: HashSet::new(),
+        }
+    }
+
+    /// record a buy of a group wallet, returns the wallets when the mint reaches `min_wallets`.
+    ///
+    /// a mint alerts once, and again only after it dropped below `min_wallets`.
+    pub fn record(&mut self, trade: &TradeEventData) -> Option<Vec<ConsensusWallet>> {
+        let buys = self.buys.entry(trade.mint.clone()).or_default();
+        // the wallets' subscriptions race each other, keep the buys in chain order
+        let at = buys.partition_point(|b| b.timestamp <= trade.timestamp);
+        buys.insert(
+            at,
+            GroupBuy {
+                wallet: trade.user.clone(),
+                sol_amount: trade.sol_amount,
+                token_amount: trade.token_amount,
+                timestamp: trade.timestamp,
+            },
+        );
+        // a mint without another group buy is dropped too
+        self.expire_all(trade.timestamp);
+
+        let wallets = self.wallets(&trade.mint);
+        if wallets.len() < self.min_wallets || !self.fired.insert(trade.mint.clone()) {
+            return None;
+        }
+        Some(wallets)
+    }
+
+    /// drop the buys that left the window at `now`, for every mint
+    pub fn expire_all(&mut self, now: i64) {
+        let mints: Vec<String> = self.buys.keys().cloned().collect();
+        for mint in mints {
+            self.expire(&mint, now);
+        }
+    }
+
+    fn expire(&mut self, mint: &str, now: i64) {
+        let Some(buys) = self.buys.get_mut(mint) else {
+            return;
+        };
+        let start = now - self.window_secs as i64;
+        while buys.front().is_some_and(|b| b.timestamp < start) {
+            buys.pop_front();
+        }
+        if buys.is_empty() {
+            self.buys.remove(mint);
+        }
+        if self.wallets(mint).len() < self.min_wallets {
+            self.fired.remove(mint);
+        }
+    }
+
+    /// the buys in the window summed per wallet, in the order the wallets first bought
+    fn wallets(&self, mint: &str) -> Vec<ConsensusWallet> {
+        let mut wallets: Vec<ConsensusWallet> = Vec::new();
+        for buy in self.buys.get(mint).into_iter().flatten() {
+            match wallets.iter_mut().find(|w| w.wallet == buy.wallet) {
+                Some(w) => {
+                    w.sol_amount += buy.sol_amount;
+                    w.token_amount += buy.token_amount;
+                }
+                None => wallets.push(ConsensusWallet {
+                    wallet: buy.wallet.clone(),
+                    sol_amount: buy.sol_amount,
+                    token_amount: buy.token_amount,
+                    entry_price: 0.0,
+                }),
+            }
+        }
+        for w in &mut wallets {
+            if w.token_amount > 0 {
+                w.entry_price = ui_amount(w.sol_amount, SOL_DECIMALS)
+                    / ui_amount(w.token_amount, PUMP_TOKEN_DECIMALS);
+            }
+        }
+        wallets
+    }
+}
+
+impl ConsensusRule {
+    /// watch every wallet of the group until all the subscriptions end or the daemon shuts down
+    pub async fn run(&self, ctx: &MonitorContext) -> Result<()> {
+        let (sender, mut receiver) = mpsc::channel::<Response<RpcLogsResponse>>(1000);
+        let subscriptions: Vec<_> = self
+            .wallets
+            .iter()
+            .map(|wallet| spawn_log_subscribe(wallet, ctx, sender.clone()))
+            .collect();
+        // the subscriptions hold the only senders, recv ends with the last of them
+        drop(sender);
+        info!(
+            "consensus group {} watching {} wallets",
+            self.name,
+            subscriptions.len()
+        );
+
+        let group: HashSet<&String> = self.wallets.iter().collect();
+        let mut tracker = ConsensusTracker::new(self.min_wallets, self.window_secs);
+        let key = CheckpointKey {
+            address: &self.name,
+            rule_type: CONSENSUS_STRATEGY,
+            target: "group",
+            name: "tracker",
+        };
+        if let Some(c) = ctx
+            .manager
+            .take_checkpoint::<ConsensusCheckpoint>(&key)
+            .await?
+        {
+            tracker.buys = c.buys;
+            tracker.fired = c.fired;
+            tracker.expire_all(chrono::Utc::now().timestamp());
+        }
+        let mut recent = RecentSignatures::new(1000);
+        while let Some(log) = ctx.next_log(&mut receiver).await {
+            if !recent.insert(&log.value.signature) {
+                continue;
+            }
+            let event = StrategyEvent::from(log);
+            for trade in &event.trades {
+                if !trade.is_buy || !group.contains(&trade.user) {
+                    continue;
+                }
+                let Some(wallets) = tracker.record(trade) else {
+                    continue;
+                };
+                debug!("consensus of group {} on {}", self.name, trade.mint);
+                send_alert(
+                    &ctx.alerts,
+                    Alert {
+                        address: self.name.clone(),
+                        rule: CONSENSUS_STRATEGY.to_string(),
+                        mint: trade.mint.clone(),
+                        signature: event.signature.clone(),
+                        kind: AlertKind::Consensus {
+                            wallets,
+                            window_secs: self.window_secs,
+                        },
+                        suppressed: 0,
+                    },
+                )
+                .await;
+            }
+        }
+        for s in subscriptions {
+            s.abort();
+        }
+        let checkpoint = ConsensusCheckpoint {
+            buys: tracker.buys,
+            fired: tracker.fired,
+        };
+        if let Err(e) = ctx.manager.save_checkpoint(&key, &checkpoint).await {
+            error!("save consensus error: {:?}, group: {}", e, self.name);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alert::alert_channel, test_support::MockSolana};
+
+    fn buy(user: &str, mint: &str, sol_amount: u64, timestamp: i64) -> TradeEventData {
+        TradeEventData {
+            mint: mint.to_string(),
+            sol_amount,
+            token_amount: sol_amount * 30,
+            is_buy: true,
+            user: user.to_string(),
+            timestamp,
+            virtual_sol_reserves: 30_000_000_000,
+            virtual_token_reserves: 1_000_000_000_000_000,
+            real_sol_reserves: 0,
+            real_token_reserves: 0,
+        }
+    }
+
+    #[test]
+    fn test_consensus_tracker() {
+        let mut tracker = ConsensusTracker::new(2, 300);
+        assert!(tracker.record(&buy("a", "m", 1_000_000_000, 0)).is_none());
+        // the same wallet again is still one wallet
+        assert!(tracker.record(&buy("a", "m", 1_000_000_000, 10)).is_none());
+        // another mint
+        assert!(tracker.record(&buy("b", "n", 1_000_000_000, 20)).is_none());
+
+        let wallets = tracker.record(&buy("b", "m", 500_000_000, 30)).unwrap();
+        assert_eq!(wallets.len(), 2);
+        assert_eq!(wallets[0].wallet, "a");
+        assert_eq!(wallets[0].sol_amount, 2_000_000_000);
+        assert_eq!(wallets[1].sol_amount, 500_000_000);
+        // 1 SOL for 30000 tokens
+        assert!((wallets[0].entry_price - 1.0 / 30000.0).abs() < 1e-12);
+
+        // alerted once while the consensus holds
+        assert!(tracker.record(&buy("c", "m", 1_000_000_000, 40)).is_none());
+        // a and b left the window, c alone is below min_wallets
+        tracker.expire_all(335);
+        assert!(tracker.record(&buy("a", "m", 1_000_000_000, 336)).is_some());
+
+        // the next buy of any mint drops the alerted mints out of the window
+        assert!(tracker.record(&buy("b", "m", 1_000_000_000, 340)).is_none());
+        assert!(tracker.record(&buy("a", "n", 1_000_000_000, 700)).is_none());
+        assert!(!tracker.buys.contains_key("m"));
+        assert!(!tracker.fired.contains("m"));
+        assert_eq!(tracker.buys.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_consensus_alert() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let rule: ConsensusRule = toml::from_str(
+            r#"
+            name = "alpha's" # the checkpoint key is bound
+            wallets = ["ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x", "A2k8ViMJmRUTeFcv6ER15Kbzg5kQF8W6QtHMM9JNe6om"]
+            min_wallets = 2
+            window_secs = 300
+            "#,
+        )?;
+        rule.validate()?;
+        mock.push_logs_fixture(&rule.wallets[0], "pump_buy_logs.json");
+        mock.push_logs_fixture(&rule.wallets[1], "pump_other_buy_logs.json");
+
+        let (sender, mut receiver) = alert_channel();
+        let ctx = mock.context(sender).await?;
+        tokio::spawn(async move { rule.run(&ctx).await });
+
+        let alert = receiver.recv().await.expect("no alert received");
+        assert_eq!(alert.rule, CONSENSUS_STRATEGY);
+        assert_eq!(alert.address, "alpha's");
+        assert_eq!(alert.mint, "7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump");
+        let AlertKind::Consensus { wallets, .. } = alert.kind else {
+            panic!("not a consensus alert");
+        };
+        assert_eq!(wallets.len(), 2);
+        assert_eq!(wallets[1].sol_amount, 20_000_000_000);
+
+        let mut invalid: ConsensusRule = toml::from_str(
+            r#"
+            name = "beta"
+            wallets = ["a", "b"]
+            min_wallets = 3
+            window_secs = 300
+            "#,
+        )?;
+        assert!(invalid.validate().is_err());
+        invalid.min_wallets = 2;
+        invalid.wallets[1] = "a".to_string();
+        assert!(invalid.validate().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use super::{
+    follow::{default_follow_sells, exit_reason, follow_sell_amount, validate_follow},
+    MonitorCondition, MonitorRule, PositionBook, RuleTarget, Strategy, StrategyContext,
+    StrategyEvent, TrailingStopConfig,
+};
+use crate::{
+    execution::Executor,
+    models::{ExecutionPosition, EXECUTION_POSITION_OPEN},
+    sol_client::{ui_amount, TradeEventData, PUMP_TOKEN_DECIMALS, SOL_DECIMALS},
+};
+
+/// strategy name of the copy trading
+pub const COPY_TRADE_STRATEGY: &str = "CopyTrade";
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CopyTradeConfig {
+    pub sol_amount: f64, // 每次跟单的 SOL into the curve, capped by the execution limits
+    #[serde(default = "default_follow_sells")]
+    pub follow_sells: bool, // sell the part of the position the wallet sold of its own
+    pub exit: Option<TrailingStopConfig>, // sell the whole position on these rules
+}
+
+fn entry_price(position: &ExecutionPosition) -> f64 {
+    if position.token_amount <= 0 {
+        return 0.0;
+    }
+    ui_amount(position.cost as u64, SOL_DECIMALS)
+        / ui_amount(position.token_amount as u64, PUMP_TOKEN_DECIMALS)
+}
+
+/// copy the buys and sells of the monitored wallet with real orders through the daemon's executor.
+/// without an executor, execution disabled or a backtest, the rule does nothing.
+pub struct CopyTradeStrategy {
+    conditions: MonitorCondition,
+    config: CopyTradeConfig,
+    book: PositionBook, // the wallet's own positions, for the part of a sell
+    high_prices: HashMap<String, f64>, // mint -> highest price since the buy, for the exit rules
+}
+
+impl CopyTradeStrategy {
+    pub fn new(rule: &MonitorRule, config: CopyTradeConfig) -> Result<Self> {
+        rule.check_target(COPY_TRADE_STRATEGY, &[RuleTarget::Wallet])?;
+        rule.conditions.check(COPY_TRADE_STRATEGY, &[])?;
+        validate_follow(config.sol_amount, config.exit.as_ref())?;
+        Ok(Self {
+            conditions: rule.conditions.clone(),
+            config,
+            book: PositionBook::default(),
+            high_prices: HashMap::new(),
+        })
+    }
+
+    async fn buy(
+        &mut self,
+        ctx: &StrategyContext,
+        executor: &Arc<Executor>,
+        trade: &TradeEventData,
+    ) -> Result<()> {
+        let facts = ctx.trade_facts(trade, &self.conditions, None).await;
+        if !self.conditions.matches(&facts) {
+            return Ok(());
+        }
+        let order = executor
+            .buy(&ctx.address, &trade.mint, self.config.sol_amount, ctx.now())
+            .await?;
+        if order.is_some() {
+            ctx.watch(&trade.mint);
+            self.high_prices.insert(trade.mint.clone(), trade.price());
+        }
+        Ok(())
+    }
+
+    /// the position copying the monitored wallet in `mint`, the copy of another rule's wallet is left alone
+    async fn own_position(
+        ctx: &StrategyContext,
+        executor: &Executor,
+        mint: &str,
+    ) -> Result<Option<ExecutionPosition>> {
+        Ok(executor
+            .position(mint)
+            .await?
+            .filter(|p| p.wallet == ctx.address))
+    }
+
+    /// the wallet sold, sell the same part of the position
+    async fn follow_sell(
+        &mut self,
+        ctx: &StrategyContext,
+        executor: &Arc<Executor>,
+        trade: &TradeEventData,
+        holding: Option<u64>,
+    ) -> Result<()> {
+        let Some(position) = Self::own_position(ctx, executor, &trade.mint).await? else {
+            return Ok(());
+        };
+        let tokens = follow_sell_amount(position.token_amount as u64, trade.token_amount, holding);
+        executor
+            .sell(&trade.mint, tokens, "copy", ctx.now())
+            .await?;
+        self.forget_closed(ctx, executor, &trade.mint).await
+    }
+
+    async fn check_exit(
+        &mut self,
+        ctx: &StrategyContext,
+        executor: &Arc<Executor>,
+        trade: &TradeEventData,
+    ) -> Result<()> {
+        let (Some(exit), Some(high_price)) =
+            (&self.config.exit, self.high_prices.get_mut(&trade.mint))
+        else {
+            return Ok(());
+        };
+        let Some(position) = Self::own_position(ctx, executor, &trade.mint)
+            .await?
+            .filter(|p| p.status == EXECUTION_POSITION_OPEN)
+        else {
+            return Ok(());
+        };
+        if let Some(reason) = exit_reason(exit, entry_price(&position), high_price, trade.price()) {
+            let tokens = position.token_amount as u64;
+            executor
+                .sell(&trade.mint, tokens, &format!("{:?}", reason), ctx.now())
+                .await?;
+            self.forget_closed(ctx, executor, &trade.mint).await?;
+        }
+        Ok(())
+    }
+
+    /// stop following the price once the position is gone
+    async fn forget_closed(
+        &mut self,
+        ctx: &StrategyContext,
+        executor: &Executor,
+        mint: &str,
+    ) -> Result<()> {
+        if Self::own_position(ctx, executor, mint).await?.is_none() {
+            self.high_prices.remove(mint);
+            ctx.unwatch(mint);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Strategy for CopyTradeStrategy {
+    async fn init(&mut self, ctx: &StrategyContext) -> Result<()> {
+        let Some(executor) = ctx.executor() else {
+            warn!(
+                "execution is disabled, {} sends no orders, address: {}",
+                COPY_TRADE_STRATEGY, ctx.address
+            );
+            return Ok(());
+        };
+        for p in executor.positions().await? {
+            if p.wallet != ctx.address {
+                continue;
+            }
+            info!(
+                "execution position of {} from an earlier run, address: {}",
+                p.mint, ctx.address
+            );
+            ctx.watch(&p.mint);
+            self.high_prices.insert(p.mint.clone(), entry_price(&p));
+        }
+        Ok(())
+    }
+
+    async fn on_event(&mut self, ctx: &StrategyContext, event: &StrategyEvent) -> Result<()> {
+        let Some(executor) = ctx.executor().cloned() else {
+            return Ok(());
+        };
+        for trade in &event.trades {
+            if trade.user == ctx.address {
+                let holding = self.book.get(&trade.user, &trade.mint).map(|p| p.holding());
+                self.book.record(trade);
+                if trade.is_buy {
+                    self.buy(ctx, &executor, trade).await?;
+                } else if self.config.follow_sells {
+                    self.follow_sell(ctx, &executor, trade, holding).await?;
+                }
+            }
+            self.check_exit(ctx, &executor, trade).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alert::alert_channel, execution::ExecutionConfig, models::EXECUTION_ORDER_DRY_RUN,
+        sol_client::client::SolanaMonitor, strategies::MonitorContext, test_support::MockSolana,
+    };
+
+    #[tokio::test]
+    async fn test_copy_trade_dry_run() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let (sender, _receiver) = alert_channel();
+        let ctx = mock.context(sender).await?;
+        let wallet = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x";
+        let mint = "7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump";
+        let start = 1_734_616_564;
+        mock.add_bonding_curve(mint, 30_000_000_000);
+
+        let executor = Executor::new(
+            &ExecutionConfig {
+                enabled: true,
+                ..Default::default()
+            },
+            ctx.manager.clone(),
+            SolanaMonitor::new(&mock.wss_url, &mock.rpc_url),
+        )?;
+        let ctx = MonitorContext {
+            executor: Some(Arc::new(executor)),
+            ..ctx
+        };
+        let rule: MonitorRule = toml::from_str(&format!(
+            r#"
+            address = "{}"
+            rule_type = "CopyTrade"
+            [config]
+            sol_amount = 0.05
+            exit = {{ take_profit = 100.0 }}
+            "#,
+            wallet
+        ))?;
+        let mut strategy = CopyTradeStrategy::new(&rule, rule.strategy_config()?)?;
+        let sctx = StrategyContext::replay(&rule, &ctx, start);
+        strategy.init(&sctx).await?;
+
+        let trade = |user: &str, is_buy: bool, tokens: u64, sol: u64| TradeEventData {
+            mint: mint.to_string(),
+            sol_amount: 1_000_000_000,
+            token_amount: tokens,
+            is_buy,
+            user: user.to_string(),
+            timestamp: start,
+            virtual_sol_reserves: sol * 1_000_000_000,
+            virtual_token_reserves: 1_000_000_000_000_000,
+            real_sol_reserves: 0,
+            real_token_reserves: 700_000_000_000_000,
+        };
+        let event = |i: usize, t: TradeEventData| StrategyEvent {
+            signature: format!("sig{}", i),
+            trades: vec![t],
+            ..Default::default()
+        };
+        strategy
+            .on_event(
+                &sctx,
+                &event(0, trade(wallet, true, 40_000_000_000_000, 30)),
+            )
+            .await?;
+
+        // a rule copying another wallet in the mint buys nothing and sells none of the position
+        let other = "A2k8ViMJmRUTeFcv6ER15Kbzg5kQF8W6QtHMM9JNe6om";
+        let other_rule = MonitorRule {
+            address: other.to_string(),
+            ..rule.clone()
+        };
+        let mut other_strategy = CopyTradeStrategy::new(&other_rule, rule.strategy_config()?)?;
+        let other_sctx = StrategyContext::replay(&other_rule, &ctx, start);
+        other_strategy.init(&other_sctx).await?;
+        for (i, t) in [
+            trade(other, true, 10_000_000_000_000, 30),
+            trade(other, false, 10_000_000_000_000, 30),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            other_strategy
+                .on_event(&other_sctx, &event(i + 1, t))
+                .await?;
+        }
+        assert_eq!(ctx.manager.get_execution_orders(start).await?.len(), 1);
+        assert!(!other_sctx.is_watching(mint));
+
+        for (i, t) in [
+            // the wallet sells half, so does the position
+            trade(wallet, false, 20_000_000_000_000, 31),
+            // doubled from the entry
+            trade("other", true, 1, 62),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            strategy.on_event(&sctx, &event(i + 3, t)).await?;
+        }
+
+        let orders = ctx.manager.get_execution_orders(start).await?;
+        let reasons: Vec<&str> = orders.iter().map(|o| o.reason.as_str()).collect();
+        assert_eq!(reasons, ["copy", "copy", "TakeProfit"]);
+        assert!(orders.iter().all(|o| o.status == EXECUTION_ORDER_DRY_RUN));
+        // 0.05 SOL into the curve, the fee on top
+        assert_eq!(orders[0].sol_amount, 50_500_000);
+        assert_eq!(orders[1].token_amount, orders[0].token_amount / 2);
+        assert_eq!(
+            orders[1].token_amount + orders[2].token_amount,
+            orders[0].token_amount
+        );
+        assert!(ctx
+            .executor
+            .as_ref()
+            .expect("no executor")
+            .positions()
+            .await?
+            .is_empty());
+        assert!(!sctx.is_watching(mint));
+        Ok(())
+    }
+}
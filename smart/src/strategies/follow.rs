@@ -0,0 +1,48 @@
+//! the sizing and exit rules shared by the copy trading strategies, on paper or with real orders
+
+use anyhow::{anyhow, Result};
+
+use super::TrailingStopConfig;
+use crate::{alert::ExitReason, models::PositionWatermark};
+
+pub(crate) fn default_follow_sells() -> bool {
+    true
+}
+
+/// a positive size per buy and valid exit rules
+pub(crate) fn validate_follow(sol_amount: f64, exit: Option<&TrailingStopConfig>) -> Result<()> {
+    if !sol_amount.is_finite() || sol_amount <= 0.0 {
+        return Err(anyhow!("sol_amount is positive"));
+    }
+    if let Some(exit) = exit {
+        exit.validate()?;
+    }
+    Ok(())
+}
+
+/// the tokens of a position to sell when the wallet sold `sold` of its `holding`.
+/// a holding the monitor didn't see bought is sold all at once
+pub(crate) fn follow_sell_amount(position: u64, sold: u64, holding: Option<u64>) -> u64 {
+    match holding {
+        Some(h) if sold < h => (position as u128 * sold as u128 / h as u128) as u64,
+        _ => position,
+    }
+}
+
+/// the exit rule `price` triggers first, `high_price` follows the price up.
+/// every exit sells the whole position, the fired flags are not kept
+pub(crate) fn exit_reason(
+    exit: &TrailingStopConfig,
+    entry_price: f64,
+    high_price: &mut f64,
+    price: f64,
+) -> Option<ExitReason> {
+    let mut w = PositionWatermark {
+        entry_price,
+        high_price: *high_price,
+        ..Default::default()
+    };
+    let reasons = exit.evaluate(&mut w, price);
+    *high_price = w.high_price;
+    reasons.first().copied()
+}
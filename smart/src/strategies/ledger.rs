@@ -0,0 +1,204 @@
+use std::collections::VecDeque;
+
+use serde::Deserialize;
+
+use crate::{
+    models::PositionLot,
+    sol_client::{
+        curve::PUMP_FEE_BPS, ui_amount, TradeEventData, PUMP_TOKEN_DECIMALS, SOL_DECIMALS,
+    },
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountingMode {
+    Fifo, // a sell closes the oldest lots first
+    #[default]
+    AverageCost, // a sell takes its share of the average cost
+}
+
+impl PositionLot {
+    /// the lot of a trade, `network_fee` is the transaction fee attributed to it
+    pub fn from_trade(signature: &str, trade: &TradeEventData, network_fee: u64) -> Self {
+        Self {
+            wallet: trade.user.clone(),
+            mint: trade.mint.clone(),
+            signature: signature.to_string(),
+            is_buy: trade.is_buy,
+            sol_amount: trade.sol_amount as i64,
+            token_amount: trade.token_amount as i64,
+            pump_fee: (trade.sol_amount * PUMP_FEE_BPS / 10_000) as i64,
+            network_fee: network_fee as i64,
+            timestamp: trade.timestamp,
+        }
+    }
+
+    /// lamports paid for a buy, or received for a sell, after the fees
+    fn value(&self) -> i128 {
+        let fees = self.pump_fee as i128 + self.network_fee as i128;
+        if self.is_buy {
+            self.sol_amount as i128 + fees
+        } else {
+            self.sol_amount as i128 - fees
+        }
+    }
+}
+
+/// a position replayed from its lots, lamports and raw token units
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LedgerPnl {
+    pub holding: u64,
+    pub cost_basis: u64,   // cost of the holding, fees included
+    pub realized_pnl: i64, // proceeds of the sells minus the cost of the tokens they closed
+    pub fees: u64,         // pump.fun and network fees paid
+}
+
+impl LedgerPnl {
+    /// replay `lots`, oldest first. the part of a sell beyond the known holding is ignored.
+    pub fn compute(lots: &[PositionLot], mode: AccountingMode) -> Self {
+        let mut pnl = LedgerPnl::default();
+        let mut open: VecDeque<(u128, u128)> = VecDeque::new(); // (tokens, cost) of the fifo lots
+        let mut realized: i128 = 0;
+        let (mut holding, mut cost): (u128, u128) = (0, 0);
+        for lot in lots {
+            pnl.fees += (lot.pump_fee + lot.network_fee) as u64;
+            let tokens = lot.token_amount as u128;
+            if lot.is_buy {
+                let value = lot.value() as u128;
+                holding += tokens;
+                cost += value;
+                open.push_back((tokens, value));
+                continue;
+            }
+            if tokens == 0 {
+                continue;
+            }
+            let sold = tokens.min(holding);
+            let removed = match mode {
+                AccountingMode::AverageCost if holding > 0 => cost * sold / holding,
+                AccountingMode::AverageCost => 0,
+                AccountingMode::Fifo => {
+                    let (mut left, mut removed) = (sold, 0);
+                    while left > 0 {
+                        let Some(front) = open.front_mut() else {
+                            break;
+                        };
+                        let take = left.min(front.0);
+                        let part = front.1 * take / front.0;
+                        removed += part;
+                        front.0 -= take;
+                        front.1 -= part;
+                        left -= take;
+                        if front.0 == 0 {
+                            open.pop_front();
+                        }
+                    }
+                    removed
+                }
+            };
+            holding -= sold;
+            cost -= removed;
+            if holding == 0 {
+                cost = 0;
+                open.clear();
+            }
+            realized += lot.value() * sold as i128 / tokens as i128 - removed as i128;
+        }
+        pnl.holding = holding as u64;
+        pnl.cost_basis = cost as u64;
+        pnl.realized_pnl = realized as i64;
+        pnl
+    }
+
+    /// average entry price of the holding in SOL, fees included
+    pub fn entry_price(&self) -> Option<f64> {
+        if self.cost_basis == 0 || self.holding == 0 {
+            return None;
+        }
+        Some(
+            ui_amount(self.cost_basis, SOL_DECIMALS) / ui_amount(self.holding, PUMP_TOKEN_DECIMALS),
+        )
+    }
+
+    /// unrealized profit of the holding at `price`, lamports
+    pub fn unrealized_pnl(&self, price: f64) -> i64 {
+        let value =
+            price * ui_amount(self.holding, PUMP_TOKEN_DECIMALS) * 10f64.powi(SOL_DECIMALS as i32);
+        value.round() as i64 - self.cost_basis as i64
+    }
+
+    pub fn profit_percentage(&self, price: f64) -> Option<f64> {
+        let entry_price = self.entry_price()?;
+        Some((price - entry_price) / entry_price * 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::memory_manager;
+    use anyhow::Result;
+
+    fn lot(is_buy: bool, sol_amount: i64, token_amount: i64, timestamp: i64) -> PositionLot {
+        PositionLot {
+            wallet: "wallet".to_string(),
+            mint: "mint".to_string(),
+            signature: format!("sig{}", timestamp),
+            is_buy,
+            sol_amount,
+            token_amount,
+            pump_fee: sol_amount / 100,
+            network_fee: 5000,
+            timestamp,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ledger_pnl() -> Result<()> {
+        let manager = memory_manager().await?;
+        let sol = 1_000_000_000;
+        let tokens = 1_000_000_000;
+        for l in [
+            lot(true, sol, tokens, 1),
+            lot(true, 3 * sol, tokens, 2),
+            // sells the first lot for 2 SOL
+            lot(false, 2 * sol, tokens, 3),
+        ] {
+            manager.add_position_lot(&l).await?;
+            // stored once
+            manager.add_position_lot(&l).await?;
+        }
+        let lots = manager.get_position_lots("wallet", "mint").await?;
+        assert_eq!(lots.len(), 3);
+
+        let buy_fees = |s: i64| s + s / 100 + 5000;
+        let proceeds = 2 * sol - 2 * sol / 100 - 5000;
+
+        let fifo = LedgerPnl::compute(&lots, AccountingMode::Fifo);
+        assert_eq!(fifo.holding, tokens as u64);
+        assert_eq!(fifo.cost_basis, buy_fees(3 * sol) as u64);
+        assert_eq!(fifo.realized_pnl, proceeds - buy_fees(sol));
+        assert_eq!(
+            fifo.fees,
+            (sol / 100 + 3 * sol / 100 + 2 * sol / 100 + 15000) as u64
+        );
+
+        let average = LedgerPnl::compute(&lots, AccountingMode::AverageCost);
+        let half = (buy_fees(sol) + buy_fees(3 * sol)) / 2;
+        assert_eq!(average.cost_basis, half as u64);
+        assert_eq!(average.realized_pnl, proceeds - half);
+
+        // 1000 tokens at 0.004 SOL
+        assert_eq!(average.unrealized_pnl(0.004), 4 * sol - half);
+        assert!(average.profit_percentage(0.004).unwrap() > 95.0);
+
+        // a sell beyond the holding only closes what is known
+        let mut over = lots.clone();
+        over.push(lot(false, 4 * sol, 2 * tokens, 4));
+        let closed = LedgerPnl::compute(&over, AccountingMode::Fifo);
+        assert_eq!(closed.holding, 0);
+        assert_eq!(closed.cost_basis, 0);
+        assert_eq!(closed.entry_price(), None);
+        Ok(())
+    }
+}
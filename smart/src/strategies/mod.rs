@@ -1,28 +1,108 @@
-use anyhow::Result;
+use std::{future::Future, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result};
 use serde::Deserialize;
-use solana_client::rpc_response::RpcLogsResponse;
-use tokio::sync::mpsc;
-use tracing::{debug, error};
+use solana_client::rpc_response::{Response, RpcLogsResponse};
+use tokio::{
+    sync::{mpsc, watch},
+    task::JoinHandle,
+};
+use tracing::{debug, error, warn};
+use validator::{Validate, ValidationError};
 
-use crate::{config::get_global_config, sol_client::client::SolanaMonitor};
+use crate::{
+    alert::AlertSender,
+    config::get_global_config,
+    cooldown::AlertCooldown,
+    execution::Executor,
+    models::{get_global_manager, ModelsManager},
+    sol_client::client::SolanaMonitor,
+};
 
+mod buy;
+mod condition;
+mod consensus;
+mod copy_trade;
+mod follow;
+mod ledger;
+mod momentum;
+mod paper_trade;
+mod position;
 mod profit_holding;
+mod registry;
+mod sell;
+mod strategy;
+mod threshold;
+mod trailing_stop;
+
+pub use buy::{BuyConfig, BuyStrategy, BUY_STRATEGY};
+pub use condition::{CompareOp, Condition, Field, TradeFacts};
+pub use consensus::{ConsensusRule, ConsensusTracker, CONSENSUS_STRATEGY};
+pub use copy_trade::{CopyTradeConfig, CopyTradeStrategy, COPY_TRADE_STRATEGY};
+pub use ledger::{AccountingMode, LedgerPnl};
+pub use momentum::{
+    MomentumConfig, MomentumSpike, MomentumStrategy, MomentumTracker, MOMENTUM_STRATEGY,
+};
+pub use paper_trade::{PaperFillQuote, PaperTradeConfig, PaperTradeStrategy, PAPER_TRADE_STRATEGY};
+pub use position::{Position, PositionBook};
+pub use profit_holding::{ProfitHoldingConfig, ProfitHoldingStrategy, PROFIT_HOLDING_STRATEGY};
+pub use registry::{StrategyFactory, StrategyRegistry};
+pub use sell::{SellConfig, SellInfo, SellStrategy, SELL_STRATEGY};
+pub use strategy::{RecentSignatures, Strategy, StrategyContext, StrategyEvent};
+pub use threshold::{ThresholdConfig, ThresholdStrategy, THRESHOLD_STRATEGY};
+pub use trailing_stop::{TrailingStopConfig, TrailingStopStrategy, TRAILING_STOP_STRATEGY};
 
-#[derive(Clone, Debug, Deserialize)]
+/// how often `Strategy::on_tick` is called
+pub const TICK_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Validate)]
+#[validate(schema(function = "validate_monitor_rule"))]
 pub struct MonitorRule {
-    pub address: String,              // 监控地址
-    pub rule_type: MonitorRuleType,   // 监控规则类型
+    pub address: String,   // 监控地址
+    pub rule_type: String, // 监控规则类型, the strategy name in the registry
+    #[serde(default)]
+    pub target: RuleTarget, // 监控目标, what the address is
+    #[serde(default)]
     pub conditions: MonitorCondition, // 触发条件
+    #[serde(default)]
+    pub config: toml::Table, // typed config of the strategy
+    #[validate(nested)]
+    pub cooldown: Option<AlertCooldown>, // 告警冷却
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleTarget {
+    #[default]
+    Wallet, // the trades of the wallet
+    Mint, // the trades of every wallet in the mint
 }
 
-#[derive(Clone, Debug, Deserialize)]
-pub enum MonitorRuleType {
-    Buy,           // 买入监控
-    Sell,          // 卖出监控
-    ProfitHolding, // 持仓收益监控
+impl RuleTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleTarget::Wallet => "wallet",
+            RuleTarget::Mint => "mint",
+        }
+    }
 }
 
-#[derive(Clone, Debug, Deserialize)]
+impl MonitorRule {
+    /// reject a target `strategy` does not support
+    pub fn check_target(&self, strategy: &str, supported: &[RuleTarget]) -> Result<()> {
+        if !supported.contains(&self.target) {
+            return Err(anyhow!(
+                "Target {:?} is not supported by strategy {}",
+                self.target,
+                strategy
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct MonitorCondition {
     pub price_below: Option<f64>,        // 价格低于
     pub price_above: Option<f64>,        // 价格高于
@@ -30,60 +110,207 @@ pub struct MonitorCondition {
     pub is_first_sell: Option<bool>,     // 是否首次卖出
     pub partial_sell: Option<bool>,      // 是否部分卖出
     pub holding_percentage: Option<f64>, // 持仓百分比
+    pub when: Option<Condition>,         // 组合条件, evaluated against the trade
 }
 
-impl MonitorRule {
-    pub async fn should_alert(&self) -> Result<()> {
-        let c = get_global_config().await;
-        match self.rule_type {
-            MonitorRuleType::Buy => {
-                todo!()
-                // if let Some(price_below) = self.conditions.price_below {
-                //     if rpcLogs.price < price_below {
-                //         return true;
-                //     }
-                // }
+impl MonitorCondition {
+    /// whether the price is inside `price_above` and `price_below`, an unset bound always matches
+    pub fn price_matches(&self, price: f64) -> bool {
+        self.price_below.is_none_or(|below| price < below)
+            && self.price_above.is_none_or(|above| price > above)
+    }
+
+    /// the price bounds and the `when` condition both hold for the trade
+    pub fn matches(&self, facts: &TradeFacts) -> bool {
+        self.price_matches(facts.price) && self.when.as_ref().is_none_or(|c| c.evaluate(facts))
+    }
+
+    pub fn uses(&self, field: Field) -> bool {
+        self.when.as_ref().is_some_and(|c| c.uses(field))
+    }
+
+    /// the windows of the wallet scores `when` compares
+    pub fn score_windows(&self) -> Vec<u64> {
+        let mut windows = Vec::new();
+        if let Some(when) = &self.when {
+            when.score_windows(&mut windows);
+        }
+        windows
+    }
+
+    /// reject a `when` tree that can't be evaluated and the fields `strategy` does not support,
+    /// the price bounds apply to every strategy
+    pub fn check(&self, strategy: &str, supported: &[&str]) -> Result<()> {
+        if let Some(when) = &self.when {
+            when.validate()?;
+        }
+        let fields = [
+            ("profit_percentage", self.profit_percentage.is_some()),
+            ("is_first_sell", self.is_first_sell.is_some()),
+            ("partial_sell", self.partial_sell.is_some()),
+            ("holding_percentage", self.holding_percentage.is_some()),
+        ];
+        for (name, set) in fields {
+            if set && !supported.contains(&name) {
+                return Err(anyhow!(
+                    "Condition {} is not supported by strategy {}",
+                    name,
+                    strategy
+                ));
             }
-            MonitorRuleType::Sell => {
-                todo!()
-                // if self.conditions.is_first_sell.unwrap_or(false)
-                //     && self.conditions.partial_sell.unwrap_or(false)
-                // {
-                //     // 检查是否是首次卖出且部分卖出
-                //     return true;
-                // }
+        }
+        Ok(())
+    }
+}
+
+fn validate_monitor_rule(rule: &MonitorRule) -> Result<(), ValidationError> {
+    StrategyRegistry::default()
+        .create(rule)
+        .map(|_| ())
+        .map_err(|e| ValidationError::new("strategy").with_message(e.to_string().into()))
+}
+
+/// what a running monitor needs from the daemon
+#[derive(Clone)]
+pub struct MonitorContext {
+    pub wss_url: String,
+    pub rpc_url: String,
+    pub manager: Arc<ModelsManager>,
+    pub alerts: AlertSender,
+    pub registry: Arc<StrategyRegistry>,
+    pub shutdown: watch::Receiver<bool>, // set once the daemon shuts down
+    pub executor: Option<Arc<Executor>>, // sends the CopyTrade orders, None unless execution is enabled
+}
+
+impl MonitorContext {
+    /// the context of the configured endpoints and database
+    pub async fn from_global(alerts: AlertSender, shutdown: watch::Receiver<bool>) -> Self {
+        let c = get_global_config().await;
+        MonitorContext {
+            wss_url: c.solana_wss_url.clone(),
+            rpc_url: c.solana_rpc_url.clone(),
+            manager: get_global_manager().await.clone(),
+            alerts,
+            registry: Arc::new(StrategyRegistry::default()),
+            shutdown,
+            executor: None,
+        }
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+
+    /// completes once the daemon shuts down, never when the shutdown sender is gone without it
+    pub async fn stopped(&self) {
+        let mut shutdown = self.shutdown.clone();
+        let stopped = shutdown.wait_for(|s| *s).await.is_ok();
+        if !stopped {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    /// the next item of `receiver`, after the shutdown only the ones already queued
+    pub async fn next_log<T>(&self, receiver: &mut mpsc::Receiver<T>) -> Option<T> {
+        if !self.is_shutdown() {
+            tokio::select! {
+                item = receiver.recv() => return item,
+                _ = self.stopped() => {}
             }
-            MonitorRuleType::ProfitHolding => {
-                let buy_flag = "Program log: Instruction: Buy".to_string();
-                let data_flag = "Program data".to_string();
-                let (sender, mut receiver) = mpsc::channel::<RpcLogsResponse>(1000);
-
-                let address = self.address.clone();
-                tokio::spawn(async move {
-                    let solana_client = SolanaMonitor::new(&c.solana_wss_url, &c.solana_rpc_url);
-                    solana_client
-                        .start_log_subscribe(&address, sender)
-                        .await
-                        .unwrap();
-                });
-
-                while let Some(log) = receiver.recv().await {
+        }
+        // stop the ingestion, drain the queue
+        receiver.close();
+        receiver.recv().await
+    }
+}
+
+impl MonitorRule {
+    /// feed the transactions mentioning the address to the rule's strategy until the subscription ends
+    pub async fn run(&self, ctx: &MonitorContext) -> Result<()> {
+        self.run_until(ctx, std::future::pending()).await
+    }
+
+    /// `run` until `stop` completes and the queued logs are handled, the strategy is shut down either way
+    pub async fn run_until(
+        &self,
+        ctx: &MonitorContext,
+        stop: impl Future<Output = ()>,
+    ) -> Result<()> {
+        let mut strategy = ctx.registry.create(self)?;
+        let (sender, mut receiver) = mpsc::channel::<Response<RpcLogsResponse>>(1000);
+        let mut subscription = spawn_log_subscribe(&self.address, ctx, sender.clone());
+        let sctx = StrategyContext::new(self, ctx, sender);
+
+        strategy.init(&sctx).await?;
+        if let Err(e) = sctx.restore_throttle().await {
+            warn!(
+                "{} restore cooldown error: {:?}, address: {}",
+                self.rule_type, e, self.address
+            );
+        }
+        let mut recent = RecentSignatures::new(1000);
+        let mut tick = tokio::time::interval(TICK_INTERVAL);
+        tokio::pin!(stop);
+        let mut stopping = false;
+        loop {
+            tokio::select! {
+                log = receiver.recv() => {
+                    let Some(log) = log else { break };
                     debug!("log: {:?}", log);
-                    if log.logs.contains(&buy_flag) {
-                        if let Err(e) = self
-                            .deal_profit_holding(&log.signature)
-                            // .deal_profit_holding(&solana_client, &log.signature)
-                            .await
-                        {
-                            error!(
-                                "deal_profit_holding error: {:?}, address: {}",
-                                e, self.address
-                            );
-                        }
+                    if !recent.insert(&log.value.signature) {
+                        continue;
+                    }
+                    let mut event = log.into();
+                    sctx.attach_swaps(&mut event).await;
+                    if let Err(e) = strategy.on_event(&sctx, &event).await {
+                        error!("{} on_event error: {:?}, address: {}", self.rule_type, e, self.address);
+                    }
+                }
+                _ = tick.tick(), if !stopping => {
+                    if let Err(e) = strategy.on_tick(&sctx).await {
+                        error!("{} on_tick error: {:?}, address: {}", self.rule_type, e, self.address);
                     }
                 }
-                Ok(())
+                _ = &mut subscription, if !stopping => {
+                    debug!("{} subscription ended, address: {}", self.rule_type, self.address);
+                    // the logs it queued are handled before returning
+                    stopping = true;
+                    sctx.unwatch_all();
+                    receiver.close();
+                }
+                _ = &mut stop, if !stopping => {
+                    debug!("{} stopping, address: {}", self.rule_type, self.address);
+                    // no new logs, the queued ones are still handled
+                    stopping = true;
+                    subscription.abort();
+                    sctx.unwatch_all();
+                    receiver.close();
+                }
             }
         }
+
+        sctx.unwatch_all();
+        if let Err(e) = sctx.save_throttle().await {
+            error!(
+                "{} save cooldown error: {:?}, address: {}",
+                self.rule_type, e, self.address
+            );
+        }
+        strategy.shutdown(&sctx).await
     }
 }
+
+/// subscribe the logs mentioning `address` into `sender` in a new task
+pub(crate) fn spawn_log_subscribe(
+    address: &str,
+    ctx: &MonitorContext,
+    sender: mpsc::Sender<Response<RpcLogsResponse>>,
+) -> JoinHandle<()> {
+    let address = address.to_string();
+    let solana_client = SolanaMonitor::new(&ctx.wss_url, &ctx.rpc_url);
+    tokio::spawn(async move {
+        if let Err(e) = solana_client.start_log_subscribe(&address, sender).await {
+            error!("log subscribe error: {:?}, address: {}", e, address);
+        }
+    })
+}
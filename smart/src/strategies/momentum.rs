@@ -0,0 +1,459 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::{
+    MonitorCondition, MonitorRule, PositionBook, RuleTarget, Strategy, StrategyContext,
+    StrategyEvent,
+};
+use crate::{
+    alert::{AlertKind, SpikeMetric},
+    models::{MONITOR_STATUS_ACTIVE, MONITOR_STATUS_CLOSED},
+    sol_client::{ui_amount, TradeEventData, SOL_DECIMALS},
+};
+
+pub const MOMENTUM_STRATEGY: &str = "Momentum";
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MomentumConfig {
+    pub window_secs: u64,            // length of a rolling window
+    pub baseline_windows: usize,     // earlier windows averaged into the baseline
+    pub min_baseline_windows: usize, // windows of history a mint needs before it alerts
+    pub spike_ratio: f64,            // alert when a metric reaches this multiple of its baseline
+    pub min_sol_volume: f64,         // SOL, quieter windows never alert
+}
+
+impl Default for MomentumConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: 60,
+            baseline_windows: 10,
+            min_baseline_windows: 3,
+            spike_ratio: 3.0,
+            min_sol_volume: 1.0,
+        }
+    }
+}
+
+impl MomentumConfig {
+    fn validate(&self) -> Result<()> {
+        if self.window_secs == 0 {
+            return Err(anyhow!("window_secs is at least 1"));
+        }
+        if self.min_baseline_windows == 0 || self.min_baseline_windows > self.baseline_windows {
+            return Err(anyhow!(
+                "min_baseline_windows is between 1 and baseline_windows"
+            ));
+        }
+        if !self.spike_ratio.is_finite() || self.spike_ratio <= 1.0 {
+            return Err(anyhow!("spike_ratio is above 1"));
+        }
+        if !self.min_sol_volume.is_finite() || self.min_sol_volume < 0.0 {
+            return Err(anyhow!("min_sol_volume is not negative"));
+        }
+        Ok(())
+    }
+}
+
+/// a metric of the current window against its baseline
+#[derive(Debug, Clone, PartialEq)]
+pub struct MomentumSpike {
+    pub metric: SpikeMetric,
+    pub value: f64,
+    pub baseline: f64,
+    pub buy_sol: u64,
+    pub sell_sol: u64,
+    pub buyers: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Window {
+    start: i64,
+    buy_sol: u64,
+    sell_sol: u64,
+    buyers: HashSet<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MintWindows {
+    first_start: i64,
+    windows: VecDeque<Window>,   // oldest first, the last one is current
+    fired: HashSet<SpikeMetric>, // metrics alerted in the current window
+}
+
+/// the windows kept over a restart, with the length they were cut with
+#[derive(Serialize, Deserialize)]
+struct MomentumCheckpoint {
+    window_secs: u64,
+    mints: HashMap<String, MintWindows>,
+}
+
+/// rolling windows of the trades per mint, on chain time so replayed trades behave the same
+pub struct MomentumTracker {
+    config: MomentumConfig,
+    mints: HashMap<String, MintWindows>,
+}
+
+impl MomentumTracker {
+    pub fn new(config: MomentumConfig) -> Self {
+        Self {
+            config,
+            mints: HashMap::new(),
+        }
+    }
+
+    pub fn forget(&mut self, mint: &str) {
+        self.mints.remove(mint);
+    }
+
+    /// add a trade to its window, returns the metrics of the current window that spiked first now
+    pub fn record(&mut self, trade: &TradeEventData) -> Vec<MomentumSpike> {
+        let w = self.config.window_secs as i64;
+        let start = trade.timestamp - trade.timestamp.rem_euclid(w);
+        let m = self
+            .mints
+            .entry(trade.mint.clone())
+            .or_insert_with(|| MintWindows {
+                first_start: start,
+                windows: VecDeque::new(),
+                fired: HashSet::new(),
+            });
+        let current = m.windows.back().map_or(i64::MIN, |c| c.start);
+        if start > current {
+            m.windows.push_back(Window {
+                start,
+                ..Default::default()
+            });
+            m.fired.clear();
+            let oldest = start - self.config.baseline_windows as i64 * w;
+            while m.windows.front().is_some_and(|f| f.start < oldest) {
+                m.windows.pop_front();
+            }
+        }
+        // a late trade only counts for the baseline
+        let Some(window) = m.windows.iter_mut().rev().find(|x| x.start <= start) else {
+            return Vec::new();
+        };
+        if window.start != start {
+            return Vec::new();
+        }
+        if trade.is_buy {
+            window.buy_sol += trade.sol_amount;
+            window.buyers.insert(trade.user.clone());
+        } else {
+            window.sell_sol += trade.sol_amount;
+        }
+        if start < current {
+            return Vec::new();
+        }
+
+        let history = ((start - m.first_start) / w) as usize;
+        if history < self.config.min_baseline_windows {
+            return Vec::new();
+        }
+        let n = history.min(self.config.baseline_windows);
+        let (mut base_buy, mut base_sell, mut base_buyers) = (0u64, 0u64, 0usize);
+        for x in m.windows.iter().filter(|x| x.start < start) {
+            if x.start >= start - n as i64 * w {
+                base_buy += x.buy_sol;
+                base_sell += x.sell_sol;
+                base_buyers += x.buyers.len();
+            }
+        }
+        let cur = m.windows.back().expect("current window");
+        let (buy, sell) = (
+            ui_amount(cur.buy_sol, SOL_DECIMALS),
+            ui_amount(cur.sell_sol, SOL_DECIMALS),
+        );
+        let per_window = |v: f64| v / n as f64;
+        let mut candidates = vec![
+            (
+                SpikeMetric::BuyVolume,
+                buy,
+                per_window(ui_amount(base_buy, SOL_DECIMALS)),
+                buy,
+            ),
+            (
+                SpikeMetric::SellVolume,
+                sell,
+                per_window(ui_amount(base_sell, SOL_DECIMALS)),
+                sell,
+            ),
+            (
+                SpikeMetric::UniqueBuyers,
+                cur.buyers.len() as f64,
+                per_window(base_buyers as f64),
+                buy,
+            ),
+        ];
+        // the ratio is only defined with sells, a window of buys alone spikes the buy volume
+        if base_sell > 0 && cur.sell_sol > 0 {
+            candidates.push((
+                SpikeMetric::BuySellRatio,
+                buy / sell,
+                base_buy as f64 / base_sell as f64,
+                buy,
+            ));
+        }
+
+        let mut spikes = Vec::new();
+        for (metric, value, baseline, volume) in candidates {
+            if volume < self.config.min_sol_volume
+                || value < baseline * self.config.spike_ratio
+                || m.fired.contains(&metric)
+            {
+                continue;
+            }
+            m.fired.insert(metric);
+            spikes.push(MomentumSpike {
+                metric,
+                value,
+                baseline,
+                buy_sol: cur.buy_sol,
+                sell_sol: cur.sell_sol,
+                buyers: cur.buyers.len(),
+            });
+        }
+        spikes
+    }
+}
+
+/// follow the wallet into its tokens, or the monitored mint, and alert when their trading spikes against their own baseline
+pub struct MomentumStrategy {
+    conditions: MonitorCondition,
+    window_secs: u64,
+    tracker: MomentumTracker,
+    book: PositionBook,
+    followed: HashSet<String>, // mints the wallet holds
+}
+
+impl MomentumStrategy {
+    pub fn new(rule: &MonitorRule, config: MomentumConfig) -> Result<Self> {
+        rule.check_target(MOMENTUM_STRATEGY, &[RuleTarget::Wallet, RuleTarget::Mint])?;
+        rule.conditions.check(MOMENTUM_STRATEGY, &[])?;
+        config.validate()?;
+        Ok(Self {
+            conditions: rule.conditions.clone(),
+            window_secs: config.window_secs,
+            tracker: MomentumTracker::new(config),
+            book: PositionBook::default(),
+            followed: HashSet::new(),
+        })
+    }
+
+    /// follow a buy of the wallet, or stop following once it sold everything
+    async fn follow(
+        &mut self,
+        ctx: &StrategyContext,
+        trade: &TradeEventData,
+        signature: &str,
+    ) -> Result<()> {
+        let known = self.book.get(&trade.user, &trade.mint).is_some();
+        let position = self.book.record(trade);
+        if trade.is_buy {
+            if self.followed.insert(trade.mint.clone()) {
+                ctx.manager
+                    .add_new_spl_token(&trade.mint, &ctx.address, &ctx.strategy_name)
+                    .await?;
+                ctx.watch(&trade.mint);
+            }
+            return Ok(());
+        }
+        if !self.followed.contains(&trade.mint) {
+            return Ok(());
+        }
+        // a position restored from the database has no trades in the book
+        let closed = if known {
+            position.holding() == 0
+        } else {
+            match ctx
+                .solana_client
+                .get_token_balances(signature, &trade.user, &trade.mint)
+                .await
+            {
+                Ok((_, post)) => post == 0,
+                Err(e) => {
+                    warn!(
+                        "get token balances error: {:?}, signature: {}",
+                        e, signature
+                    );
+                    false
+                }
+            }
+        };
+        if closed {
+            info!("stop following {}, address: {}", trade.mint, ctx.address);
+            self.followed.remove(&trade.mint);
+            self.tracker.forget(&trade.mint);
+            ctx.manager
+                .update_spl_token_status(
+                    &trade.mint,
+                    &ctx.address,
+                    &ctx.strategy_name,
+                    MONITOR_STATUS_CLOSED,
+                )
+                .await?;
+            ctx.unwatch(&trade.mint);
+        }
+        Ok(())
+    }
+
+    /// the windows of the followed mints saved by the last run
+    async fn restore_windows(&mut self, ctx: &StrategyContext) -> Result<()> {
+        let Some(checkpoint) = ctx.take_checkpoint::<MomentumCheckpoint>().await? else {
+            return Ok(());
+        };
+        if checkpoint.window_secs != self.window_secs {
+            return Ok(());
+        }
+        self.tracker.mints = checkpoint
+            .mints
+            .into_iter()
+            .filter(|(mint, _)| self.followed.contains(mint))
+            .collect();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Strategy for MomentumStrategy {
+    async fn init(&mut self, ctx: &StrategyContext) -> Result<()> {
+        // a mint rule is already subscribed to its mint
+        if ctx.target == RuleTarget::Mint {
+            self.followed.insert(ctx.address.clone());
+            return self.restore_windows(ctx).await;
+        }
+        for token in ctx
+            .manager
+            .get_spl_tokens(&ctx.address, &ctx.strategy_name, MONITOR_STATUS_ACTIVE)
+            .await?
+        {
+            info!(
+                "following {} from an earlier run, address: {}",
+                token.mint, ctx.address
+            );
+            ctx.watch(&token.mint);
+            self.followed.insert(token.mint);
+        }
+        self.restore_windows(ctx).await
+    }
+
+    async fn on_event(&mut self, ctx: &StrategyContext, event: &StrategyEvent) -> Result<()> {
+        for trade in &event.trades {
+            if ctx.target == RuleTarget::Wallet && trade.user == ctx.address {
+                self.follow(ctx, trade, &event.signature).await?;
+            }
+            if !self.followed.contains(&trade.mint) {
+                continue;
+            }
+            let spikes = self.tracker.record(trade);
+            if spikes.is_empty() {
+                continue;
+            }
+            let facts = ctx.trade_facts(trade, &self.conditions, None).await;
+            if !self.conditions.matches(&facts) {
+                continue;
+            }
+            for s in spikes {
+                ctx.alert(
+                    &trade.mint,
+                    &event.signature,
+                    AlertKind::Momentum {
+                        metric: s.metric,
+                        value: s.value,
+                        baseline: s.baseline,
+                        window_secs: self.window_secs,
+                        buy_sol: s.buy_sol,
+                        sell_sol: s.sell_sol,
+                        buyers: s.buyers,
+                    },
+                )
+                .await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&mut self, ctx: &StrategyContext) -> Result<()> {
+        ctx.save_checkpoint(&MomentumCheckpoint {
+            window_secs: self.window_secs,
+            mints: std::mem::take(&mut self.tracker.mints),
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(user: &str, is_buy: bool, sol: u64, timestamp: i64) -> TradeEventData {
+        TradeEventData {
+            mint: "mint".to_string(),
+            sol_amount: sol,
+            token_amount: 1_000_000,
+            is_buy,
+            user: user.to_string(),
+            timestamp,
+            virtual_sol_reserves: 30_000_000_000,
+            virtual_token_reserves: 1_000_000_000_000_000,
+            real_sol_reserves: 0,
+            real_token_reserves: 0,
+        }
+    }
+
+    #[test]
+    fn test_momentum_tracker() -> Result<()> {
+        let config = MomentumConfig {
+            window_secs: 60,
+            baseline_windows: 4,
+            min_baseline_windows: 2,
+            spike_ratio: 3.0,
+            min_sol_volume: 1.0,
+        };
+        config.validate()?;
+        let mut tracker = MomentumTracker::new(config);
+        let sol = 1_000_000_000;
+        // a quiet baseline: 1 SOL of buys and 1 SOL of sells per minute, one buyer
+        for minute in 0..4 {
+            let t = 1_734_600_000 + minute * 60;
+            assert!(tracker.record(&trade("a", true, sol, t)).is_empty());
+            assert!(tracker.record(&trade("b", false, sol, t + 1)).is_empty());
+        }
+
+        // 2 SOL from a new buyer isn't a spike yet
+        let t = 1_734_600_000 + 4 * 60;
+        assert!(tracker.record(&trade("c", true, 2 * sol, t)).is_empty());
+        // 3 SOL of buys is
+        let spikes = tracker.record(&trade("d", true, sol, t + 1));
+        assert_eq!(spikes.len(), 1);
+        assert_eq!(spikes[0].metric, SpikeMetric::BuyVolume);
+        assert_eq!(spikes[0].value, 3.0);
+        assert_eq!(spikes[0].baseline, 1.0);
+        assert_eq!(spikes[0].buyers, 2);
+        // alerted once per window
+        let spikes = tracker.record(&trade("e", true, sol, t + 2));
+        assert_eq!(spikes.len(), 1);
+        assert_eq!(spikes[0].metric, SpikeMetric::UniqueBuyers);
+        assert_eq!(spikes[0].value, 3.0);
+        // 4 SOL bought per SOL sold against 1 in the baseline
+        let spikes = tracker.record(&trade("b", false, sol, t + 3));
+        assert_eq!(spikes.len(), 1);
+        assert_eq!(spikes[0].metric, SpikeMetric::BuySellRatio);
+        assert_eq!(spikes[0].value, 4.0);
+
+        // a late trade goes into its own window
+        assert!(tracker
+            .record(&trade("f", true, 10 * sol, t - 60))
+            .is_empty());
+        // a new token has no baseline
+        let mut other = trade("a", true, 100 * sol, t);
+        other.mint = "other".to_string();
+        assert!(tracker.record(&other).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::{debug, info};
+
+use super::{
+    follow::{default_follow_sells, exit_reason, follow_sell_amount, validate_follow},
+    MonitorCondition, MonitorRule, PositionBook, RuleTarget, Strategy, StrategyContext,
+    StrategyEvent, TrailingStopConfig,
+};
+use crate::{
+    alert::{AlertKind, ExitReason},
+    models::{
+        PaperFill, PaperPosition, PAPER_FILL_COPY, PAPER_POSITION_CLOSED, PAPER_POSITION_OPEN,
+    },
+    sol_client::{
+        curve::{BondingCurve, PUMP_FEE_BPS},
+        ui_amount, TradeEventData, PUMP_TOKEN_DECIMALS, SOL_DECIMALS,
+    },
+};
+
+/// strategy name of the paper copy trading
+pub const PAPER_TRADE_STRATEGY: &str = "PaperTrade";
+
+fn default_max_slippage() -> f64 {
+    5.0
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PaperTradeConfig {
+    pub sol_amount: f64, // 每次跟单的 SOL, the pump.fun fee included
+    #[serde(default = "default_max_slippage")]
+    pub max_slippage: f64, // percent, a buy filling further from the price is skipped
+    #[serde(default = "default_follow_sells")]
+    pub follow_sells: bool, // sell the part of the position the wallet sold of its own
+    pub exit: Option<TrailingStopConfig>, // sell the whole position on these rules
+}
+
+impl PaperTradeConfig {
+    fn validate(&self) -> Result<()> {
+        if !self.max_slippage.is_finite() || self.max_slippage <= 0.0 {
+            return Err(anyhow!("max_slippage is a positive percentage"));
+        }
+        validate_follow(self.sol_amount, self.exit.as_ref())
+    }
+}
+
+/// a simulated trade against the bonding curve
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaperFillQuote {
+    pub sol_amount: u64, // paid or received, fee included, lamports
+    pub token_amount: u64,
+    pub fee: u64,
+    pub price: f64,    // average price in SOL
+    pub slippage: f64, // percent
+}
+
+impl PaperFillQuote {
+    /// buy with `budget` lamports, the fee included
+    pub fn buy(curve: &BondingCurve, budget: u64) -> Self {
+        let sol = (budget as u128 * 10_000 / (10_000 + PUMP_FEE_BPS as u128)) as u64;
+        let sol = curve.buy_spend(sol);
+        let tokens = curve.buy_quote(sol);
+        Self::new(curve, sol, tokens, true)
+    }
+
+    pub fn sell(curve: &BondingCurve, tokens: u64) -> Self {
+        let sol = curve.sell_quote(tokens);
+        Self::new(curve, sol, tokens, false)
+    }
+
+    fn new(curve: &BondingCurve, sol: u64, tokens: u64, is_buy: bool) -> Self {
+        let fee = sol * PUMP_FEE_BPS / 10_000;
+        let price = if tokens == 0 {
+            0.0
+        } else {
+            ui_amount(sol, SOL_DECIMALS) / ui_amount(tokens, PUMP_TOKEN_DECIMALS)
+        };
+        Self {
+            sol_amount: if is_buy { sol + fee } else { sol - fee },
+            token_amount: tokens,
+            fee,
+            price,
+            slippage: curve.slippage(sol, tokens),
+        }
+    }
+}
+
+/// copy the buys of the monitored wallet on paper with a fixed size, filled against the bonding curve
+/// after the wallet's trade. the positions are sold when the wallet sells or on the exit rules.
+pub struct PaperTradeStrategy {
+    conditions: MonitorCondition,
+    config: PaperTradeConfig,
+    book: PositionBook, // the wallet's own positions, for the part of a sell
+    positions: HashMap<String, PaperPosition>, // mint -> open paper position
+}
+
+impl PaperTradeStrategy {
+    pub fn new(rule: &MonitorRule, config: PaperTradeConfig) -> Result<Self> {
+        rule.check_target(PAPER_TRADE_STRATEGY, &[RuleTarget::Wallet])?;
+        rule.conditions.check(PAPER_TRADE_STRATEGY, &[])?;
+        config.validate()?;
+        Ok(Self {
+            conditions: rule.conditions.clone(),
+            config,
+            book: PositionBook::default(),
+            positions: HashMap::new(),
+        })
+    }
+
+    async fn open(
+        &mut self,
+        ctx: &StrategyContext,
+        trade: &TradeEventData,
+        signature: &str,
+    ) -> Result<()> {
+        if self.positions.contains_key(&trade.mint) {
+            debug!("paper position of {} already open", trade.mint);
+            return Ok(());
+        }
+        let facts = ctx.trade_facts(trade, &self.conditions, None).await;
+        if !self.conditions.matches(&facts) {
+            return Ok(());
+        }
+        let budget = (self.config.sol_amount * 10f64.powi(SOL_DECIMALS as i32)) as u64;
+        let fill = PaperFillQuote::buy(&BondingCurve::from_trade(trade), budget);
+        if fill.token_amount == 0 || fill.slippage > self.config.max_slippage {
+            info!(
+                "skip paper buy of {}, slippage {:.2}%, address: {}",
+                trade.mint, fill.slippage, ctx.address
+            );
+            return Ok(());
+        }
+        let mut position = PaperPosition {
+            wallet: ctx.address.clone(),
+            mint: trade.mint.clone(),
+            token_amount: fill.token_amount as i64,
+            cost: fill.sol_amount as i64,
+            entry_price: fill.price,
+            high_price: trade.price(),
+            status: PAPER_POSITION_OPEN.to_string(),
+            opened_at: trade.timestamp,
+            ..Default::default()
+        };
+        position.id = ctx.manager.add_paper_position(&position).await?;
+        record_fill(ctx, position.id, trade, signature, fill, None, None).await?;
+        ctx.watch(&trade.mint);
+        self.positions.insert(trade.mint.clone(), position);
+        Ok(())
+    }
+
+    /// sell `tokens` of the open position in the mint of `trade`
+    async fn close(
+        &mut self,
+        ctx: &StrategyContext,
+        trade: &TradeEventData,
+        signature: &str,
+        tokens: u64,
+        exit: Option<ExitReason>,
+    ) -> Result<()> {
+        let Some(mut position) = self.positions.get(&trade.mint).cloned() else {
+            return Ok(());
+        };
+        let holding = position.token_amount as u64;
+        let tokens = tokens.min(holding);
+        let fill = PaperFillQuote::sell(&BondingCurve::from_trade(trade), tokens);
+        let cost = (position.cost as u128 * tokens as u128 / holding.max(1) as u128) as i64;
+        let pnl = fill.sol_amount as i64 - cost;
+        position.token_amount -= tokens as i64;
+        position.cost -= cost;
+        position.realized_pnl += pnl;
+        if position.token_amount == 0 {
+            position.status = PAPER_POSITION_CLOSED.to_string();
+            position.closed_at = Some(trade.timestamp);
+        }
+        // the position in memory follows the database, a failed update keeps it as it was
+        ctx.manager.update_paper_position(&position).await?;
+        let id = position.id;
+        if position.token_amount == 0 {
+            self.positions.remove(&trade.mint);
+            ctx.unwatch(&trade.mint);
+        } else {
+            self.positions.insert(trade.mint.clone(), position);
+        }
+        record_fill(ctx, id, trade, signature, fill, exit, Some(pnl)).await
+    }
+
+    /// the wallet sold, sell the same part of the paper position
+    async fn follow_sell(
+        &mut self,
+        ctx: &StrategyContext,
+        trade: &TradeEventData,
+        signature: &str,
+        holding: Option<u64>,
+    ) -> Result<()> {
+        let Some(position) = self.positions.get(&trade.mint) else {
+            return Ok(());
+        };
+        let tokens = follow_sell_amount(position.token_amount as u64, trade.token_amount, holding);
+        self.close(ctx, trade, signature, tokens, None).await
+    }
+
+    async fn check_exit(
+        &mut self,
+        ctx: &StrategyContext,
+        trade: &TradeEventData,
+        signature: &str,
+    ) -> Result<()> {
+        let (Some(exit), Some(position)) = (&self.config.exit, self.positions.get_mut(&trade.mint))
+        else {
+            return Ok(());
+        };
+        let mut high_price = position.high_price;
+        let reason = exit_reason(exit, position.entry_price, &mut high_price, trade.price());
+        if high_price > position.high_price {
+            position.high_price = high_price;
+            ctx.manager.update_paper_position(position).await?;
+        }
+        if let Some(reason) = reason {
+            let tokens = position.token_amount as u64;
+            self.close(ctx, trade, signature, tokens, Some(reason))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// store the fill and alert on it
+async fn record_fill(
+    ctx: &StrategyContext,
+    position_id: i64,
+    trade: &TradeEventData,
+    signature: &str,
+    fill: PaperFillQuote,
+    exit: Option<ExitReason>,
+    realized_pnl: Option<i64>,
+) -> Result<()> {
+    let is_buy = realized_pnl.is_none();
+    ctx.manager
+        .add_paper_fill(&PaperFill {
+            position_id,
+            signature: signature.to_string(),
+            is_buy,
+            sol_amount: fill.sol_amount as i64,
+            token_amount: fill.token_amount as i64,
+            fee: fill.fee as i64,
+            price: fill.price,
+            slippage: fill.slippage,
+            reason: exit.map_or(PAPER_FILL_COPY.to_string(), |r| format!("{:?}", r)),
+            realized_pnl,
+            timestamp: trade.timestamp,
+            ..Default::default()
+        })
+        .await?;
+    ctx.alert(
+        &trade.mint,
+        signature,
+        AlertKind::PaperFill {
+            is_buy,
+            sol_amount: fill.sol_amount,
+            token_amount: fill.token_amount,
+            price: fill.price,
+            slippage: fill.slippage,
+            exit,
+            realized_pnl,
+        },
+    )
+    .await;
+    Ok(())
+}
+
+#[async_trait]
+impl Strategy for PaperTradeStrategy {
+    async fn init(&mut self, ctx: &StrategyContext) -> Result<()> {
+        for p in ctx
+            .manager
+            .get_paper_positions(Some(&ctx.address), PAPER_POSITION_OPEN)
+            .await?
+        {
+            info!(
+                "paper position of {} from an earlier run, address: {}",
+                p.mint, ctx.address
+            );
+            ctx.watch(&p.mint);
+            self.positions.insert(p.mint.clone(), p);
+        }
+        Ok(())
+    }
+
+    async fn on_event(&mut self, ctx: &StrategyContext, event: &StrategyEvent) -> Result<()> {
+        for trade in &event.trades {
+            if trade.user == ctx.address {
+                let holding = self.book.get(&trade.user, &trade.mint).map(|p| p.holding());
+                self.book.record(trade);
+                if trade.is_buy {
+                    self.open(ctx, trade, &event.signature).await?;
+                } else if self.config.follow_sells {
+                    self.follow_sell(ctx, trade, &event.signature, holding)
+                        .await?;
+                }
+            }
+            self.check_exit(ctx, trade, &event.signature).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alert::alert_channel, test_support::MockSolana};
+
+    #[tokio::test]
+    async fn test_paper_copy_trade() -> Result<()> {
+        let curve = BondingCurve {
+            virtual_sol_reserves: 30_000_000_000,
+            virtual_token_reserves: 1_000_000_000_000_000,
+            real_token_reserves: 700_000_000_000_000,
+        };
+        let fill = PaperFillQuote::buy(&curve, 1_010_000_000);
+        assert_eq!(fill.fee, 10_000_000);
+        assert_eq!(fill.sol_amount, 1_010_000_000);
+        // 1 SOL into 30 SOL of virtual reserves gets 1/31 of the tokens
+        assert_eq!(fill.token_amount, 32_258_064_516_129);
+        assert!((fill.slippage - 100.0 / 30.0).abs() < 1e-6);
+
+        let mock = MockSolana::start().await?;
+        let (sender, mut receiver) = alert_channel();
+        let ctx = mock.context(sender).await?;
+        let wallet = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x";
+        let mint = "7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump";
+        let start = 1_734_616_564;
+        let rule: MonitorRule = toml::from_str(&format!(
+            r#"
+            address = "{}"
+            rule_type = "PaperTrade"
+            [config]
+            sol_amount = 0.1
+            exit = {{ take_profit = 100.0 }}
+            "#,
+            wallet
+        ))?;
+        let mut strategy = PaperTradeStrategy::new(&rule, rule.strategy_config()?)?;
+        let sctx = StrategyContext::replay(&rule, &ctx, start);
+        strategy.init(&sctx).await?;
+
+        let trade = |user: &str, is_buy: bool, tokens: u64, sol: u64| TradeEventData {
+            mint: mint.to_string(),
+            sol_amount: 1_000_000_000,
+            token_amount: tokens,
+            is_buy,
+            user: user.to_string(),
+            timestamp: start,
+            virtual_sol_reserves: sol * 1_000_000_000,
+            virtual_token_reserves: 1_000_000_000_000_000,
+            real_sol_reserves: 0,
+            real_token_reserves: 700_000_000_000_000,
+        };
+        let event = |i: usize, t: TradeEventData| StrategyEvent {
+            signature: format!("sig{}", i),
+            trades: vec![t],
+            ..Default::default()
+        };
+        for (i, t) in [
+            trade(wallet, true, 40_000_000_000_000, 30),
+            // the wallet sells half, so does the paper position
+            trade(wallet, false, 20_000_000_000_000, 45),
+            // doubled from the entry
+            trade("other", true, 1, 62),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            strategy.on_event(&sctx, &event(i, t)).await?;
+        }
+
+        let mut kinds = vec![];
+        while let Ok(alert) = receiver.try_recv() {
+            kinds.push(alert.kind);
+        }
+        let names: Vec<String> = kinds.iter().map(|k| k.name()).collect();
+        assert_eq!(names, ["PaperBuy", "PaperSell", "PaperSell"]);
+        let AlertKind::PaperFill { exit, .. } = &kinds[2] else {
+            panic!("not a paper fill");
+        };
+        assert_eq!(*exit, Some(ExitReason::TakeProfit));
+
+        assert!(ctx
+            .manager
+            .get_paper_positions(None, PAPER_POSITION_OPEN)
+            .await?
+            .is_empty());
+        let closed = ctx
+            .manager
+            .get_paper_positions(Some(wallet), PAPER_POSITION_CLOSED)
+            .await?;
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].token_amount, 0);
+        assert!(closed[0].realized_pnl > 0);
+        let fills = ctx.manager.get_paper_fills(start).await?;
+        assert_eq!(fills.len(), 3);
+        assert_eq!(fills[1].token_amount, fills[0].token_amount / 2);
+        assert_eq!(
+            fills[1].token_amount + fills[2].token_amount,
+            fills[0].token_amount
+        );
+        assert_eq!(fills[2].reason, "TakeProfit");
+        let realized: i64 = fills.iter().filter_map(|f| f.realized_pnl).sum();
+        assert_eq!(realized, closed[0].realized_pnl);
+
+        // a sell the database didn't take leaves the position as it was
+        let buy = trade(wallet, true, 40_000_000_000_000, 30);
+        strategy.on_event(&sctx, &event(3, buy)).await?;
+        let held = strategy.positions[mint].clone();
+        ctx.manager.close().await;
+        let sell = trade(wallet, false, 40_000_000_000_000, 45);
+        let tokens = held.token_amount as u64;
+        assert!(strategy
+            .close(&sctx, &sell, "sig4", tokens, None)
+            .await
+            .is_err());
+        assert_eq!(strategy.positions[mint], held);
+        Ok(())
+    }
+}
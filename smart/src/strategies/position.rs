@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use crate::sol_client::{ui_amount, TradeEventData, PUMP_TOKEN_DECIMALS, SOL_DECIMALS};
+
+/// what a wallet holds of one mint, seen from the trades the monitor received
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Position {
+    pub bought: u64,     // tokens bought, or held when first seen, raw units
+    pub sold: u64,       // tokens sold, raw units
+    pub sell_count: u32, // sells since the position was opened
+    pub opened: bool,    // the opening buy was seen by the monitor
+    pub cost: u64,       // average cost of the holding, lamports, 0 when unknown
+}
+
+impl Position {
+    pub fn holding(&self) -> u64 {
+        self.bought.saturating_sub(self.sold)
+    }
+
+    /// remaining holding as a percentage of the tokens bought
+    pub fn holding_percentage(&self) -> f64 {
+        if self.bought == 0 {
+            return 0.0;
+        }
+        self.holding() as f64 / self.bought as f64 * 100.0
+    }
+
+    /// average entry price in SOL
+    pub fn entry_price(&self) -> Option<f64> {
+        if self.cost == 0 || self.holding() == 0 {
+            return None;
+        }
+        Some(ui_amount(self.cost, SOL_DECIMALS) / ui_amount(self.holding(), PUMP_TOKEN_DECIMALS))
+    }
+
+    /// unrealized profit of the holding at `price`, None when the cost is unknown
+    pub fn profit_percentage(&self, price: f64) -> Option<f64> {
+        let entry_price = self.entry_price()?;
+        Some((price - entry_price) / entry_price * 100.0)
+    }
+}
+
+/// positions per (wallet, mint)
+#[derive(Debug, Default)]
+pub struct PositionBook {
+    positions: HashMap<(String, String), Position>,
+}
+
+impl PositionBook {
+    pub fn get(&self, wallet: &str, mint: &str) -> Option<&Position> {
+        self.positions.get(&(wallet.to_string(), mint.to_string()))
+    }
+
+    /// start tracking a position the monitor did not see opened, `holding` is the current balance
+    pub fn seed(&mut self, wallet: &str, mint: &str, holding: u64) {
+        self.positions.insert(
+            (wallet.to_string(), mint.to_string()),
+            Position {
+                bought: holding,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// apply a trade and return the position after it, a full exit closes the position
+    pub fn record(&mut self, trade: &TradeEventData) -> Position {
+        let key = (trade.user.clone(), trade.mint.clone());
+        let position = self.positions.entry(key.clone()).or_default();
+        if trade.is_buy {
+            if position.holding() == 0 {
+                *position = Position {
+                    opened: true,
+                    ..Default::default()
+                };
+            }
+            position.bought += trade.token_amount;
+            position.cost += trade.sol_amount;
+        } else {
+            // average cost, the sold part takes its share of the cost with it
+            let holding = position.holding();
+            let sold = trade.token_amount.min(holding);
+            if holding > 0 {
+                position.cost -= (position.cost as u128 * sold as u128 / holding as u128) as u64;
+            }
+            position.sold += trade.token_amount;
+            position.sell_count += 1;
+        }
+        let after = position.clone();
+        if after.holding() == 0 {
+            self.positions.remove(&key);
+        }
+        after
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(is_buy: bool, token_amount: u64) -> TradeEventData {
+        TradeEventData {
+            mint: "mint".to_string(),
+            sol_amount: token_amount * 1000,
+            token_amount,
+            is_buy,
+            user: "wallet".to_string(),
+            timestamp: 0,
+            virtual_sol_reserves: 0,
+            virtual_token_reserves: 0,
+            real_sol_reserves: 0,
+            real_token_reserves: 0,
+        }
+    }
+
+    #[test]
+    fn test_position_book() {
+        let mut book = PositionBook::default();
+        book.record(&trade(true, 600));
+        let p = book.record(&trade(true, 400));
+        assert!(p.opened);
+        assert_eq!(p.holding(), 1000);
+
+        let p = book.record(&trade(false, 700));
+        assert_eq!(p.sell_count, 1);
+        assert_eq!(p.holding_percentage(), 30.0);
+        assert_eq!(p.cost, 300_000);
+        // 1000 lamports per raw unit is 1 SOL per token
+        assert_eq!(p.profit_percentage(1.5), Some(50.0));
+
+        let p = book.record(&trade(false, 300));
+        assert_eq!(p.holding(), 0);
+        assert!(book.get("wallet", "mint").is_none());
+
+        // a position first seen at a sell
+        book.seed("wallet", "mint", 500);
+        let p = book.record(&trade(false, 100));
+        assert!(!p.opened);
+        assert_eq!(p.holding_percentage(), 80.0);
+        assert_eq!(p.profit_percentage(1.5), None);
+    }
+}
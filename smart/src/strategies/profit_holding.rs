@@ -1,31 +1,356 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
-use solana_transaction_status::option_serializer::OptionSerializer;
-use tracing::info;
-use super::MonitorRule;
-
-impl MonitorRule {
-    pub async fn deal_profit_holding(&self, sig: &str) -> Result<()> {
-        todo!()
-        // 1. get tx use sig
-        // let meta = solana_client.get_tx(&sig).await?;
-        // if let OptionSerializer::Some(post_token_balances) = meta.post_token_balances {
-        //     for post_token_balance in post_token_balances {
-        //         if post_token_balance.owner == OptionSerializer::Some(self.address.clone()) {
-        //             let token_address = post_token_balance.mint;
-        //             let buy_amount = post_token_balance.ui_token_amount;
-        //             info!(
-        //                 "deal_profit_holding: token_address: {}, buy_amount: {}",
-        //                 token_address, buy_amount.ui_amount_string
-        //             );
-        //         }
-        //     }
-        // }
-        // Ok(())
-
-        // if let Some(profit_percentage) = self.conditions.profit_percentage {
-        //     if rpcLogs.current_profit_percentage > profit_percentage {
-        //         return true;
-        //     }
-        // }
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use super::{
+    AccountingMode, LedgerPnl, MonitorCondition, MonitorRule, RuleTarget, Strategy,
+    StrategyContext, StrategyEvent,
+};
+use crate::{
+    alert::AlertKind,
+    models::{PositionLot, MONITOR_STATUS_ACTIVE, MONITOR_STATUS_CLOSED},
+    sol_client::TradeEventData,
+};
+
+/// strategy name stored in `spl_token`
+pub const PROFIT_HOLDING_STRATEGY: &str = "ProfitHolding";
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfitHoldingConfig {
+    #[serde(default)]
+    pub report_interval_secs: u64, // log the open positions every n seconds, 0 disables
+    #[serde(default)]
+    pub accounting: AccountingMode, // fifo or average_cost
+}
+
+/// positions of the monitored wallet, marked to market with every trade of their mints
+pub struct ProfitHoldingStrategy {
+    conditions: MonitorCondition,
+    config: ProfitHoldingConfig,
+    lots: HashMap<String, Vec<PositionLot>>, // mint -> lots of the open position
+    above: HashMap<String, bool>,            // mint -> profit is above the threshold
+    prices: HashMap<String, f64>,            // mint -> last price
+    last_report: Option<i64>,                // unix seconds, the first tick starts the interval
+}
+
+impl ProfitHoldingStrategy {
+    pub fn new(rule: &MonitorRule, config: ProfitHoldingConfig) -> Result<Self> {
+        rule.check_target(PROFIT_HOLDING_STRATEGY, &[RuleTarget::Wallet])?;
+        rule.conditions
+            .check(PROFIT_HOLDING_STRATEGY, &["profit_percentage"])?;
+        Ok(Self {
+            conditions: rule.conditions.clone(),
+            config,
+            lots: HashMap::new(),
+            above: HashMap::new(),
+            prices: HashMap::new(),
+            last_report: None,
+        })
+    }
+
+    fn pnl(&self, mint: &str) -> Option<LedgerPnl> {
+        let lots = self.lots.get(mint)?;
+        Some(LedgerPnl::compute(lots, self.config.accounting))
+    }
+
+    /// open, add to or reduce the positions with the wallet's own trades, every lot is kept in the ledger
+    async fn deal_profit_holding(
+        &mut self,
+        ctx: &StrategyContext,
+        trade: &TradeEventData,
+        signature: &str,
+        network_fee: u64,
+    ) -> Result<()> {
+        if !trade.is_buy && !self.lots.contains_key(&trade.mint) {
+            return Ok(());
+        }
+        let lot = PositionLot::from_trade(signature, trade, network_fee);
+        ctx.manager.add_position_lot(&lot).await?;
+        self.lots.entry(trade.mint.clone()).or_default().push(lot);
+        let pnl = self.pnl(&trade.mint).unwrap_or_default();
+        info!(
+            "deal_profit_holding: token_address: {}, holding: {}, cost: {}, realized: {}",
+            trade.mint, pnl.holding, pnl.cost_basis, pnl.realized_pnl
+        );
+        if trade.is_buy {
+            ctx.manager
+                .add_new_spl_token(&trade.mint, &ctx.address, &ctx.strategy_name)
+                .await?;
+            ctx.watch(&trade.mint);
+            return Ok(());
+        }
+
+        if pnl.holding == 0 {
+            info!(
+                "position closed, token_address: {}, address: {}",
+                trade.mint, ctx.address
+            );
+            ctx.manager
+                .update_spl_token_status(
+                    &trade.mint,
+                    &ctx.address,
+                    &ctx.strategy_name,
+                    MONITOR_STATUS_CLOSED,
+                )
+                .await?;
+            self.lots.remove(&trade.mint);
+            self.above.remove(&trade.mint);
+            self.prices.remove(&trade.mint);
+            ctx.unwatch(&trade.mint);
+        }
+        Ok(())
+    }
+
+    /// price the position with any trade of its mint, alert when the profit crosses the threshold
+    async fn mark_to_market(
+        &mut self,
+        ctx: &StrategyContext,
+        trade: &TradeEventData,
+        signature: &str,
+    ) {
+        let Some(position) = self.pnl(&trade.mint) else {
+            return;
+        };
+        let price = trade.price();
+        self.prices.insert(trade.mint.clone(), price);
+        let Some(threshold) = self.conditions.profit_percentage else {
+            return;
+        };
+        let (Some(profit), Some(entry_price)) =
+            (position.profit_percentage(price), position.entry_price())
+        else {
+            return;
+        };
+        let facts = ctx.trade_facts(trade, &self.conditions, Some(profit)).await;
+        let above = profit >= threshold && self.conditions.matches(&facts);
+        let was_above = self
+            .above
+            .insert(trade.mint.clone(), above)
+            .unwrap_or(false);
+        if !above || was_above {
+            return;
+        }
+        ctx.alert(
+            &trade.mint,
+            signature,
+            AlertKind::Profit {
+                profit_percentage: profit,
+                price,
+                entry_price,
+                holding: position.holding,
+            },
+        )
+        .await;
+    }
+}
+
+#[async_trait]
+impl Strategy for ProfitHoldingStrategy {
+    async fn init(&mut self, ctx: &StrategyContext) -> Result<()> {
+        let active = ctx
+            .manager
+            .get_spl_tokens(&ctx.address, &ctx.strategy_name, MONITOR_STATUS_ACTIVE)
+            .await?;
+        // restore the positions of an earlier run from the ledger
+        for token in active {
+            let lots = ctx
+                .manager
+                .get_position_lots(&ctx.address, &token.mint)
+                .await?;
+            if LedgerPnl::compute(&lots, self.config.accounting).holding == 0 {
+                continue;
+            }
+            info!(
+                "restored position: {}, lots: {}, address: {}",
+                token.mint,
+                lots.len(),
+                ctx.address
+            );
+            self.lots.insert(token.mint.clone(), lots);
+            ctx.watch(&token.mint);
+        }
+        // the positions already above the threshold don't alert again
+        if let Some(above) = ctx.take_checkpoint::<HashMap<String, bool>>().await? {
+            self.above = above
+                .into_iter()
+                .filter(|(mint, _)| self.lots.contains_key(mint))
+                .collect();
+        }
+        Ok(())
+    }
+
+    async fn on_event(&mut self, ctx: &StrategyContext, event: &StrategyEvent) -> Result<()> {
+        // the transaction fee is paid once, by the wallet when it signed
+        let mut network_fee = None;
+        for trade in &event.trades {
+            if trade.user == ctx.address {
+                let fee = match network_fee {
+                    Some(_) => 0,
+                    None => match ctx.solana_client.get_tx(&event.signature).await {
+                        Ok(meta) => meta.fee,
+                        Err(e) => {
+                            warn!(
+                                "get transaction fee error: {:?}, signature: {}",
+                                e, event.signature
+                            );
+                            0
+                        }
+                    },
+                };
+                network_fee = Some(fee);
+                self.deal_profit_holding(ctx, trade, &event.signature, fee)
+                    .await?;
+            }
+            self.mark_to_market(ctx, trade, &event.signature).await;
+        }
+        Ok(())
+    }
+
+    async fn on_tick(&mut self, ctx: &StrategyContext) -> Result<()> {
+        let interval = self.config.report_interval_secs as i64;
+        let now = ctx.now();
+        let last_report = *self.last_report.get_or_insert(now);
+        if interval == 0 || now - last_report < interval {
+            return Ok(());
+        }
+        self.last_report = Some(now);
+        for (mint, price) in &self.prices {
+            if let Some(position) = self.pnl(mint) {
+                info!(
+                    "position: {}, holding: {}, price: {:.10}, profit: {:?}, realized: {}, unrealized: {}, fees: {}, address: {}",
+                    mint,
+                    position.holding,
+                    price,
+                    position.profit_percentage(*price),
+                    position.realized_pnl,
+                    position.unrealized_pnl(*price),
+                    position.fees,
+                    ctx.address
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&mut self, ctx: &StrategyContext) -> Result<()> {
+        ctx.save_checkpoint(&self.above).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alert::alert_channel, test_support::MockSolana};
+
+    #[tokio::test]
+    async fn test_profit_holding_alert() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let address = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x";
+        let mint = "7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump";
+        mock.add_transaction_fixture("pump_buy_tx.json");
+        mock.push_logs_fixture(address, "pump_buy_logs.json");
+        // a 20 SOL buy of someone else pushes the price up
+        mock.push_logs_fixture(mint, "pump_other_buy_logs.json");
+
+        let rule = MonitorRule {
+            address: address.to_string(),
+            rule_type: PROFIT_HOLDING_STRATEGY.to_string(),
+            target: Default::default(),
+            conditions: MonitorCondition {
+                profit_percentage: Some(100.0),
+                ..Default::default()
+            },
+            config: Default::default(),
+            cooldown: None,
+        };
+        let (sender, mut receiver) = alert_channel();
+        let ctx = mock.context(sender).await?;
+        let manager = ctx.manager.clone();
+        tokio::spawn(async move { rule.run(&ctx).await });
+
+        let alert = receiver.recv().await.expect("no alert received");
+        assert_eq!(alert.mint, mint);
+        let AlertKind::Profit {
+            profit_percentage,
+            holding,
+            ..
+        } = alert.kind
+        else {
+            panic!("not a profit alert");
+        };
+        assert!(profit_percentage > 100.0);
+        assert_eq!(holding, 37809162736217);
+
+        let tokens = manager
+            .get_spl_tokens(address, PROFIT_HOLDING_STRATEGY, MONITOR_STATUS_ACTIVE)
+            .await?;
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].mint, mint);
+
+        let lots = manager.get_position_lots(address, mint).await?;
+        assert_eq!(lots.len(), 1);
+        assert_eq!(lots[0].network_fee, 5000);
+        assert_eq!(lots[0].pump_fee, 12539518);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_profit_holding_checkpoint() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let (sender, _receiver) = alert_channel();
+        let ctx = mock.context(sender).await?;
+        let address = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x";
+        let mint = "7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump";
+        let rule: MonitorRule = toml::from_str(&format!(
+            r#"
+            address = "{}"
+            rule_type = "ProfitHolding"
+            [conditions]
+            profit_percentage = 100.0
+            "#,
+            address
+        ))?;
+        // the open position of an earlier run
+        let trade = TradeEventData {
+            mint: mint.to_string(),
+            sol_amount: 1_000_000_000,
+            token_amount: 30_000_000_000_000,
+            is_buy: true,
+            user: address.to_string(),
+            timestamp: 1_734_616_564,
+            virtual_sol_reserves: 31_000_000_000,
+            virtual_token_reserves: 1_000_000_000_000_000,
+            real_sol_reserves: 1_000_000_000,
+            real_token_reserves: 700_000_000_000_000,
+        };
+        ctx.manager
+            .add_position_lot(&PositionLot::from_trade("sig", &trade, 5000))
+            .await?;
+        ctx.manager
+            .add_new_spl_token(mint, address, PROFIT_HOLDING_STRATEGY)
+            .await?;
+
+        let (events, _) = tokio::sync::mpsc::channel(1);
+        let sctx = StrategyContext::new(&rule, &ctx, events);
+        let mut strategy = ProfitHoldingStrategy::new(&rule, Default::default())?;
+        strategy.init(&sctx).await?;
+        strategy.above.insert(mint.to_string(), true);
+        strategy.above.insert("closed".to_string(), true);
+        strategy.shutdown(&sctx).await?;
+
+        // the next run doesn't alert the position above the threshold again
+        let mut restarted = ProfitHoldingStrategy::new(&rule, Default::default())?;
+        restarted.init(&sctx).await?;
+        assert_eq!(restarted.above, HashMap::from([(mint.to_string(), true)]));
+        assert!(sctx
+            .take_checkpoint::<HashMap<String, bool>>()
+            .await?
+            .is_none());
+        sctx.unwatch_all();
+        Ok(())
     }
 }
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+
+use super::{
+    buy::BuyStrategy, copy_trade::CopyTradeStrategy, momentum::MomentumStrategy,
+    paper_trade::PaperTradeStrategy, profit_holding::ProfitHoldingStrategy, sell::SellStrategy,
+    threshold::ThresholdStrategy, trailing_stop::TrailingStopStrategy, MonitorRule, Strategy,
+    BUY_STRATEGY, COPY_TRADE_STRATEGY, MOMENTUM_STRATEGY, PAPER_TRADE_STRATEGY,
+    PROFIT_HOLDING_STRATEGY, SELL_STRATEGY, THRESHOLD_STRATEGY, TRAILING_STOP_STRATEGY,
+};
+
+pub type StrategyFactory = fn(&MonitorRule) -> Result<Box<dyn Strategy>>;
+
+/// strategy factories keyed by the strategy name of the rule
+pub struct StrategyRegistry {
+    factories: HashMap<String, StrategyFactory>,
+}
+
+impl StrategyRegistry {
+    pub fn new() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, name: &str, factory: StrategyFactory) {
+        self.factories.insert(name.to_string(), factory);
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.factories.keys().map(|k| k.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    pub fn create(&self, rule: &MonitorRule) -> Result<Box<dyn Strategy>> {
+        let factory = self.factories.get(&rule.rule_type).ok_or_else(|| {
+            anyhow!(
+                "Unknown strategy: {}, available: {:?}",
+                rule.rule_type,
+                self.names()
+            )
+        })?;
+        factory(rule)
+    }
+}
+
+impl Default for StrategyRegistry {
+    /// the builtin strategies
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(BUY_STRATEGY, |rule| {
+            Ok(Box::new(BuyStrategy::new(rule, rule.strategy_config()?)?))
+        });
+        registry.register(SELL_STRATEGY, |rule| {
+            Ok(Box::new(SellStrategy::new(rule, rule.strategy_config()?)?))
+        });
+        registry.register(PROFIT_HOLDING_STRATEGY, |rule| {
+            Ok(Box::new(ProfitHoldingStrategy::new(
+                rule,
+                rule.strategy_config()?,
+            )?))
+        });
+        registry.register(MOMENTUM_STRATEGY, |rule| {
+            Ok(Box::new(MomentumStrategy::new(
+                rule,
+                rule.strategy_config()?,
+            )?))
+        });
+        registry.register(PAPER_TRADE_STRATEGY, |rule| {
+            Ok(Box::new(PaperTradeStrategy::new(
+                rule,
+                rule.strategy_config()?,
+            )?))
+        });
+        registry.register(COPY_TRADE_STRATEGY, |rule| {
+            Ok(Box::new(CopyTradeStrategy::new(
+                rule,
+                rule.strategy_config()?,
+            )?))
+        });
+        registry.register(THRESHOLD_STRATEGY, |rule| {
+            Ok(Box::new(ThresholdStrategy::new(
+                rule,
+                rule.strategy_config()?,
+            )?))
+        });
+        registry.register(TRAILING_STOP_STRATEGY, |rule| {
+            Ok(Box::new(TrailingStopStrategy::new(
+                rule,
+                rule.strategy_config()?,
+            )?))
+        });
+        registry
+    }
+}
+
+impl MonitorRule {
+    /// deserialize the `config` table of the rule into the strategy's typed config
+    pub fn strategy_config<T: DeserializeOwned>(&self) -> Result<T> {
+        toml::Value::Table(self.config.clone())
+            .try_into()
+            .map_err(|e| anyhow!("Invalid config of strategy {}: {}", self.rule_type, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_create() {
+        let registry = StrategyRegistry::default();
+        assert_eq!(
+            registry.names(),
+            vec![
+                "Buy",
+                "CopyTrade",
+                "Momentum",
+                "PaperTrade",
+                "ProfitHolding",
+                "Sell",
+                "Threshold",
+                "TrailingStop"
+            ]
+        );
+
+        let rule: MonitorRule = toml::from_str(
+            r#"
+            address = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x"
+            rule_type = "Buy"
+            [conditions]
+            price_below = 0.001
+            [config]
+            min_sol_amount = 0.5
+            "#,
+        )
+        .unwrap();
+        assert!(registry.create(&rule).is_ok());
+
+        let mut unknown = rule.clone();
+        unknown.rule_type = "Unknown".to_string();
+        assert!(registry.create(&unknown).is_err());
+
+        let mut invalid = rule.clone();
+        invalid
+            .config
+            .insert("min_sol".to_string(), toml::Value::Float(0.5));
+        assert!(registry.create(&invalid).is_err());
+
+        // a sell condition on a buy rule
+        let mut unsupported = rule.clone();
+        unsupported.conditions.is_first_sell = Some(true);
+        assert!(registry.create(&unsupported).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use super::{
+    position::PositionBook, MonitorCondition, MonitorRule, RuleTarget, Strategy, StrategyContext,
+    StrategyEvent,
+};
+use crate::alert::AlertKind;
+
+/// strategy name stored in `spl_token`
+pub const SELL_STRATEGY: &str = "Sell";
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SellConfig {
+    // fetch the balance before the sell of positions opened before the monitor started
+    #[serde(default = "default_resolve_unknown_positions")]
+    pub resolve_unknown_positions: bool,
+}
+
+fn default_resolve_unknown_positions() -> bool {
+    true
+}
+
+/// how a sell changed the wallet's position
+#[derive(Debug, Clone, PartialEq)]
+pub struct SellInfo {
+    pub first_sell: bool,    // first sell of a position the monitor saw opened
+    pub partial: bool,       // the wallet still holds some tokens after the sell
+    pub holding_before: f64, // holding percentage before the sell
+    pub holding_after: f64,  // holding percentage after the sell
+}
+
+impl MonitorCondition {
+    /// `holding_percentage` fires when the holding drops below it, not on every sell under it
+    pub fn sell_matches(&self, sell: &SellInfo) -> bool {
+        self.is_first_sell.is_none_or(|f| f == sell.first_sell)
+            && self.partial_sell.is_none_or(|p| p == sell.partial)
+            && self
+                .holding_percentage
+                .is_none_or(|p| sell.holding_before >= p && sell.holding_after < p)
+    }
+}
+
+/// alert on the sells of the monitored wallet, or of every wallet in the monitored mint, per (wallet, mint) position
+pub struct SellStrategy {
+    conditions: MonitorCondition,
+    config: SellConfig,
+    book: PositionBook,
+}
+
+impl SellStrategy {
+    pub fn new(rule: &MonitorRule, config: SellConfig) -> Result<Self> {
+        rule.check_target(SELL_STRATEGY, &[RuleTarget::Wallet, RuleTarget::Mint])?;
+        rule.conditions.check(
+            SELL_STRATEGY,
+            &["is_first_sell", "partial_sell", "holding_percentage"],
+        )?;
+        Ok(Self {
+            conditions: rule.conditions.clone(),
+            config,
+            book: PositionBook::default(),
+        })
+    }
+}
+
+#[async_trait]
+impl Strategy for SellStrategy {
+    async fn on_event(&mut self, ctx: &StrategyContext, event: &StrategyEvent) -> Result<()> {
+        for trade in &event.trades {
+            if !ctx.is_target(trade) {
+                continue;
+            }
+            if trade.is_buy {
+                self.book.record(trade);
+                continue;
+            }
+
+            // the position was opened before the monitor started, start from the balance before the sell
+            if self.book.get(&trade.user, &trade.mint).is_none()
+                && self.config.resolve_unknown_positions
+            {
+                match ctx
+                    .solana_client
+                    .get_token_balances(&event.signature, &trade.user, &trade.mint)
+                    .await
+                {
+                    Ok((pre, _)) => self.book.seed(&trade.user, &trade.mint, pre),
+                    Err(e) => warn!(
+                        "get token balances error: {:?}, signature: {}",
+                        e, event.signature
+                    ),
+                }
+            }
+            let before = self.book.get(&trade.user, &trade.mint).cloned();
+            let holding_before = before
+                .as_ref()
+                .map(|p| p.holding_percentage())
+                .unwrap_or_default();
+            let pnl = before.and_then(|p| p.profit_percentage(trade.price()));
+            let after = self.book.record(trade);
+            let sell = SellInfo {
+                first_sell: after.opened && after.sell_count == 1,
+                partial: after.holding() > 0,
+                holding_before,
+                holding_after: after.holding_percentage(),
+            };
+            let facts = ctx.trade_facts(trade, &self.conditions, pnl).await;
+            if !self.conditions.sell_matches(&sell) || !self.conditions.matches(&facts) {
+                debug!(
+                    "sell {:?} {:?} not match, mint: {}, address: {}",
+                    sell, facts, trade.mint, ctx.address
+                );
+                continue;
+            }
+            ctx.alert_trade(
+                trade,
+                &event.signature,
+                AlertKind::Sell {
+                    sol_amount: trade.sol_amount,
+                    token_amount: trade.token_amount,
+                    price: trade.price(),
+                    first_sell: sell.first_sell,
+                    partial: sell.partial,
+                    holding_percentage: sell.holding_after,
+                },
+            )
+            .await;
+        }
+        // the conditions are evaluated against pump.fun trades, only an unconditional rule alerts on swaps
+        if self.conditions == MonitorCondition::default() {
+            ctx.alert_swaps(event, false, 0).await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alert::alert_channel, test_support::MockSolana};
+
+    #[tokio::test]
+    async fn test_sell_alert() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let address = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x";
+        mock.push_logs_fixture(address, "pump_buy_logs.json");
+        mock.push_logs_fixture(address, "pump_sell_half_logs.json");
+        mock.push_logs_fixture(address, "pump_sell_rest_logs.json");
+
+        // the first sell is partial and drops the holding below 60%, the second is a full exit
+        let rule = MonitorRule {
+            address: address.to_string(),
+            rule_type: SELL_STRATEGY.to_string(),
+            target: Default::default(),
+            conditions: MonitorCondition {
+                holding_percentage: Some(60.0),
+                ..Default::default()
+            },
+            config: Default::default(),
+            cooldown: None,
+        };
+        let (sender, mut receiver) = alert_channel();
+        let ctx = mock.context(sender).await?;
+        tokio::spawn(async move { rule.run(&ctx).await });
+
+        let alert = receiver.recv().await.expect("no alert received");
+        let AlertKind::Sell {
+            token_amount,
+            first_sell,
+            partial,
+            holding_percentage,
+            ..
+        } = alert.kind
+        else {
+            panic!("not a sell alert");
+        };
+        assert_eq!(token_amount, 18904581368108);
+        assert!(first_sell);
+        assert!(partial);
+        assert!((holding_percentage - 50.0).abs() < 1e-6);
+
+        let full_exit = MonitorCondition {
+            partial_sell: Some(false),
+            is_first_sell: Some(false),
+            ..Default::default()
+        };
+        assert!(full_exit.sell_matches(&SellInfo {
+            first_sell: false,
+            partial: false,
+            holding_before: 50.0,
+            holding_after: 0.0,
+        }));
+        Ok(())
+    }
+}
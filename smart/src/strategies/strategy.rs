@@ -0,0 +1,421 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use solana_client::rpc_response::{Response, RpcLogsResponse};
+use tokio::{sync::mpsc, task::JoinHandle};
+use tracing::warn;
+
+use super::{
+    spawn_log_subscribe, Field, MonitorCondition, MonitorContext, MonitorRule, RuleTarget,
+    TradeFacts,
+};
+use crate::{
+    alert::{send_alert, Alert, AlertKind, AlertSender},
+    cooldown::{AlertThrottle, ThrottleState},
+    execution::Executor,
+    models::{CheckpointKey, ModelsManager},
+    sniper::sniper_report,
+    sol_client::{
+        client::SolanaMonitor,
+        jupiter::{is_jupiter_logs, JupiterSwap},
+        parse_complete_events, parse_create_events, parse_trade_events, CompleteEventData,
+        CreateEventData, TradeEventData,
+    },
+};
+
+// the parts of a rule's checkpoint
+const STRATEGY_CHECKPOINT: &str = "strategy";
+const COOLDOWN_CHECKPOINT: &str = "cooldown";
+
+/// a transaction seen by a monitor, with its pump.fun events decoded
+#[derive(Debug, Clone, Default)]
+pub struct StrategyEvent {
+    pub signature: String,
+    pub slot: u64,
+    pub logs: Vec<String>,
+    pub trades: Vec<TradeEventData>,
+    pub creates: Vec<CreateEventData>,
+    pub completes: Vec<CompleteEventData>,
+    pub swaps: Vec<JupiterSwap>, // jupiter swaps of the monitored wallet, see `attach_swaps`
+}
+
+impl From<Response<RpcLogsResponse>> for StrategyEvent {
+    fn from(log: Response<RpcLogsResponse>) -> Self {
+        let trades = parse_trade_events(&log.value.logs)
+            .into_iter()
+            .map(|e| e.data)
+            .collect();
+        let creates = parse_create_events(&log.value.logs);
+        let completes = parse_complete_events(&log.value.logs);
+        Self {
+            signature: log.value.signature,
+            slot: log.context.slot,
+            logs: log.value.logs,
+            trades,
+            creates,
+            completes,
+            swaps: vec![],
+        }
+    }
+}
+
+/// strategy logic of a monitor rule, driven by the monitor's event loop
+#[async_trait]
+pub trait Strategy: Send {
+    /// called once before the first event
+    async fn init(&mut self, _ctx: &StrategyContext) -> Result<()> {
+        Ok(())
+    }
+
+    /// called for every transaction mentioning a watched address
+    async fn on_event(&mut self, ctx: &StrategyContext, event: &StrategyEvent) -> Result<()>;
+
+    /// called every `TICK_INTERVAL`
+    async fn on_tick(&mut self, _ctx: &StrategyContext) -> Result<()> {
+        Ok(())
+    }
+
+    /// called once when the monitor stops
+    async fn shutdown(&mut self, _ctx: &StrategyContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// what a strategy can do outside of itself: alert, query, and watch more addresses
+pub struct StrategyContext {
+    pub address: String,       // monitored address
+    pub strategy_name: String, // registry key, also stored in spl_token
+    pub target: RuleTarget,
+    pub manager: Arc<ModelsManager>,
+    pub solana_client: SolanaMonitor,
+    alerts: AlertSender,
+    monitor: MonitorContext,
+    events: mpsc::Sender<Response<RpcLogsResponse>>,
+    subscriptions: Mutex<HashMap<String, Option<JoinHandle<()>>>>, // None when replaying
+    created_at: Mutex<HashMap<String, Option<i64>>>,               // mint -> creation time
+    throttle: Option<Mutex<AlertThrottle>>,                        // the rule's alert cooldown
+    clock: Option<Mutex<i64>>, // simulated time of a replay, unix seconds
+}
+
+impl StrategyContext {
+    pub fn new(
+        rule: &MonitorRule,
+        monitor: &MonitorContext,
+        events: mpsc::Sender<Response<RpcLogsResponse>>,
+    ) -> Self {
+        Self {
+            address: rule.address.clone(),
+            strategy_name: rule.rule_type.clone(),
+            target: rule.target,
+            manager: monitor.manager.clone(),
+            solana_client: SolanaMonitor::new(&monitor.wss_url, &monitor.rpc_url),
+            alerts: monitor.alerts.clone(),
+            monitor: monitor.clone(),
+            events,
+            subscriptions: Mutex::new(HashMap::new()),
+            created_at: Mutex::new(HashMap::new()),
+            throttle: rule
+                .cooldown
+                .clone()
+                .map(|c| Mutex::new(AlertThrottle::new(c))),
+            clock: None,
+        }
+    }
+
+    /// a context replaying history: nothing is subscribed and the time is set by `set_now`
+    pub fn replay(rule: &MonitorRule, monitor: &MonitorContext, now: i64) -> Self {
+        let (events, _) = mpsc::channel(1);
+        Self {
+            clock: Some(Mutex::new(now)),
+            ..Self::new(rule, monitor, events)
+        }
+    }
+
+    /// unix seconds, simulated when replaying
+    pub fn now(&self) -> i64 {
+        match &self.clock {
+            Some(clock) => *clock.lock().unwrap(),
+            None => chrono::Utc::now().timestamp(),
+        }
+    }
+
+    /// the copy trade executor of the daemon, None unless execution is enabled
+    pub fn executor(&self) -> Option<&Arc<Executor>> {
+        self.monitor.executor.as_ref()
+    }
+
+    pub fn set_now(&self, now: i64) {
+        if let Some(clock) = &self.clock {
+            *clock.lock().unwrap() = now;
+        }
+    }
+
+    /// whether the trade is one of the rule's: made by the wallet, or in the mint
+    pub fn is_target(&self, trade: &TradeEventData) -> bool {
+        match self.target {
+            RuleTarget::Wallet => trade.user == self.address,
+            RuleTarget::Mint => trade.mint == self.address,
+        }
+    }
+
+    /// the facts `conditions` is evaluated against, the lookups only run for the fields it uses
+    pub async fn trade_facts(
+        &self,
+        trade: &TradeEventData,
+        conditions: &MonitorCondition,
+        wallet_pnl: Option<f64>,
+    ) -> TradeFacts {
+        let mut facts = TradeFacts::from_trade(trade);
+        facts.wallet_pnl = wallet_pnl;
+        if conditions.uses(Field::TokenAge) {
+            facts.token_age = self
+                .token_created_at(&trade.mint)
+                .await
+                .map(|created_at| (trade.timestamp - created_at) as f64);
+        }
+        if conditions.uses(Field::SnipedSupply) {
+            match sniper_report(&self.manager, &trade.mint).await {
+                Ok(report) => facts.sniped_supply = report.map(|r| r.sniped_supply()),
+                Err(e) => warn!("sniper report error: {:?}, mint: {}", e, trade.mint),
+            }
+        }
+        // a mint rule compares the scores of whoever traded
+        let wallet = match self.target {
+            RuleTarget::Wallet => &self.address,
+            RuleTarget::Mint => &trade.user,
+        };
+        for window_secs in conditions.score_windows() {
+            match self
+                .manager
+                .get_wallet_score(wallet, window_secs as i64)
+                .await
+            {
+                Ok(Some(score)) => {
+                    facts.scores.insert(window_secs, score);
+                }
+                Ok(None) => {}
+                Err(e) => warn!("get wallet score error: {:?}, address: {}", e, wallet),
+            }
+        }
+        facts
+    }
+
+    async fn token_created_at(&self, mint: &str) -> Option<i64> {
+        if let Some(created_at) = self.created_at.lock().unwrap().get(mint) {
+            return *created_at;
+        }
+        let created_at = match self.solana_client.get_token_created_at(mint).await {
+            Ok(created_at) => created_at,
+            Err(e) => {
+                warn!("get token created at error: {:?}, mint: {}", e, mint);
+                return None;
+            }
+        };
+        self.created_at
+            .lock()
+            .unwrap()
+            .insert(mint.to_string(), created_at);
+        created_at
+    }
+
+    /// send the alert unless the rule's cooldown holds it back
+    async fn send(&self, mut alert: Alert) {
+        if let Some(throttle) = &self.throttle {
+            if !throttle.lock().unwrap().check(&mut alert, self.now()) {
+                return;
+            }
+        }
+        send_alert(&self.alerts, alert).await;
+    }
+
+    /// alert on a trade of the rule, the trader is the alert's address
+    pub async fn alert_trade(&self, trade: &TradeEventData, signature: &str, kind: AlertKind) {
+        self.send(Alert {
+            address: trade.user.clone(),
+            rule: self.strategy_name.clone(),
+            mint: trade.mint.clone(),
+            signature: signature.to_string(),
+            kind,
+            suppressed: 0,
+        })
+        .await;
+    }
+
+    pub async fn alert(&self, mint: &str, signature: &str, kind: AlertKind) {
+        self.send(Alert {
+            address: self.address.clone(),
+            rule: self.strategy_name.clone(),
+            mint: mint.to_string(),
+            signature: signature.to_string(),
+            kind,
+            suppressed: 0,
+        })
+        .await;
+    }
+
+    /// the jupiter swap of a wallet monitor's transaction has no pump.fun event in the logs,
+    /// fetch and decode it from the transaction
+    pub async fn attach_swaps(&self, event: &mut StrategyEvent) {
+        if self.target != RuleTarget::Wallet || !is_jupiter_logs(&event.logs) {
+            return;
+        }
+        match self.solana_client.get_jupiter_swap(&event.signature).await {
+            Ok(Some(swap)) if swap.user == self.address => event.swaps.push(swap),
+            Ok(_) => {}
+            Err(e) => warn!(
+                "get jupiter swap error: {:?}, signature: {}",
+                e, event.signature
+            ),
+        }
+    }
+
+    /// alert on the wallet's jupiter swaps buying (`is_buy`) or selling a token for at least `min_sol` lamports
+    pub async fn alert_swaps(&self, event: &StrategyEvent, is_buy: bool, min_sol: u64) {
+        for swap in &event.swaps {
+            let (mint, sol) = match is_buy {
+                true if swap.is_buy() => (&swap.output_mint, swap.input_amount),
+                false if swap.is_sell() => (&swap.input_mint, swap.output_amount),
+                _ => continue,
+            };
+            if sol < min_sol {
+                continue;
+            }
+            self.alert(
+                mint,
+                &event.signature,
+                AlertKind::Swap {
+                    input_mint: swap.input_mint.clone(),
+                    input_amount: swap.input_amount,
+                    output_mint: swap.output_mint.clone(),
+                    output_amount: swap.output_amount,
+                    amms: swap.amms.clone(),
+                },
+            )
+            .await;
+        }
+    }
+
+    /// also receive the transactions mentioning `address`, e.g. every trade of a mint
+    pub fn watch(&self, address: &str) {
+        let replay = self.clock.is_some();
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .entry(address.to_string())
+            .or_insert_with_key(|address| {
+                (!replay).then(|| spawn_log_subscribe(address, &self.monitor, self.events.clone()))
+            });
+    }
+
+    pub fn is_watching(&self, address: &str) -> bool {
+        self.subscriptions.lock().unwrap().contains_key(address)
+    }
+
+    pub fn unwatch(&self, address: &str) {
+        if let Some(Some(subscription)) = self.subscriptions.lock().unwrap().remove(address) {
+            subscription.abort();
+        }
+    }
+
+    pub fn unwatch_all(&self) {
+        for subscription in self
+            .subscriptions
+            .lock()
+            .unwrap()
+            .drain()
+            .filter_map(|(_, s)| s)
+        {
+            subscription.abort();
+        }
+    }
+
+    fn checkpoint_key<'a>(&'a self, name: &'a str) -> CheckpointKey<'a> {
+        CheckpointKey {
+            address: &self.address,
+            rule_type: &self.strategy_name,
+            target: self.target.as_str(),
+            name,
+        }
+    }
+
+    /// save the strategy's state for the next run of the rule, a replay keeps nothing
+    pub async fn save_checkpoint<T: Serialize>(&self, state: &T) -> Result<()> {
+        if self.clock.is_some() {
+            return Ok(());
+        }
+        self.manager
+            .save_checkpoint(&self.checkpoint_key(STRATEGY_CHECKPOINT), state)
+            .await
+    }
+
+    /// the state the last run of the rule saved, None when replaying
+    pub async fn take_checkpoint<T: DeserializeOwned>(&self) -> Result<Option<T>> {
+        if self.clock.is_some() {
+            return Ok(None);
+        }
+        self.manager
+            .take_checkpoint(&self.checkpoint_key(STRATEGY_CHECKPOINT))
+            .await
+    }
+
+    /// keep the rule's cooldowns for its next run
+    pub async fn save_throttle(&self) -> Result<()> {
+        let (Some(throttle), None) = (&self.throttle, &self.clock) else {
+            return Ok(());
+        };
+        let state = throttle.lock().unwrap().state();
+        self.manager
+            .save_checkpoint(&self.checkpoint_key(COOLDOWN_CHECKPOINT), &state)
+            .await
+    }
+
+    pub async fn restore_throttle(&self) -> Result<()> {
+        let (Some(throttle), None) = (&self.throttle, &self.clock) else {
+            return Ok(());
+        };
+        let state = self
+            .manager
+            .take_checkpoint::<ThrottleState>(&self.checkpoint_key(COOLDOWN_CHECKPOINT))
+            .await?;
+        if let Some(state) = state {
+            throttle.lock().unwrap().restore(state);
+        }
+        Ok(())
+    }
+}
+
+/// signatures already handled, a transaction mentioning several watched addresses arrives once per subscription
+pub struct RecentSignatures {
+    capacity: usize,
+    order: VecDeque<String>,
+    seen: HashSet<String>,
+}
+
+impl RecentSignatures {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            seen: HashSet::with_capacity(capacity),
+        }
+    }
+
+    /// returns false if the signature was already seen
+    pub fn insert(&mut self, signature: &str) -> bool {
+        if !self.seen.insert(signature.to_string()) {
+            return false;
+        }
+        self.order.push_back(signature.to_string());
+        if self.order.len() > self.capacity {
+            if let Some(old) = self.order.pop_front() {
+                self.seen.remove(&old);
+            }
+        }
+        true
+    }
+}
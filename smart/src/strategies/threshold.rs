@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::debug;
+
+use super::{MonitorCondition, MonitorRule, RuleTarget, Strategy, StrategyContext, StrategyEvent};
+use crate::alert::AlertKind;
+
+/// strategy name of the threshold rules
+pub const THRESHOLD_STRATEGY: &str = "Threshold";
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThresholdConfig {
+    // alert again once the conditions stopped holding and hold again
+    #[serde(default = "default_rearm")]
+    pub rearm: bool,
+}
+
+fn default_rearm() -> bool {
+    true
+}
+
+impl Default for ThresholdConfig {
+    fn default() -> Self {
+        Self {
+            rearm: default_rearm(),
+        }
+    }
+}
+
+/// alert when the conditions start to hold for a mint, e.g. its market cap passes a value
+pub struct ThresholdStrategy {
+    conditions: MonitorCondition,
+    config: ThresholdConfig,
+    crossed: HashSet<String>, // mints the conditions hold for
+}
+
+impl ThresholdStrategy {
+    pub fn new(rule: &MonitorRule, config: ThresholdConfig) -> Result<Self> {
+        rule.check_target(THRESHOLD_STRATEGY, &[RuleTarget::Wallet, RuleTarget::Mint])?;
+        rule.conditions.check(THRESHOLD_STRATEGY, &[])?;
+        let c = &rule.conditions;
+        if c.when.is_none() && c.price_above.is_none() && c.price_below.is_none() {
+            return Err(anyhow!(
+                "Strategy {} needs a price bound or a when condition",
+                THRESHOLD_STRATEGY
+            ));
+        }
+        Ok(Self {
+            conditions: rule.conditions.clone(),
+            config,
+            crossed: HashSet::new(),
+        })
+    }
+}
+
+#[async_trait]
+impl Strategy for ThresholdStrategy {
+    async fn on_event(&mut self, ctx: &StrategyContext, event: &StrategyEvent) -> Result<()> {
+        for trade in &event.trades {
+            if !ctx.is_target(trade) {
+                continue;
+            }
+            let facts = ctx.trade_facts(trade, &self.conditions, None).await;
+            if !self.conditions.matches(&facts) {
+                if self.config.rearm {
+                    self.crossed.remove(&trade.mint);
+                }
+                continue;
+            }
+            // only the trade crossing the threshold alerts
+            if !self.crossed.insert(trade.mint.clone()) {
+                debug!(
+                    "{} still above the threshold, address: {}",
+                    trade.mint, ctx.address
+                );
+                continue;
+            }
+            ctx.alert(
+                &trade.mint,
+                &event.signature,
+                AlertKind::Threshold {
+                    price: facts.price,
+                    market_cap: facts.market_cap,
+                },
+            )
+            .await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alert::alert_channel, strategies::StrategyRegistry, test_support::MockSolana};
+
+    #[tokio::test]
+    async fn test_mint_threshold_alert() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let mint = "7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump";
+        // the market cap passes 50 SOL on the other buy only
+        mock.push_logs_fixture(mint, "pump_buy_logs.json");
+        mock.push_logs_fixture(mint, "pump_other_buy_logs.json");
+        mock.push_logs_fixture(mint, "pump_sell_half_logs.json");
+
+        let rule: MonitorRule = toml::from_str(&format!(
+            r#"
+            address = "{}"
+            rule_type = "Threshold"
+            target = "mint"
+            [conditions.when]
+            field = "market_cap"
+            op = ">"
+            value = 50.0
+            "#,
+            mint
+        ))?;
+        assert_eq!(rule.target, RuleTarget::Mint);
+        // a wallet only strategy
+        let holding = MonitorRule {
+            rule_type: "ProfitHolding".to_string(),
+            ..rule.clone()
+        };
+        let err = StrategyRegistry::default()
+            .create(&holding)
+            .err()
+            .expect("mint target accepted");
+        assert!(err.to_string().contains("Target Mint"));
+
+        let (sender, mut receiver) = alert_channel();
+        let ctx = mock.context(sender).await?;
+        tokio::spawn(async move { rule.run(&ctx).await });
+
+        let alert = receiver.recv().await.expect("no alert received");
+        assert_eq!(alert.rule, THRESHOLD_STRATEGY);
+        assert_eq!(alert.mint, mint);
+        let AlertKind::Threshold { market_cap, .. } = alert.kind else {
+            panic!("not a threshold alert");
+        };
+        assert!(market_cap > 50.0);
+        assert_eq!(alert.signature, "5XzuoTPJZmK77ZN3DsSnCntfiXrRUgQcSm752WiavRKV3QDRxRjBuSQ9mfhAas9xeCJZhGVE8Qv6xDhNcxATd2z5");
+        Ok(())
+    }
+}
@@ -0,0 +1,396 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use super::{
+    MonitorCondition, MonitorRule, PositionBook, RuleTarget, Strategy, StrategyContext,
+    StrategyEvent,
+};
+use crate::{
+    alert::{AlertKind, ExitReason},
+    models::{PositionWatermark, MONITOR_STATUS_CLOSED},
+    sol_client::TradeEventData,
+};
+
+/// strategy name stored in `spl_token` and `position_watermark`
+pub const TRAILING_STOP_STRATEGY: &str = "TrailingStop";
+
+/// exit rules in percent, at least one is set
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrailingStopConfig {
+    pub take_profit: Option<f64>,   // price above the entry by this much
+    pub stop_loss: Option<f64>,     // price below the entry by this much
+    pub trailing_stop: Option<f64>, // price below the high since the entry by this much
+}
+
+impl TrailingStopConfig {
+    pub(crate) fn validate(&self) -> Result<()> {
+        let rules = [self.take_profit, self.stop_loss, self.trailing_stop];
+        if rules.iter().all(|r| r.is_none()) {
+            return Err(anyhow!(
+                "take_profit, stop_loss or trailing_stop is required"
+            ));
+        }
+        if rules.iter().flatten().any(|r| !r.is_finite() || *r <= 0.0) {
+            return Err(anyhow!("exit rules are positive percentages"));
+        }
+        if self.stop_loss.is_some_and(|r| r >= 100.0)
+            || self.trailing_stop.is_some_and(|r| r >= 100.0)
+        {
+            return Err(anyhow!("stop_loss and trailing_stop are below 100"));
+        }
+        Ok(())
+    }
+
+    /// the rules `price` triggers, marking them fired. a new high rearms the trailing stop.
+    pub fn evaluate(&self, w: &mut PositionWatermark, price: f64) -> Vec<ExitReason> {
+        if price > w.high_price {
+            w.high_price = price;
+            w.trailing_stop_fired = false;
+        }
+        let mut reasons = Vec::new();
+        if let Some(p) = self.take_profit {
+            if !w.take_profit_fired && price >= w.entry_price * (1.0 + p / 100.0) {
+                w.take_profit_fired = true;
+                reasons.push(ExitReason::TakeProfit);
+            }
+        }
+        if let Some(p) = self.stop_loss {
+            if !w.stop_loss_fired && price <= w.entry_price * (1.0 - p / 100.0) {
+                w.stop_loss_fired = true;
+                reasons.push(ExitReason::StopLoss);
+            }
+        }
+        if let Some(p) = self.trailing_stop {
+            if !w.trailing_stop_fired && price <= w.high_price * (1.0 - p / 100.0) {
+                w.trailing_stop_fired = true;
+                reasons.push(ExitReason::TrailingStop);
+            }
+        }
+        reasons
+    }
+}
+
+/// follow the wallet into its tokens and alert on their price against the exit rules
+pub struct TrailingStopStrategy {
+    conditions: MonitorCondition,
+    config: TrailingStopConfig,
+    book: PositionBook,
+    watermarks: HashMap<String, PositionWatermark>, // mint -> state of the followed position
+    unsaved: HashSet<String>, // mints whose new high is only in memory, saved on shutdown
+}
+
+impl TrailingStopStrategy {
+    pub fn new(rule: &MonitorRule, config: TrailingStopConfig) -> Result<Self> {
+        rule.check_target(TRAILING_STOP_STRATEGY, &[RuleTarget::Wallet])?;
+        rule.conditions.check(TRAILING_STOP_STRATEGY, &[])?;
+        config.validate()?;
+        Ok(Self {
+            conditions: rule.conditions.clone(),
+            config,
+            book: PositionBook::default(),
+            watermarks: HashMap::new(),
+            unsaved: HashSet::new(),
+        })
+    }
+
+    /// follow a buy of the wallet, or stop following once it sold everything
+    async fn follow(
+        &mut self,
+        ctx: &StrategyContext,
+        trade: &TradeEventData,
+        signature: &str,
+    ) -> Result<()> {
+        let known = self.book.get(&trade.user, &trade.mint).is_some();
+        let position = self.book.record(trade);
+        if trade.is_buy {
+            let entry_price = position.entry_price().unwrap_or_else(|| trade.price());
+            let w = self
+                .watermarks
+                .entry(trade.mint.clone())
+                .or_insert_with(|| PositionWatermark {
+                    wallet: ctx.address.clone(),
+                    mint: trade.mint.clone(),
+                    strategy_name: ctx.strategy_name.clone(),
+                    high_price: trade.price(),
+                    ..Default::default()
+                });
+            w.entry_price = entry_price;
+            ctx.manager.upsert_position_watermark(w).await?;
+            self.unsaved.remove(&trade.mint);
+            ctx.manager
+                .add_new_spl_token(&trade.mint, &ctx.address, &ctx.strategy_name)
+                .await?;
+            ctx.watch(&trade.mint);
+            return Ok(());
+        }
+
+        if !self.watermarks.contains_key(&trade.mint) {
+            return Ok(());
+        }
+        // a position restored from the database has no trades in the book
+        let closed = if known {
+            position.holding() == 0
+        } else {
+            match ctx
+                .solana_client
+                .get_token_balances(signature, &trade.user, &trade.mint)
+                .await
+            {
+                Ok((_, post)) => post == 0,
+                Err(e) => {
+                    warn!(
+                        "get token balances error: {:?}, signature: {}",
+                        e, signature
+                    );
+                    false
+                }
+            }
+        };
+        if closed {
+            info!("stop following {}, address: {}", trade.mint, ctx.address);
+            self.watermarks.remove(&trade.mint);
+            self.unsaved.remove(&trade.mint);
+            ctx.manager
+                .delete_position_watermark(&ctx.address, &trade.mint, &ctx.strategy_name)
+                .await?;
+            ctx.manager
+                .update_spl_token_status(
+                    &trade.mint,
+                    &ctx.address,
+                    &ctx.strategy_name,
+                    MONITOR_STATUS_CLOSED,
+                )
+                .await?;
+            ctx.unwatch(&trade.mint);
+        }
+        Ok(())
+    }
+
+    async fn check_exit(
+        &mut self,
+        ctx: &StrategyContext,
+        trade: &TradeEventData,
+        signature: &str,
+    ) -> Result<()> {
+        let Some(w) = self.watermarks.get_mut(&trade.mint) else {
+            return Ok(());
+        };
+        let price = trade.price();
+        let before = w.clone();
+        let reasons = self.config.evaluate(w, price);
+        if *w != before {
+            // a new high alone waits for the shutdown, a fired rule is saved before it alerts again
+            let new_high = PositionWatermark {
+                high_price: w.high_price,
+                ..before
+            };
+            if *w == new_high {
+                self.unsaved.insert(trade.mint.clone());
+            } else {
+                ctx.manager.upsert_position_watermark(w).await?;
+                self.unsaved.remove(&trade.mint);
+            }
+        }
+        if reasons.is_empty() {
+            return Ok(());
+        }
+        let w = w.clone();
+        let change = (price - w.entry_price) / w.entry_price * 100.0;
+        let facts = ctx.trade_facts(trade, &self.conditions, Some(change)).await;
+        if !self.conditions.matches(&facts) {
+            return Ok(());
+        }
+        for reason in reasons {
+            ctx.alert(
+                &trade.mint,
+                signature,
+                AlertKind::Exit {
+                    reason,
+                    price,
+                    entry_price: w.entry_price,
+                    high_price: w.high_price,
+                    change_percentage: change,
+                },
+            )
+            .await;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Strategy for TrailingStopStrategy {
+    async fn init(&mut self, ctx: &StrategyContext) -> Result<()> {
+        for w in ctx
+            .manager
+            .get_position_watermarks(&ctx.address, &ctx.strategy_name)
+            .await?
+        {
+            info!(
+                "following {} from an earlier run, high: {:.10}, address: {}",
+                w.mint, w.high_price, ctx.address
+            );
+            ctx.watch(&w.mint);
+            self.watermarks.insert(w.mint.clone(), w);
+        }
+        Ok(())
+    }
+
+    async fn on_event(&mut self, ctx: &StrategyContext, event: &StrategyEvent) -> Result<()> {
+        for trade in &event.trades {
+            if trade.user == ctx.address {
+                self.follow(ctx, trade, &event.signature).await?;
+            }
+            self.check_exit(ctx, trade, &event.signature).await?;
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&mut self, ctx: &StrategyContext) -> Result<()> {
+        for mint in self.unsaved.drain() {
+            if let Some(w) = self.watermarks.get(&mint) {
+                ctx.manager.upsert_position_watermark(w).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alert::alert_channel, test_support::MockSolana};
+
+    #[tokio::test]
+    async fn test_trailing_stop_alert() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let address = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x";
+        let mint = "7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump";
+        mock.push_logs_fixture(address, "pump_buy_logs.json");
+        // a 20 SOL buy of someone else more than doubles the price
+        mock.push_logs_fixture(mint, "pump_other_buy_logs.json");
+
+        let rule: MonitorRule = toml::from_str(&format!(
+            r#"
+            address = "{}"
+            rule_type = "TrailingStop"
+            [config]
+            take_profit = 100.0
+            stop_loss = 50.0
+            trailing_stop = 30.0
+            "#,
+            address
+        ))?;
+        let (sender, mut receiver) = alert_channel();
+        let ctx = mock.context(sender).await?;
+        let manager = ctx.manager.clone();
+        tokio::spawn(async move { rule.run(&ctx).await });
+
+        let alert = receiver.recv().await.expect("no alert received");
+        let AlertKind::Exit {
+            reason, high_price, ..
+        } = alert.kind
+        else {
+            panic!("not an exit alert");
+        };
+        assert_eq!(reason, ExitReason::TakeProfit);
+        assert!((high_price - 8.8277e-8).abs() < 1e-11);
+
+        // the wallet sells half, the price falls back near the entry
+        mock.push_logs_fixture(address, "pump_sell_half_logs.json");
+        let alert = receiver.recv().await.expect("no alert received");
+        let AlertKind::Exit { reason, .. } = alert.kind else {
+            panic!("not an exit alert");
+        };
+        assert_eq!(reason, ExitReason::TrailingStop);
+
+        let watermarks = manager
+            .get_position_watermarks(address, TRAILING_STOP_STRATEGY)
+            .await?;
+        assert_eq!(watermarks.len(), 1);
+        assert!(watermarks[0].take_profit_fired);
+        assert!(watermarks[0].trailing_stop_fired);
+        assert!(!watermarks[0].stop_loss_fired);
+        assert!((watermarks[0].high_price - 8.8277e-8).abs() < 1e-11);
+
+        // a new high rearms the trailing stop
+        let config = TrailingStopConfig {
+            trailing_stop: Some(30.0),
+            ..Default::default()
+        };
+        let mut w = watermarks[0].clone();
+        assert!(config.evaluate(&mut w, 1e-7).is_empty());
+        assert!(!w.trailing_stop_fired);
+        assert_eq!(
+            config.evaluate(&mut w, 6e-8),
+            vec![ExitReason::TrailingStop]
+        );
+        assert!(TrailingStopConfig::default().validate().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_trailing_stop_saves_high_on_shutdown() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let (sender, _receiver) = alert_channel();
+        let ctx = mock.context(sender).await?;
+        let address = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x";
+        let mint = "7R4zU5pgHFxRQaNUhhCAPFXaSN6AWiheD6rRfkFJpump";
+        let rule: MonitorRule = toml::from_str(&format!(
+            r#"
+            address = "{}"
+            rule_type = "TrailingStop"
+            [config]
+            stop_loss = 50.0
+            "#,
+            address
+        ))?;
+        let start = 1_734_616_564;
+        let trade = |user: &str, secs: i64, sol: u64| TradeEventData {
+            mint: mint.to_string(),
+            sol_amount: 1_000_000_000,
+            token_amount: 1_000_000,
+            is_buy: true,
+            user: user.to_string(),
+            timestamp: start + secs,
+            virtual_sol_reserves: sol * 1_000_000_000,
+            virtual_token_reserves: 1_000_000_000 * 1_000_000,
+            real_sol_reserves: 0,
+            real_token_reserves: 0,
+        };
+        let event = |signature: &str, t: TradeEventData| StrategyEvent {
+            signature: signature.to_string(),
+            trades: vec![t],
+            ..Default::default()
+        };
+        let sctx = StrategyContext::replay(&rule, &ctx, start);
+        let mut strategy = TrailingStopStrategy::new(&rule, rule.strategy_config()?)?;
+        strategy.init(&sctx).await?;
+        strategy
+            .on_event(&sctx, &event("sig0", trade(address, 0, 30)))
+            .await?;
+        strategy
+            .on_event(&sctx, &event("sig1", trade("other", 10, 45)))
+            .await?;
+
+        // the new high is not written on every trade
+        let watermarks = ctx
+            .manager
+            .get_position_watermarks(address, TRAILING_STOP_STRATEGY)
+            .await?;
+        assert!((watermarks[0].high_price - 3e-8).abs() < 1e-11);
+        strategy.shutdown(&sctx).await?;
+        let watermarks = ctx
+            .manager
+            .get_position_watermarks(address, TRAILING_STOP_STRATEGY)
+            .await?;
+        assert!((watermarks[0].high_price - 4.5e-8).abs() < 1e-11);
+        sctx.unwatch_all();
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use serde::Deserialize;
+use tokio::{
+    sync::watch,
+    task::{JoinError, JoinHandle},
+    time::Instant,
+};
+use tracing::{error, info, warn};
+use validator::{Validate, ValidationError};
+
+use crate::models::{
+    ModelsManager, MonitorStatus, MONITOR_STATE_FAILED, MONITOR_STATE_RESTARTING,
+    MONITOR_STATE_RUNNING, MONITOR_STATE_STOPPED,
+};
+
+#[derive(Clone, Debug, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+#[validate(schema(function = "validate_supervisor_config"))]
+pub struct SupervisorConfig {
+    pub max_restarts: u32, // 最大重启次数, the monitor fails after that many restarts in a row
+    #[validate(range(min = 1))]
+    pub backoff_secs: u64, // wait before the first restart, doubled on every restart
+    pub max_backoff_secs: u64,
+    #[validate(range(min = 1))]
+    pub healthy_secs: u64, // a run lasting that long resets the restarts
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            backoff_secs: 1,
+            max_backoff_secs: 60,
+            healthy_secs: 300,
+        }
+    }
+}
+
+fn validate_supervisor_config(config: &SupervisorConfig) -> Result<(), ValidationError> {
+    if config.max_backoff_secs < config.backoff_secs {
+        return Err(ValidationError::new("max_backoff_secs")
+            .with_message("max_backoff_secs is less than backoff_secs".into()));
+    }
+    Ok(())
+}
+
+impl SupervisorConfig {
+    /// the wait before the `restarts`th restart
+    pub fn backoff(&self, restarts: u32) -> Duration {
+        let secs = self
+            .backoff_secs
+            .saturating_mul(1u64 << restarts.saturating_sub(1).min(32));
+        Duration::from_secs(secs.min(self.max_backoff_secs))
+    }
+}
+
+/// the state of a supervised task, persisted to `monitor_status` on every change
+pub struct StatusReporter {
+    manager: Arc<ModelsManager>,
+    status: Mutex<MonitorStatus>,
+}
+
+impl StatusReporter {
+    pub fn new(manager: Arc<ModelsManager>, status: MonitorStatus) -> Self {
+        Self {
+            manager,
+            status: Mutex::new(status),
+        }
+    }
+
+    pub fn get(&self) -> MonitorStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    async fn set(&self, state: &str, restarts: u32, last_error: Option<String>) {
+        let status = {
+            let mut s = self.status.lock().unwrap();
+            s.state = state.to_string();
+            s.restarts = restarts as i64;
+            if last_error.is_some() {
+                s.last_error = last_error;
+            }
+            s.clone()
+        };
+        if let Err(e) = self.manager.upsert_monitor_status(&status).await {
+            warn!(
+                "update monitor status error: {:?}, address: {}",
+                e, status.address
+            );
+        }
+    }
+}
+
+fn join_error_message(e: JoinError) -> String {
+    if !e.is_panic() {
+        return e.to_string();
+    }
+    let panic = e.into_panic();
+    let message = panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown".to_string());
+    format!("panicked: {}", message)
+}
+
+/// aborts the task once dropped, an aborted supervisor leaves no task running
+struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// run the task made by `make` until `stop` is set, restarting it with backoff when it fails,
+/// panics or ends. after `max_restarts` restarts in a row the task is given up as failed.
+pub async fn supervise<F, Fut>(
+    name: &str,
+    config: &SupervisorConfig,
+    status: &StatusReporter,
+    mut stop: watch::Receiver<bool>,
+    mut make: F,
+) where
+    F: FnMut(watch::Receiver<bool>) -> Fut,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let mut restarts = 0;
+    loop {
+        status.set(MONITOR_STATE_RUNNING, restarts, None).await;
+        let started = Instant::now();
+        // a panic only ends the inner task
+        let mut task = AbortOnDrop(tokio::spawn(make(stop.clone())));
+        let result = (&mut task.0).await;
+        let stopped = *stop.borrow();
+        if stopped {
+            status.set(MONITOR_STATE_STOPPED, restarts, None).await;
+            return;
+        }
+        let e = match result {
+            Ok(Ok(())) => "ended".to_string(),
+            Ok(Err(e)) => e.to_string(),
+            Err(e) => join_error_message(e),
+        };
+        if started.elapsed() >= Duration::from_secs(config.healthy_secs) {
+            restarts = 0;
+        }
+        if restarts >= config.max_restarts {
+            error!("{} failed after {} restarts: {}", name, restarts, e);
+            status.set(MONITOR_STATE_FAILED, restarts, Some(e)).await;
+            return;
+        }
+        restarts += 1;
+        let backoff = config.backoff(restarts);
+        warn!("{} {}, restart {} in {:?}", name, e, restarts, backoff);
+        status
+            .set(MONITOR_STATE_RESTARTING, restarts, Some(e))
+            .await;
+        let stopped = tokio::select! {
+            _ = tokio::time::sleep(backoff) => false,
+            _ = stop.wait_for(|s| *s) => true,
+        };
+        if stopped {
+            status.set(MONITOR_STATE_STOPPED, restarts, None).await;
+            return;
+        }
+        info!("restarting {}", name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::memory_manager;
+    use anyhow::anyhow;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_supervise_restarts() -> Result<()> {
+        let config = SupervisorConfig {
+            max_restarts: 2,
+            backoff_secs: 1,
+            max_backoff_secs: 1,
+            healthy_secs: 300,
+        };
+        assert!(config.validate().is_ok());
+        assert_eq!(config.backoff(1), Duration::from_secs(1));
+        assert_eq!(config.backoff(3), Duration::from_secs(1));
+        let slower = SupervisorConfig {
+            max_backoff_secs: 60,
+            ..config.clone()
+        };
+        assert_eq!(slower.backoff(3), Duration::from_secs(4));
+
+        let manager = memory_manager().await?;
+        let status = StatusReporter::new(
+            manager.clone(),
+            MonitorStatus {
+                address: "wallet".to_string(),
+                rule_type: "Buy".to_string(),
+                target: "wallet".to_string(),
+                ..Default::default()
+            },
+        );
+        let runs = Arc::new(AtomicU32::new(0));
+        let (_stop, stopped) = watch::channel(false);
+        let r = runs.clone();
+        supervise("monitor", &config, &status, stopped, move |_| {
+            let run = r.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if run == 0 {
+                    panic!("not yet implemented");
+                }
+                Err(anyhow!("subscription closed"))
+            }
+        })
+        .await;
+        // the first run and two restarts
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        let s = status.get();
+        assert_eq!(s.state, MONITOR_STATE_FAILED);
+        assert_eq!(s.restarts, 2);
+        assert_eq!(s.last_error.as_deref(), Some("subscription closed"));
+        let stored = manager.get_monitor_statuses().await?;
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].state, MONITOR_STATE_FAILED);
+        assert_eq!(stored[0].last_error, s.last_error);
+
+        // a stopped task is not restarted
+        let (stop, stopped) = watch::channel(false);
+        let r = runs.clone();
+        let stopping = async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let _ = stop.send(true);
+        };
+        let supervised = supervise("monitor", &config, &status, stopped, move |mut stopped| {
+            r.fetch_add(1, Ordering::SeqCst);
+            async move {
+                let _ = stopped.wait_for(|s| *s).await;
+                Ok(())
+            }
+        });
+        tokio::join!(supervised, stopping);
+        assert_eq!(runs.load(Ordering::SeqCst), 4);
+        assert_eq!(status.get().state, MONITOR_STATE_STOPPED);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_supervise_abort() -> Result<()> {
+        let manager = memory_manager().await?;
+        // held by the closure and by the running task
+        let alive = Arc::new(());
+        let a = alive.clone();
+        let (_stop, stopped) = watch::channel(false);
+        let supervisor = tokio::spawn(async move {
+            let status = StatusReporter::new(manager, MonitorStatus::default());
+            let config = SupervisorConfig::default();
+            supervise("monitor", &config, &status, stopped, move |_| {
+                let a = a.clone();
+                async move {
+                    let _a = a;
+                    std::future::pending::<()>().await;
+                    Ok(())
+                }
+            })
+            .await
+        });
+        while Arc::strong_count(&alive) < 3 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // a monitor overrunning the stop timeout is aborted, the task it runs ends with it
+        supervisor.abort();
+        assert!(supervisor.await.is_err_and(|e| e.is_cancelled()));
+        for _ in 0..100 {
+            if Arc::strong_count(&alive) == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(Arc::strong_count(&alive), 1);
+        Ok(())
+    }
+}
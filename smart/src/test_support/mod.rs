@@ -0,0 +1,488 @@
+//! in-process solana json rpc and pubsub servers, so the daemon pipeline can run offline in tests
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use axum::{extract::State, routing::post, Json, Router};
+use base64::{engine::general_purpose::STANDARD as base64, Engine};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use solana_sdk::{pubkey::Pubkey, transaction::Transaction};
+use tokio::{net::TcpListener, sync::Notify};
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+use tracing::debug;
+
+use crate::{
+    alert::AlertSender,
+    models::ModelsManager,
+    sol_client::{
+        pump::{PumpAccounts, BONDING_CURVE_DISCRIMINATOR, GLOBAL_DISCRIMINATOR},
+        TradeEventData, PUMP_PROGRAM_ID, TRADE_EVENT_DISCRIMINATOR,
+    },
+    strategies::{MonitorContext, StrategyRegistry},
+};
+
+/// load a json fixture from `src/test_support/fixtures`
+pub fn fixture(name: &str) -> Value {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/test_support/fixtures")
+        .join(name);
+    let s = std::fs::read_to_string(&path).expect("Failed to read fixture");
+    serde_json::from_str(&s).expect("Failed to parse fixture")
+}
+
+/// a migrated in-memory database
+pub async fn memory_manager() -> Result<Arc<ModelsManager>> {
+    Ok(Arc::new(ModelsManager::open_memory().await?))
+}
+
+/// the creator of the mock bonding curves
+pub const MOCK_CREATOR: &str = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU";
+/// the fee recipient of the mock pump.fun global account
+pub const MOCK_FEE_RECIPIENT: &str = "CebN5WGQ4jvEPvsVU4EoHEpgzq1VV7AbicfhtW4xC9iM";
+
+/// the account data of a live bonding curve with 700m tokens left at `virtual_sol_reserves`
+pub fn bonding_curve_data(virtual_sol_reserves: u64) -> Vec<u8> {
+    let mut data = BONDING_CURVE_DISCRIMINATOR.to_vec();
+    for v in [
+        1_000_000_000_000_000u64,
+        virtual_sol_reserves,
+        700_000_000_000_000,
+        0,
+        1_000_000_000_000_000,
+    ] {
+        data.extend_from_slice(&v.to_le_bytes());
+    }
+    data.push(0);
+    data.extend_from_slice(&Pubkey::from_str(MOCK_CREATOR).unwrap().to_bytes());
+    data
+}
+
+/// the account data of the pump.fun global account, up to the fee recipient
+pub fn pump_global_data() -> Vec<u8> {
+    let mut data = GLOBAL_DISCRIMINATOR.to_vec();
+    data.push(1);
+    data.extend_from_slice(&[0; 32]);
+    data.extend_from_slice(&Pubkey::from_str(MOCK_FEE_RECIPIENT).unwrap().to_bytes());
+    data
+}
+
+/// the `Program data` log line of a pump.fun `TradeEvent`
+pub fn trade_event_log(trade: &TradeEventData) -> String {
+    let key = |s: &str| Pubkey::from_str(s).expect("Invalid pubkey").to_bytes();
+    let mut data = TRADE_EVENT_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&key(&trade.mint));
+    data.extend_from_slice(&trade.sol_amount.to_le_bytes());
+    data.extend_from_slice(&trade.token_amount.to_le_bytes());
+    data.push(trade.is_buy as u8);
+    data.extend_from_slice(&key(&trade.user));
+    data.extend_from_slice(&trade.timestamp.to_le_bytes());
+    for v in [
+        trade.virtual_sol_reserves,
+        trade.virtual_token_reserves,
+        trade.real_sol_reserves,
+        trade.real_token_reserves,
+    ] {
+        data.extend_from_slice(&v.to_le_bytes());
+    }
+    format!("Program data: {}", base64.encode(data))
+}
+
+#[derive(Default)]
+struct MockState {
+    transactions: Mutex<HashMap<String, Value>>, // signature -> getTransaction result
+    signatures: Mutex<HashMap<String, Vec<(String, i64)>>>, // address -> (signature, block time), newest first
+    logs: Mutex<Vec<(String, Value)>>, // (mentioned address, logsNotification result)
+    logs_notify: Notify,
+    accounts: Mutex<HashMap<String, Vec<u8>>>, // address -> getAccountInfo data
+    sent: Mutex<Vec<String>>,                  // signatures of sendTransaction, in order
+    statuses: Mutex<HashMap<String, Value>>,   // signature -> getSignatureStatuses value
+    block_height: Mutex<u64>,
+}
+
+pub struct MockSolana {
+    pub rpc_url: String,
+    pub wss_url: String,
+    state: Arc<MockState>,
+}
+
+impl MockSolana {
+    pub async fn start() -> Result<Self> {
+        let state = Arc::new(MockState::default());
+
+        let rpc_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let rpc_addr = rpc_listener.local_addr()?;
+        let app = Router::new()
+            .route("/", post(handle_rpc))
+            .with_state(state.clone());
+        tokio::spawn(async move {
+            axum::serve(rpc_listener, app).await.ok();
+        });
+
+        let wss_listener = TcpListener::bind("127.0.0.1:0").await?;
+        let wss_addr = wss_listener.local_addr()?;
+        let pubsub_state = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, peer)) = wss_listener.accept().await {
+                let state = pubsub_state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_pubsub(stream, state).await {
+                        debug!("mock pubsub connection {} closed: {:?}", peer, e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            rpc_url: format!("http://{}", rpc_addr),
+            wss_url: format!("ws://{}", wss_addr),
+            state,
+        })
+    }
+
+    /// serve `tx` as the `getTransaction` result of `signature`
+    pub fn add_transaction(&self, signature: &str, tx: Value) {
+        self.state
+            .transactions
+            .lock()
+            .unwrap()
+            .insert(signature.to_string(), tx);
+    }
+
+    /// serve a `getTransaction` fixture, returns its signature
+    pub fn add_transaction_fixture(&self, name: &str) -> String {
+        let tx = fixture(name);
+        let signature = tx["transaction"]["signatures"][0]
+            .as_str()
+            .expect("Fixture has no signature")
+            .to_string();
+        self.add_transaction(&signature, tx);
+        signature
+    }
+
+    /// serve `signatures` (signature, block time), newest first, from `getSignaturesForAddress` of `address`
+    pub fn add_signatures(&self, address: &str, signatures: &[(&str, i64)]) {
+        self.state.signatures.lock().unwrap().insert(
+            address.to_string(),
+            signatures
+                .iter()
+                .map(|(s, t)| (s.to_string(), *t))
+                .collect(),
+        );
+    }
+
+    /// serve `data` as the `getAccountInfo` data of `address`
+    pub fn add_account(&self, address: &str, data: Vec<u8>) {
+        self.state
+            .accounts
+            .lock()
+            .unwrap()
+            .insert(address.to_string(), data);
+    }
+
+    /// serve the bonding curve account of `mint` at `virtual_sol_reserves` and the global account
+    pub fn add_bonding_curve(&self, mint: &str, virtual_sol_reserves: u64) {
+        let accounts = PumpAccounts::new(&Pubkey::from_str(mint).expect("Invalid mint"));
+        self.add_account(
+            &accounts.bonding_curve.to_string(),
+            bonding_curve_data(virtual_sol_reserves),
+        );
+        self.add_account(&accounts.global.to_string(), pump_global_data());
+    }
+
+    /// the signatures of the sent transactions, in order
+    pub fn sent(&self) -> Vec<String> {
+        self.state.sent.lock().unwrap().clone()
+    }
+
+    /// land the transaction of `signature`, confirmed with `logs`
+    pub fn confirm_transaction(&self, signature: &str, logs: Vec<String>) {
+        let mut tx = fixture("pump_buy_tx.json");
+        tx["transaction"]["signatures"] = json!([signature]);
+        tx["meta"]["logMessages"] = json!(logs);
+        self.add_transaction(signature, tx);
+        self.state.statuses.lock().unwrap().insert(
+            signature.to_string(),
+            json!({
+                "slot": 1,
+                "confirmations": null,
+                "status": {"Ok": null},
+                "err": null,
+                "confirmationStatus": "confirmed",
+            }),
+        );
+    }
+
+    pub fn set_block_height(&self, height: u64) {
+        *self.state.block_height.lock().unwrap() = height;
+    }
+
+    /// queue a `logsNotification` result for subscribers mentioning `address`.
+    ///
+    /// notifications are kept, so a subscription opened later still receives them in order.
+    pub fn push_logs(&self, address: &str, result: Value) {
+        self.state
+            .logs
+            .lock()
+            .unwrap()
+            .push((address.to_string(), result));
+        self.state.logs_notify.notify_waiters();
+    }
+
+    pub fn push_logs_fixture(&self, address: &str, name: &str) {
+        self.push_logs(address, fixture(name));
+    }
+
+    /// a monitor context on the mock endpoints with an in-memory database
+    pub async fn context(&self, alerts: AlertSender) -> Result<MonitorContext> {
+        Ok(MonitorContext {
+            wss_url: self.wss_url.clone(),
+            rpc_url: self.rpc_url.clone(),
+            manager: memory_manager().await?,
+            alerts,
+            registry: Arc::new(StrategyRegistry::default()),
+            // the sender is dropped, the context never shuts down
+            shutdown: tokio::sync::watch::channel(false).1,
+            executor: None,
+        })
+    }
+}
+
+async fn handle_rpc(State(state): State<Arc<MockState>>, Json(req): Json<Value>) -> Json<Value> {
+    let id = req.get("id").cloned().unwrap_or(Value::Null);
+    let method = req
+        .get("method")
+        .and_then(|m| m.as_str())
+        .unwrap_or_default();
+    let result = match method {
+        "getTransaction" => {
+            let sig = req["params"][0].as_str().unwrap_or_default();
+            state
+                .transactions
+                .lock()
+                .unwrap()
+                .get(sig)
+                .cloned()
+                .unwrap_or(Value::Null)
+        }
+        "getSignaturesForAddress" => {
+            let address = req["params"][0].as_str().unwrap_or_default();
+            let before = req["params"][1]["before"].as_str();
+            let limit = req["params"][1]["limit"].as_u64().unwrap_or(1000) as usize;
+            let signatures = state.signatures.lock().unwrap();
+            let all = signatures
+                .get(address)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let start = before
+                .and_then(|b| all.iter().position(|(s, _)| s == b))
+                .map_or(0, |i| i + 1);
+            all.iter()
+                .skip(start)
+                .take(limit)
+                .map(|(signature, block_time)| {
+                    json!({
+                        "signature": signature,
+                        "slot": 0,
+                        "err": null,
+                        "memo": null,
+                        "blockTime": block_time,
+                        "confirmationStatus": "confirmed",
+                    })
+                })
+                .collect()
+        }
+        "getAccountInfo" => {
+            let address = req["params"][0].as_str().unwrap_or_default();
+            let value = state.accounts.lock().unwrap().get(address).map(|data| {
+                json!({
+                    "data": [base64.encode(data), "base64"],
+                    "executable": false,
+                    "lamports": 1_000_000,
+                    "owner": PUMP_PROGRAM_ID,
+                    "rentEpoch": 0,
+                    "space": data.len(),
+                })
+            });
+            json!({"context": {"slot": 1}, "value": value})
+        }
+        "getLatestBlockhash" => json!({
+            "context": {"slot": 1},
+            "value": {
+                "blockhash": "4ruaGCyaofHWGxPFXFVjuEJCdfBGZ2wCtEx6LzdzVqtV",
+                "lastValidBlockHeight": 100,
+            },
+        }),
+        "getBlockHeight" => json!(*state.block_height.lock().unwrap()),
+        "sendTransaction" => {
+            let data = req["params"][0].as_str().unwrap_or_default();
+            let tx: Option<Transaction> = base64
+                .decode(data)
+                .ok()
+                .and_then(|bytes| bincode::deserialize(&bytes).ok());
+            let Some(tx) = tx else {
+                return Json(json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {"code": -32602, "message": "invalid transaction"},
+                }));
+            };
+            let signature = tx.signatures[0].to_string();
+            state.sent.lock().unwrap().push(signature.clone());
+            json!(signature)
+        }
+        "getSignatureStatuses" => {
+            let statuses = state.statuses.lock().unwrap();
+            let value: Vec<Value> = req["params"][0]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|s| {
+                    let s = s.as_str().unwrap_or_default();
+                    statuses.get(s).cloned().unwrap_or(Value::Null)
+                })
+                .collect();
+            json!({"context": {"slot": 1}, "value": value})
+        }
+        "getVersion" => json!({"solana-core": "2.1.6", "feature-set": 0}),
+        _ => {
+            return Json(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": -32601, "message": format!("Method not found: {}", method)},
+            }))
+        }
+    };
+    Json(json!({"jsonrpc": "2.0", "id": id, "result": result}))
+}
+
+async fn handle_pubsub(stream: tokio::net::TcpStream, state: Arc<MockState>) -> Result<()> {
+    let ws = accept_async(stream).await?;
+    let (mut write, mut read) = ws.split();
+
+    // wait for the logsSubscribe request
+    let (address, sub_id) = loop {
+        let Some(msg) = read.next().await else {
+            return Ok(());
+        };
+        if let Message::Text(text) = msg? {
+            let req: Value = serde_json::from_str(&text)?;
+            if req["method"] == "logsSubscribe" {
+                let address = req["params"][0]["mentions"][0]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                let sub_id = 1;
+                let resp = json!({"jsonrpc": "2.0", "result": sub_id, "id": req["id"]});
+                write.send(Message::text(resp.to_string())).await?;
+                break (address, sub_id);
+            }
+        }
+    };
+
+    let mut cursor = 0;
+    loop {
+        let notified = state.logs_notify.notified();
+        let pending: Vec<Value> = {
+            let logs = state.logs.lock().unwrap();
+            let pending = logs[cursor..]
+                .iter()
+                .filter(|(a, _)| *a == address)
+                .map(|(_, v)| v.clone())
+                .collect();
+            cursor = logs.len();
+            pending
+        };
+        for result in pending {
+            let msg = json!({
+                "jsonrpc": "2.0",
+                "method": "logsNotification",
+                "params": {"result": result, "subscription": sub_id},
+            });
+            write.send(Message::text(msg.to_string())).await?;
+        }
+        tokio::select! {
+            _ = notified => {}
+            msg = read.next() => {
+                // client went away
+                if !matches!(msg, Some(Ok(_))) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sol_client::{client::SolanaMonitor, parse_program_data};
+    use solana_client::rpc_response::{Response, RpcLogsResponse};
+    use solana_transaction_status::option_serializer::OptionSerializer;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_mock_logs_subscribe_and_get_tx() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let address = "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x";
+        mock.push_logs_fixture(address, "pump_buy_logs.json");
+        let signature = mock.add_transaction_fixture("pump_buy_tx.json");
+
+        let (sender, mut receiver) = mpsc::channel::<Response<RpcLogsResponse>>(10);
+        let client = SolanaMonitor::new(&mock.wss_url, &mock.rpc_url);
+        tokio::spawn(async move { client.start_log_subscribe(address, sender).await });
+
+        let log = receiver.recv().await.expect("no logs received");
+        assert_eq!(log.context.slot, 308456789);
+        let log = log.value;
+        assert_eq!(log.signature, signature);
+        let data = log
+            .logs
+            .iter()
+            .find(|l| l.starts_with("Program data: "))
+            .expect("no program data");
+        let events = parse_program_data(data)?;
+        assert_eq!(events[0].data.user, address);
+
+        let client = SolanaMonitor::new(&mock.wss_url, &mock.rpc_url);
+        let meta = client.get_tx(&log.signature).await?;
+        let OptionSerializer::Some(balances) = meta.post_token_balances else {
+            panic!("no post token balances");
+        };
+        assert!(balances
+            .iter()
+            .any(|b| b.owner == OptionSerializer::Some(address.to_string())));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mock_jupiter_swap() -> Result<()> {
+        let mock = MockSolana::start().await?;
+        let signature = mock.add_transaction_fixture("jupiter_swap_tx.json");
+
+        let client = SolanaMonitor::new(&mock.wss_url, &mock.rpc_url);
+        let swap = client
+            .get_jupiter_swap(&signature)
+            .await?
+            .expect("no jupiter swap");
+        assert_eq!(swap.user, "ASxMiMb1AJGTU4AduPNB2CGqT1TiDqWkLvy7oCUnzw5x");
+        assert_eq!(
+            swap.input_mint,
+            "So11111111111111111111111111111111111111112"
+        );
+        assert_eq!(swap.input_amount, 1_000_000_000);
+        assert_eq!(
+            swap.output_mint,
+            "DezXAZ8z7PnrnRJjz2wXBoRgixCCWcnWFpSGJN2B263"
+        );
+        assert_eq!(swap.output_amount, 5_200_000_000_000);
+        assert_eq!(swap.amms.len(), 2);
+        assert_eq!(swap.route.map(|r| r.slippage_bps), Some(50));
+        Ok(())
+    }
+}
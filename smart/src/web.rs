@@ -1,4 +1,4 @@
-use crate::config::get_global_config;
+use crate::{config::get_global_config, shutdown::shutdown_signal};
 use anyhow::Result;
 use axum::{
     error_handling::HandleErrorLayer,
@@ -10,7 +10,7 @@ use axum::{
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
-use tokio::{net::TcpListener, signal};
+use tokio::net::TcpListener;
 use tower::{BoxError, ServiceBuilder};
 use tower_http::trace::TraceLayer;
 use tracing::info;
@@ -65,7 +65,7 @@ pub async fn start_server() -> Result<()> {
     info!("Starting web server at {}", addr.local_addr()?);
     info!("add account: /api/v1/add_account?address=xxx");
     axum::serve(addr, app)
-        .with_graceful_shutdown(shoutdown_signal())
+        .with_graceful_shutdown(shutdown_signal())
         .await
         .unwrap();
 
@@ -80,26 +80,3 @@ struct AddAccount {
 async fn add_account(input: Query<AddAccount>) -> impl IntoResponse {
     todo!()
 }
-
-async fn shoutdown_signal() {
-    let ctl_c = async {
-        signal::ctrl_c()
-            .await
-            .expect("Failed to listen for ctrl-c signal");
-    };
-    #[cfg(unix)]
-    let terminate = async {
-        signal::unix::signal(signal::unix::SignalKind::terminate())
-            .expect("Failed to listen for terminate signal")
-            .recv()
-            .await;
-    };
-    #[cfg(not(unix))]
-    let terminate = std::future::pending::<()>();
-    tokio::select! {
-        _ = ctl_c => {},
-        _ = terminate => {},
-    }
-
-    info!("signal received, shutting down");
-}